@@ -20,4 +20,5 @@ struct RenderParams {
     shadow_mapping_enabled: i32,
     realistic_sky: i32,
     grid_enabled: i32,
+    sea_level: f32,
 }
\ No newline at end of file
@@ -79,7 +79,8 @@ fn frag(@location(0) in_normal: vec3<f32>,
     }
 
     c = mix(params.sand_col, c, smoothstep(-5.0, 0.0, in_wpos.z));
-    c = mix(params.sea_col, c, smoothstep(-25.0, -20.0, in_wpos.z));
+    let sea_floor: f32 = params.sea_level - 10.0;
+    c = mix(params.sea_col, c, smoothstep(sea_floor - 5.0, sea_floor, in_wpos.z));
 
     let final_rgb: vec3<f32> = render(params.sun,
                                       params.cam_dir.xyz,
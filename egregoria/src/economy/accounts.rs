@@ -0,0 +1,95 @@
+use crate::economy::{Account, Government, Money, Workers, WORKER_CONSUMPTION_PER_SECOND};
+use crate::souls::goods_company::GoodsCompany;
+use crate::souls::human::HumanDecision;
+use crate::utils::migration::add_missing;
+use crate::utils::time::{GameTime, SECONDS_PER_HOUR};
+use crate::SoulID;
+use hecs::World;
+use resources::Resources;
+use serde::{Deserialize, Serialize};
+
+/// Paid by companies to each of their workers.
+/// The prices from `calculate_prices` cover the wages of a fully staffed company.
+pub const WORKER_WAGE_PER_SECOND: Money = WORKER_CONSUMPTION_PER_SECOND;
+/// Savings of a household moving in, enough to eat for a few days while looking for a job
+pub const HOUSEHOLD_START_MONEY: Money = Money::new_base(100);
+
+/// Revenue and expenses of a company during the current day and the previous one
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CompanyProfit {
+    pub revenue: Money,
+    pub expenses: Money,
+    pub last_revenue: Money,
+    pub last_expenses: Money,
+}
+
+impl CompanyProfit {
+    pub fn last_profit(&self) -> Money {
+        self.last_revenue - self.last_expenses
+    }
+
+    pub(crate) fn end_day(&mut self) {
+        self.last_revenue = std::mem::take(&mut self.revenue);
+        self.last_expenses = std::mem::take(&mut self.expenses);
+    }
+}
+
+/// Adds `amount` to the account of `soul`, or removes it if negative.
+/// Returns false if the soul has no account, so the money comes from or goes elsewhere.
+pub(crate) fn transfer(world: &World, soul: SoulID, amount: Money) -> bool {
+    let Ok(mut account) = world.get::<&mut Account>(soul.0) else {
+        return false;
+    };
+    account.money += amount;
+
+    if let Ok(mut profit) = world.get::<&mut CompanyProfit>(soul.0) {
+        if amount > Money::ZERO {
+            profit.revenue += amount;
+        } else {
+            profit.expenses -= amount;
+        }
+    }
+    true
+}
+
+/// Companies pay the wages of their workers every hour, the income tax is withheld from them
+#[profiling::function]
+pub fn wages_system(world: &mut World, resources: &mut Resources) {
+    if !resources
+        .get::<GameTime>()
+        .unwrap()
+        .tick(SECONDS_PER_HOUR as u32)
+    {
+        return;
+    }
+    let mut gvt = resources.get_mut::<Government>().unwrap();
+    let wage = WORKER_WAGE_PER_SECOND * SECONDS_PER_HOUR as i64;
+
+    let mut paid = vec![];
+    for (_, (_, workers, account, profit)) in
+        world.query_mut::<(&GoodsCompany, &Workers, &mut Account, &mut CompanyProfit)>()
+    {
+        let wages = wage * workers.0.len() as i64;
+        account.money -= wages;
+        profit.expenses += wages;
+        paid.extend_from_slice(&workers.0);
+    }
+
+    for worker in paid {
+        let tax = gvt.collect_income_tax(wage);
+        transfer(world, worker, wage - tax);
+    }
+}
+
+/// Companies from older saves don't have an account yet
+pub(crate) fn migrate_company_accounts(world: &mut World) {
+    add_missing::<GoodsCompany, _>(world, Account::default);
+}
+
+pub(crate) fn migrate_company_profits(world: &mut World) {
+    add_missing::<GoodsCompany, _>(world, CompanyProfit::default);
+}
+
+pub(crate) fn migrate_household_accounts(world: &mut World) {
+    add_missing::<HumanDecision, _>(world, || Account::new(HOUSEHOLD_START_MONEY));
+}
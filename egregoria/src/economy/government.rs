@@ -1,37 +1,189 @@
-use crate::economy::Money;
-use crate::engine_interaction::WorldCommand;
-use crate::map::{LanePattern, MapProject};
+use crate::economy::{Debt, Ledger, LedgerCategory, Money};
+use crate::engine_interaction::{UndoHistory, WorldCommand};
+use crate::map::procgen::city_size;
+use crate::map::{LaneKind, LanePattern, Map, MapProject, Road, RoadSegmentKind};
+use crate::transportation::bus::BusLines;
+use crate::transportation::train_line::TrainLines;
+use crate::utils::time::{GameTime, DAYS_PER_MONTH, HOURS_PER_DAY, SECONDS_PER_HOUR};
 use crate::{BuildingKind, Egregoria, GoodsCompanyRegistry};
+use common::saveload::{Bincode, Encoder};
+use geom::Vec2;
+use hecs::World;
+use resources::Resources;
 use serde::{Deserialize, Serialize};
 
+/// Daily maintenance cost of a kilometer of road lane
+const ROAD_MAINTENANCE_PER_KM: Money = Money::new_base(30);
+/// Daily maintenance cost of a kilometer of rail
+const RAIL_MAINTENANCE_PER_KM: Money = Money::new_base(80);
+
+/// Extra cost per meter of lane of the parts of a road that are bridges
+const BRIDGE_COST_PER_M: f32 = 0.15;
+/// Extra cost per meter of lane of the parts of a road that are tunnels
+const TUNNEL_COST_PER_M: f32 = 0.3;
+/// Cost of moving a cubic meter of ground when terraforming, in base units
+const TERRAFORM_COST_PER_M3: f32 = 0.01;
+/// Cost of generating a city per square kilometer it covers, in base units
+const CITY_COST_PER_KM2: f64 = 6000.0;
+
 #[derive(Serialize, Deserialize)]
 pub struct Government {
     pub money: Money,
+    pub taxes: TaxRates,
+    pub ledger: Ledger,
+    pub debt: Debt,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct TaxRates {
+    /// Fraction of the wages paid by the companies
+    pub income: f32,
+    /// Fraction of the value of the goods traded inside the city
+    pub sales: f32,
+    /// Paid every day by each house and company
+    pub property: Money,
+}
+
+impl Default for TaxRates {
+    fn default() -> Self {
+        Self {
+            income: 0.1,
+            sales: 0.05,
+            property: Money::new_base(24),
+        }
+    }
 }
 
 impl Default for Government {
     fn default() -> Self {
         Self {
             money: Money::new_base(150_000),
+            taxes: TaxRates::default(),
+            ledger: Ledger::default(),
+            debt: Debt::default(),
         }
     }
 }
 
+/// Saves from before taxes only have the money
+pub(crate) fn migrate_government_finances(mut data: Vec<u8>) -> Option<Vec<u8>> {
+    data.extend(Bincode::encode(&TaxRates::default()).ok()?);
+    data.extend(Bincode::encode(&Ledger::default()).ok()?);
+    Some(data)
+}
+
+pub(crate) fn migrate_government_debt(mut data: Vec<u8>) -> Option<Vec<u8>> {
+    data.extend(Bincode::encode(&Debt::default()).ok()?);
+    Some(data)
+}
+
+fn scale(m: Money, factor: f64) -> Money {
+    Money::new_cents((m.cents() as f64 * factor) as i64)
+}
+
 impl Government {
+    pub fn earn(&mut self, category: LedgerCategory, amount: Money) {
+        self.money += amount;
+        self.ledger.record(category, amount);
+    }
+
+    pub fn spend(&mut self, category: LedgerCategory, amount: Money) {
+        self.money -= amount;
+        self.ledger.record(category, Money::ZERO - amount);
+    }
+
+    /// Taxes a trade between two souls of the city worth `value`, returns the tax
+    pub fn collect_sales_tax(&mut self, value: Money) -> Money {
+        let tax = scale(value, self.taxes.sales as f64);
+        self.earn(LedgerCategory::SalesTax, tax);
+        tax
+    }
+
+    /// Taxes the wages paid by a company, returns the tax
+    pub fn collect_income_tax(&mut self, wages: Money) -> Money {
+        let tax = scale(wages, self.taxes.income as f64);
+        self.earn(LedgerCategory::IncomeTax, tax);
+        tax
+    }
+
+    /// Daily maintenance cost of all the roads and rails of the map
+    pub fn maintenance_cost(map: &Map) -> Money {
+        let mut road = 0.0;
+        let mut rail = 0.0;
+        for lane in map.lanes().values() {
+            let length = lane.points.length() as f64;
+            match lane.kind {
+                LaneKind::Rail => rail += length,
+                _ => road += length,
+            }
+        }
+        scale(ROAD_MAINTENANCE_PER_KM, road / 1000.0)
+            + scale(RAIL_MAINTENANCE_PER_KM, rail / 1000.0)
+    }
+
     pub fn action_cost(action: &WorldCommand, goria: &Egregoria) -> Money {
+        match *action {
+            WorldCommand::Undo(id) => {
+                let history = goria.read::<UndoHistory>();
+                return history
+                    .cost(id, false)
+                    .map_or(Money::ZERO, |c| Money::ZERO - c);
+            }
+            WorldCommand::Redo(id) => {
+                return goria
+                    .read::<UndoHistory>()
+                    .cost(id, true)
+                    .unwrap_or(Money::ZERO);
+            }
+            _ => {}
+        }
         Money::new_base(match action {
             WorldCommand::MapBuildHouse(_) => 100,
             WorldCommand::AddTrain { n_wagons, .. } => 1000 + 100 * (*n_wagons as i64),
-            WorldCommand::MapMakeConnection { from, to, pat, .. } => {
-                Self::connection_cost(from, to, pat)
-            }
+            WorldCommand::MapMakeConnection {
+                from,
+                to,
+                inter,
+                pat,
+            } => Self::connection_cost(&goria.map(), from, to, *inter, pat),
             WorldCommand::MapMakeMultipleConnections(ref projs, ref links) => {
+                let map = goria.map();
                 let mut total = 0;
-                for (from, to, _, pat) in links.iter() {
-                    total += Self::connection_cost(&projs[*from], &projs[*to], pat);
+                for (from, to, inter, pat) in links.iter() {
+                    total += Self::connection_cost(&map, &projs[*from], &projs[*to], *inter, pat);
                 }
                 total
             }
+            WorldCommand::Terraform {
+                kind,
+                center,
+                radius,
+                amount,
+            } => {
+                let map = goria.map();
+                let edits = map.terrain.terraform(*kind, *center, *radius, *amount);
+                (map.terrain.edits_volume(&edits) * TERRAFORM_COST_PER_M3).ceil() as i64
+            }
+            WorldCommand::MapGenerateCity { size, .. } => {
+                let radius = city_size(*size) as f64 * 0.5 / 1000.0;
+                (CITY_COST_PER_KM2 * std::f64::consts::PI * radius * radius).ceil() as i64
+            }
+            WorldCommand::AddBusLine(desc) => 1000 + 500 * desc.n_buses as i64,
+            WorldCommand::EditBusLine(id, desc) => {
+                let cur = goria
+                    .read::<BusLines>()
+                    .get(*id)
+                    .map_or(0, |l| l.desc.n_buses);
+                500 * (desc.n_buses.saturating_sub(cur) as i64)
+            }
+            WorldCommand::AddTrainLine(desc) => 2000 + 1500 * desc.n_trains as i64,
+            WorldCommand::EditTrainLine(id, desc) => {
+                let cur = goria
+                    .read::<TrainLines>()
+                    .get(*id)
+                    .map_or(0, |l| l.desc.n_trains);
+                1500 * (desc.n_trains.saturating_sub(cur) as i64)
+            }
             WorldCommand::MapBuildSpecialBuilding { kind: x, .. } => match x {
                 BuildingKind::GoodsCompany(x) => {
                     goria.read::<GoodsCompanyRegistry>().descriptions[*x].price
@@ -44,9 +196,51 @@ impl Government {
         })
     }
 
-    fn connection_cost(p1: &MapProject, p2: &MapProject, pat: &LanePattern) -> i64 {
+    /// Bridges and tunnels cost more than roads on the ground
+    fn connection_cost(
+        map: &Map,
+        p1: &MapProject,
+        p2: &MapProject,
+        inter: Option<Vec2>,
+        pat: &LanePattern,
+    ) -> i64 {
         let dist = p1.pos.distance(p2.pos);
-        50 + ((0.03 * dist) as i64).max(1)
+        let segment = match inter {
+            Some(x) => RoadSegmentKind::from_elbow(p1.pos.xy(), p2.pos.xy(), x),
+            None => RoadSegmentKind::Straight,
+        };
+        let points = Road::segment_points(p1.pos, p2.pos, segment, false);
+        let (bridge, tunnel) = map.terrain.structures_length(&points);
+        let structures = (BRIDGE_COST_PER_M * bridge + TUNNEL_COST_PER_M * tunnel) as i64;
+
+        50 + (((0.03 * dist) as i64).max(1) + structures)
             * (pat.lanes_forward.len() + pat.lanes_backward.len()) as i64
     }
 }
+
+/// Collects the property tax and pays for the maintenance every in-game hour.
+/// Also closes the ledger month when a new one starts.
+#[profiling::function]
+pub fn government_finances_system(_: &mut World, resources: &mut Resources) {
+    let time = *resources.get::<GameTime>().unwrap();
+    let mut gvt = resources.get_mut::<Government>().unwrap();
+
+    gvt.ledger.advance(time.daytime.day / DAYS_PER_MONTH);
+
+    if !time.tick(SECONDS_PER_HOUR as u32) {
+        return;
+    }
+
+    let map = resources.get::<Map>().unwrap();
+
+    let n_properties = map
+        .buildings()
+        .values()
+        .filter(|b| matches!(b.kind, BuildingKind::House | BuildingKind::GoodsCompany(_)))
+        .count() as i64;
+    let property_tax = gvt.taxes.property * n_properties / HOURS_PER_DAY as i64;
+    gvt.earn(LedgerCategory::PropertyTax, property_tax);
+
+    let maintenance = Government::maintenance_cost(&map) / HOURS_PER_DAY as i64;
+    gvt.spend(LedgerCategory::Maintenance, maintenance);
+}
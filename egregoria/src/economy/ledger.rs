@@ -0,0 +1,132 @@
+use crate::economy::Money;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Number of closed months kept in the ledger history
+pub const LEDGER_HISTORY_SIZE: usize = 24;
+
+/// Where the government money comes from or goes to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LedgerCategory {
+    IncomeTax,
+    SalesTax,
+    PropertyTax,
+    Exports,
+    Imports,
+    WorkerConsumption,
+    Maintenance,
+    Construction,
+    Interest,
+    /// Money borrowed minus money repaid
+    Loans,
+}
+
+impl LedgerCategory {
+    pub const ALL: [LedgerCategory; 10] = [
+        LedgerCategory::IncomeTax,
+        LedgerCategory::SalesTax,
+        LedgerCategory::PropertyTax,
+        LedgerCategory::Exports,
+        LedgerCategory::Imports,
+        LedgerCategory::WorkerConsumption,
+        LedgerCategory::Maintenance,
+        LedgerCategory::Construction,
+        LedgerCategory::Interest,
+        LedgerCategory::Loans,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LedgerCategory::IncomeTax => "Income tax",
+            LedgerCategory::SalesTax => "Sales tax",
+            LedgerCategory::PropertyTax => "Property tax",
+            LedgerCategory::Exports => "Exports",
+            LedgerCategory::Imports => "Imports",
+            LedgerCategory::WorkerConsumption => "Worker consumption",
+            LedgerCategory::Maintenance => "Maintenance",
+            LedgerCategory::Construction => "Construction",
+            LedgerCategory::Interest => "Interest",
+            LedgerCategory::Loans => "Loans",
+        }
+    }
+}
+
+/// Net amount of each category during one month, positive amounts are income
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LedgerMonth {
+    pub month: i32,
+    pub entries: BTreeMap<LedgerCategory, Money>,
+}
+
+impl LedgerMonth {
+    pub fn new(month: i32) -> Self {
+        Self {
+            month,
+            entries: Default::default(),
+        }
+    }
+
+    pub fn get(&self, category: LedgerCategory) -> Money {
+        self.entries.get(&category).copied().unwrap_or(Money::ZERO)
+    }
+
+    pub fn income(&self) -> Money {
+        self.entries
+            .values()
+            .filter(|&&m| m > Money::ZERO)
+            .copied()
+            .sum()
+    }
+
+    pub fn expenses(&self) -> Money {
+        let spent: Money = self
+            .entries
+            .values()
+            .filter(|&&m| m < Money::ZERO)
+            .copied()
+            .sum();
+        Money::ZERO - spent
+    }
+
+    pub fn net(&self) -> Money {
+        self.entries.values().copied().sum()
+    }
+}
+
+/// Record of every government transaction, grouped by category and by month
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Ledger {
+    pub current: LedgerMonth,
+    /// Closed months, oldest first
+    pub history: VecDeque<LedgerMonth>,
+}
+
+impl Ledger {
+    pub fn record(&mut self, category: LedgerCategory, amount: Money) {
+        if amount == Money::ZERO {
+            return;
+        }
+        *self.current.entries.entry(category).or_default() += amount;
+    }
+
+    /// Closes the current month if `month` is a new one.
+    /// Months without any transaction are not kept.
+    pub fn advance(&mut self, month: i32) {
+        if self.current.month == month {
+            return;
+        }
+        let closed = std::mem::replace(&mut self.current, LedgerMonth::new(month));
+        if closed.entries.is_empty() {
+            return;
+        }
+        self.history.push_back(closed);
+        if self.history.len() > LEDGER_HISTORY_SIZE {
+            self.history.pop_front();
+        }
+    }
+
+    /// The current month followed by the closed months, most recent first
+    pub fn months(&self) -> impl Iterator<Item = &LedgerMonth> {
+        std::iter::once(&self.current).chain(self.history.iter().rev())
+    }
+}
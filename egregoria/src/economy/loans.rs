@@ -0,0 +1,192 @@
+use crate::economy::{CompanyProfit, Government, LedgerCategory, Money};
+use crate::souls::goods_company::{close_company, GoodsCompany};
+use crate::utils::time::{GameTime, SECONDS_PER_DAY};
+use crate::{Egregoria, SoulID};
+use serde::{Deserialize, Serialize};
+
+/// Days in a row with a negative balance after which a company or the government goes bankrupt
+pub const BANKRUPTCY_DAYS: u32 = 3;
+
+pub const GOVERNMENT_CREDIT_LIMIT: Money = Money::new_base(500_000);
+pub const GOVERNMENT_DAILY_RATE: f32 = 0.001;
+
+pub const COMPANY_CREDIT_LIMIT: Money = Money::new_base(20_000);
+pub const COMPANY_DAILY_RATE: f32 = 0.003;
+/// Money a company starts with
+pub const COMPANY_START_MONEY: Money = Money::new_base(5_000);
+/// Companies repay their loans with what they have above this
+const COMPANY_RESERVE: Money = Money::new_base(2_000);
+
+/// Money lent to a company or to the government
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Loan {
+    /// Amount left to repay
+    pub principal: Money,
+    /// Interest paid every day, as a fraction of the principal
+    pub daily_rate: f32,
+}
+
+impl Loan {
+    pub fn daily_interest(&self) -> Money {
+        Money::new_cents((self.principal.cents() as f64 * self.daily_rate as f64) as i64)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Debt {
+    /// Oldest first, they are repaid in that order
+    pub loans: Vec<Loan>,
+    /// Days in a row spent with a negative balance
+    pub insolvent_days: u32,
+}
+
+impl Debt {
+    pub fn total(&self) -> Money {
+        self.loans.iter().map(|l| l.principal).sum()
+    }
+
+    pub fn daily_interest(&self) -> Money {
+        self.loans.iter().map(Loan::daily_interest).sum()
+    }
+
+    /// How much can still be borrowed under `limit`
+    pub fn available(&self, limit: Money) -> Money {
+        (limit - self.total()).max(Money::ZERO)
+    }
+
+    pub fn borrow(&mut self, amount: Money, daily_rate: f32) {
+        self.loans.push(Loan {
+            principal: amount,
+            daily_rate,
+        });
+    }
+
+    /// Repays up to `amount`, returns how much was actually repaid. A negative amount repays
+    /// nothing.
+    pub fn repay(&mut self, amount: Money) -> Money {
+        let amount = amount.max(Money::ZERO);
+        let mut left = amount;
+        for loan in &mut self.loans {
+            let paid = left.min(loan.principal);
+            loan.principal -= paid;
+            left -= paid;
+        }
+        self.loans.retain(|l| l.principal > Money::ZERO);
+        amount - left
+    }
+
+    pub fn is_bankrupt(&self) -> bool {
+        self.insolvent_days >= BANKRUPTCY_DAYS
+    }
+
+    fn end_day(&mut self, balance: Money) {
+        if balance < Money::ZERO {
+            self.insolvent_days += 1;
+        } else {
+            self.insolvent_days = 0;
+        }
+    }
+}
+
+/// Cash balance and loans of a company or the savings of a household
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub money: Money,
+    pub debt: Debt,
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Self {
+            money: COMPANY_START_MONEY,
+            debt: Debt::default(),
+        }
+    }
+}
+
+impl Account {
+    pub fn new(money: Money) -> Self {
+        Self {
+            money,
+            debt: Debt::default(),
+        }
+    }
+
+    /// Pays the interest, borrows to cover a negative balance and repays with the surplus
+    fn end_day(&mut self) {
+        self.money -= self.debt.daily_interest();
+
+        if self.money < Money::ZERO {
+            let needed = Money::ZERO - self.money;
+            let amount = needed.min(self.debt.available(COMPANY_CREDIT_LIMIT));
+            if amount > Money::ZERO {
+                self.debt.borrow(amount, COMPANY_DAILY_RATE);
+                self.money += amount;
+            }
+        } else if self.money > COMPANY_RESERVE {
+            let repaid = self.debt.repay(self.money - COMPANY_RESERVE);
+            self.money -= repaid;
+        }
+
+        self.debt.end_day(self.money);
+    }
+}
+
+impl Government {
+    /// Borrows up to `amount` within the credit limit, returns how much was borrowed
+    pub fn borrow(&mut self, amount: Money) -> Money {
+        let amount = amount.min(self.debt.available(GOVERNMENT_CREDIT_LIMIT));
+        if amount <= Money::ZERO {
+            return Money::ZERO;
+        }
+        self.debt.borrow(amount, GOVERNMENT_DAILY_RATE);
+        self.earn(LedgerCategory::Loans, amount);
+        amount
+    }
+
+    /// Repays up to `amount` of the loans with the money available, returns how much was repaid
+    pub fn repay(&mut self, amount: Money) -> Money {
+        let amount = amount.max(Money::ZERO).min(self.money.max(Money::ZERO));
+        let repaid = self.debt.repay(amount);
+        self.spend(LedgerCategory::Loans, repaid);
+        repaid
+    }
+
+    pub fn is_bankrupt(&self) -> bool {
+        self.debt.is_bankrupt()
+    }
+
+    fn end_day(&mut self) {
+        let interest = self.debt.daily_interest();
+        self.spend(LedgerCategory::Interest, interest);
+        self.debt.end_day(self.money);
+    }
+}
+
+/// Every day, the government and the companies pay the interest of their loans.
+/// Companies that stayed insolvent for too long are closed.
+/// Also closes the day of the companies profit reports.
+#[profiling::function]
+pub fn debt_system(goria: &mut Egregoria) {
+    if !goria.read::<GameTime>().tick(SECONDS_PER_DAY as u32) {
+        return;
+    }
+
+    goria.write::<Government>().end_day();
+
+    let mut bankrupt = vec![];
+    for (e, (_, account, profit)) in goria
+        .world
+        .query_mut::<(&GoodsCompany, &mut Account, &mut CompanyProfit)>()
+    {
+        account.end_day();
+        profit.end_day();
+        if account.debt.is_bankrupt() {
+            bankrupt.push(SoulID(e));
+        }
+    }
+
+    for soul in bankrupt {
+        close_company(goria, soul);
+    }
+}
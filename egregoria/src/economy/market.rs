@@ -364,6 +364,13 @@ impl Market {
     pub fn inner(&self) -> &BTreeMap<ItemID, SingleMarket> {
         &self.markets
     }
+
+    /// Worst case price of an item, when it has to be imported
+    pub fn import_price(&self, kind: ItemID) -> Money {
+        self.markets
+            .get(&kind)
+            .map_or(Money::ZERO, |m| m.ext_value + m.transport_cost)
+    }
 }
 
 fn calculate_prices(
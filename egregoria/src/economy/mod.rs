@@ -8,16 +8,22 @@ use std::fmt::{Debug, Display, Formatter};
 use std::iter::Sum;
 use std::ops::{Add, AddAssign, Div, Mul, SubAssign};
 
+mod accounts;
 mod ecostats;
 mod government;
 mod item;
+mod ledger;
+mod loans;
 mod market;
 
 use crate::souls::human::BasicWorker;
 use crate::utils::time::{Tick, TICKS_PER_SECOND};
+pub use accounts::*;
 pub use ecostats::*;
 pub use government::*;
 pub use item::*;
+pub use ledger::*;
+pub use loans::*;
 pub use market::*;
 
 const WORKER_CONSUMPTION_PER_SECOND: Money = Money::new_cents(1);
@@ -29,8 +35,12 @@ pub struct Money(i64);
 
 impl Display for Money {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        Display::fmt(&(self.0 / 100), f)?;
-        let cent = self.0 % 100;
+        if self.0 < 0 {
+            f.write_str("-")?;
+        }
+        let abs = self.0.abs();
+        Display::fmt(&(abs / 100), f)?;
+        let cent = abs % 100;
         if cent > 0 {
             f.write_str(".")?;
             if cent < 10 {
@@ -171,7 +181,8 @@ pub fn market_update(world: &mut World, resources: &mut Resources) {
     let tick = resources.get::<Tick>().unwrap().0;
 
     if tick % TICKS_PER_SECOND == 0 {
-        gvt.money -= n_workers as i64 * WORKER_CONSUMPTION_PER_SECOND;
+        let consumption = n_workers as i64 * WORKER_CONSUMPTION_PER_SECOND;
+        gvt.spend(LedgerCategory::WorkerConsumption, consumption);
     }
 
     let trades = m.make_trades();
@@ -193,30 +204,51 @@ pub fn market_update(world: &mut World, resources: &mut Resources) {
                 .push(trade.buyer.soul());
         }
 
-        match trade.seller {
-            TradeTarget::Soul(id) => {
-                if trade.kind != job_opening {
-                    if let Ok(mut v) = world.get::<&mut Sold>(id.0) {
-                        v.0.push(trade)
-                    }
+        if trade.kind != job_opening {
+            settle_trade(world, &mut gvt, &mut m, &trade);
+        }
+
+        if let TradeTarget::Soul(id) = trade.seller {
+            if trade.kind != job_opening {
+                if let Ok(mut v) = world.get::<&mut Sold>(id.0) {
+                    v.0.push(trade)
                 }
             }
-            TradeTarget::ExternalTrade => {
-                let singlem = m.m(trade.kind);
-                gvt.money -= (singlem.ext_value + singlem.transport_cost) * trade.qty as i64;
+        }
+
+        if let TradeTarget::Soul(id) = trade.buyer {
+            if let Ok(mut v) = world.get::<&mut Bought>(id.0) {
+                v.0.entry(trade.kind).or_default().push(trade);
             }
         }
+    }
+}
 
-        match trade.buyer {
-            TradeTarget::Soul(id) => {
-                if let Ok(mut v) = world.get::<&mut Bought>(id.0) {
-                    v.0.entry(trade.kind).or_default().push(trade);
-                }
+/// Pays the seller of a trade at the market price.
+/// Souls without an account don't pay nor get paid: the government pays for what they import
+/// and gets the money of what they export.
+fn settle_trade(world: &World, gvt: &mut Government, m: &mut Market, trade: &Trade) {
+    let singlem = m.m(trade.kind);
+    let qty = trade.qty as i64;
+    match (trade.seller, trade.buyer) {
+        (TradeTarget::Soul(seller), TradeTarget::Soul(buyer)) => {
+            let price = singlem.ext_value * qty;
+            let tax = gvt.collect_sales_tax(price);
+            transfer(world, buyer, Money::ZERO - price);
+            transfer(world, seller, price - tax);
+        }
+        (TradeTarget::ExternalTrade, TradeTarget::Soul(buyer)) => {
+            let cost = (singlem.ext_value + singlem.transport_cost) * qty;
+            if !transfer(world, buyer, Money::ZERO - cost) {
+                gvt.spend(LedgerCategory::Imports, cost);
             }
-            TradeTarget::ExternalTrade => {
-                let singlem = m.m(trade.kind);
-                gvt.money += (singlem.ext_value - singlem.transport_cost) * trade.qty as i64;
+        }
+        (TradeTarget::Soul(seller), TradeTarget::ExternalTrade) => {
+            let gain = (singlem.ext_value - singlem.transport_cost) * qty;
+            if !transfer(world, seller, gain) {
+                gvt.earn(LedgerCategory::Exports, gain);
             }
         }
+        (TradeTarget::ExternalTrade, TradeTarget::ExternalTrade) => {}
     }
 }
@@ -1,17 +1,24 @@
-use crate::economy::Government;
-use crate::map::procgen::{load_parismap, load_testfield};
+use crate::economy::{Government, LedgerCategory, Money, TaxRates};
+use crate::map::procgen::{city_reach, generate_city, load_parismap, load_testfield, OsmData};
 use crate::map::{
-    BuildingGen, BuildingID, BuildingKind, IntersectionID, LaneID, LanePattern, LanePatternBuilder,
-    LightPolicy, LotID, Map, MapProject, ProjectKind, RoadID, Terrain, TurnPolicy,
+    green_wave, Building, BuildingGen, BuildingID, BuildingKind, HeightEdit, Intersection,
+    IntersectionID, LaneID, LanePattern, LanePatternBuilder, LightPolicy, LotID, LotKind, Map,
+    MapProject, ProjectFilter, ProjectKind, Road, RoadID, RoadSegmentKind, TerraformKind, Terrain,
+    Tree, TurnPolicy,
 };
 use crate::map_dynamic::BuildingInfos;
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::transportation::bus::{BusLineDescription, BusLineID, BusLines};
 use crate::transportation::train::{spawn_train, RailWagonKind};
+use crate::transportation::train_line::{TrainLineDescription, TrainLineID, TrainLines};
+use crate::utils::rand_provider::RandProvider;
 use crate::utils::time::{GameTime, Tick};
 use crate::{Egregoria, EgregoriaOptions, Replay};
-use geom::{vec3, Polygon, Transform, Vec2, OBB};
+use geom::{vec2, vec3, Polygon, Transform, Vec2, OBB};
 use hecs::Entity;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::num::Wrapping;
 use std::time::Instant;
 use WorldCommand::*;
 
@@ -39,6 +46,147 @@ pub struct WorldCommands {
 
 defer_serialize!(WorldCommands, Vec<WorldCommand>);
 
+/// Undo history entries recorded or toggled by the commands applied during the last tick,
+/// in the order they were applied. None if the command cannot be reverted.
+#[derive(Default)]
+pub struct CommandInverses(pub Vec<Option<UndoID>>);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct UndoID(pub u64);
+
+/// How many map edits can be undone, older ones are forgotten
+const MAX_UNDO_HISTORY: usize = 1000;
+
+/// What reverts a map edit. Most edits are reverted by other commands, the ones that are only
+/// meant to be applied by the undo history are not `WorldCommand`s so that peers cannot send them.
+#[derive(Clone, Serialize, Deserialize)]
+enum Inverse {
+    Command(WorldCommand),
+    /// Sets the height of terrain points back, used to undo terraforming
+    TerrainSetHeights(Vec<HeightEdit>),
+    /// Adds back trees that terraforming put under water
+    TerrainAddTrees(Vec<Tree>),
+}
+
+impl From<WorldCommand> for Inverse {
+    fn from(command: WorldCommand) -> Self {
+        Inverse::Command(command)
+    }
+}
+
+impl Inverse {
+    /// Same as `WorldCommand::apply_effect`, the projections of commands are refreshed first
+    fn apply(mut self, goria: &mut Egregoria) -> Option<Vec<Inverse>> {
+        match self {
+            Inverse::Command(ref mut command) => {
+                command.refresh_projections(&goria.map());
+                command.apply_effect(goria)
+            }
+            Inverse::TerrainSetHeights(ref edits) => {
+                Some(undo_terraform(goria.map_mut().terraform(edits)))
+            }
+            Inverse::TerrainAddTrees(ref trees) => {
+                goria.map_mut().terrain.add_trees(trees);
+                // Setting the heights back culls them again
+                Some(vec![])
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct UndoEntry {
+    /// What reverts the edit, or reapplies it once it was undone
+    commands: Vec<Inverse>,
+    /// What the edit cost, refunded when undoing it and charged again when redoing it
+    cost: Money,
+    undone: bool,
+}
+
+/// Map edits that can be reverted.
+/// It is part of the simulation so that every peer agrees on what undoing does and how much
+/// it refunds, `Undo` and `Redo` commands only refer to entries by id.
+#[derive(Default, Serialize, Deserialize)]
+pub struct UndoHistory {
+    entries: BTreeMap<UndoID, UndoEntry>,
+    next_id: u64,
+}
+
+impl UndoHistory {
+    fn push(&mut self, commands: Vec<Inverse>, cost: Money) -> UndoID {
+        let id = UndoID(self.next_id);
+        self.next_id += 1;
+        self.entries.insert(
+            id,
+            UndoEntry {
+                commands,
+                cost,
+                undone: false,
+            },
+        );
+        while self.entries.len() > MAX_UNDO_HISTORY {
+            self.entries.pop_first();
+        }
+        id
+    }
+
+    /// Forgets every entry while keeping the ids going,
+    /// so that a replay, which does not forget them, hands out the same ids
+    pub(crate) fn forget(&mut self) {
+        self.entries.clear();
+    }
+
+    /// What undoing (or redoing if `undone`) the entry costs, None if it cannot be done
+    pub fn cost(&self, id: UndoID, undone: bool) -> Option<Money> {
+        let entry = self.entries.get(&id).filter(|e| e.undone == undone)?;
+        Some(entry.cost)
+    }
+}
+
+const MAX_UNDO: usize = 100;
+
+/// Client side undo/redo history, made of the undo history entries of the commands the client sent.
+/// Undoing and redoing are done by sending `Undo` or `Redo` through `WorldCommands`.
+#[derive(Default)]
+pub struct UndoStack {
+    undo: Vec<UndoID>,
+    redo: Vec<UndoID>,
+}
+
+impl UndoStack {
+    /// Must be called for every command sent by this client once it was applied
+    pub fn handle_applied(&mut self, command: &WorldCommand, entry: Option<UndoID>) {
+        let entry = unwrap_ret!(entry);
+        match command {
+            Undo(_) => self.redo.push(entry),
+            Redo(_) => self.undo.push(entry),
+            _ => {
+                self.undo.push(entry);
+                self.redo.clear();
+            }
+        }
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn undo(&mut self) -> Option<WorldCommand> {
+        self.undo.pop().map(Undo)
+    }
+
+    pub fn redo(&mut self) -> Option<WorldCommand> {
+        self.redo.pop().map(Redo)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WorldCommand {
     Init(Box<EgregoriaOptions>),
@@ -46,6 +194,18 @@ pub enum WorldCommand {
     MapRemoveRoad(RoadID),
     MapRemoveBuilding(BuildingID),
     MapBuildHouse(LotID),
+    /// Zones the lot, the growth system then builds on it when there is demand
+    MapSetLotKind {
+        lot: LotID,
+        kind: LotKind,
+    },
+    /// Edits the terrain with a brush, the roads on the ground follow it
+    Terraform {
+        kind: TerraformKind,
+        center: Vec2,
+        radius: f32,
+        amount: f32,
+    },
     AddTrain {
         dist: f32,
         n_wagons: u32,
@@ -66,6 +226,8 @@ pub enum WorldCommand {
         turn: TurnPolicy,
         light: LightPolicy,
     },
+    /// Synchronizes the lights of consecutive intersections, see `green_wave`
+    MapGreenWave(Vec<IntersectionID>),
     MapBuildSpecialBuilding {
         pos: OBB,
         kind: BuildingKind,
@@ -74,11 +236,19 @@ pub enum WorldCommand {
         zone: Option<Polygon>,
     },
     MapLoadParis,
+    /// Parsed by the sender so that every peer builds the same network
+    MapLoadOsm(Box<OsmData>),
     MapLoadTestField {
         pos: Vec2,
         size: u32,
         spacing: f32,
     },
+    /// Grows a road network with lots around `pos`, see `generate_city`
+    MapGenerateCity {
+        pos: Vec2,
+        size: f32,
+        seed: u64,
+    },
     UpdateZone {
         building: BuildingID,
         zone: Polygon,
@@ -86,6 +256,21 @@ pub enum WorldCommand {
     ResetSave,
     SetGameTime(GameTime),
     UpdateTransform(Entity, Transform),
+    AddBusLine(BusLineDescription),
+    EditBusLine(BusLineID, BusLineDescription),
+    RemoveBusLine(BusLineID),
+    AddTrainLine(TrainLineDescription),
+    EditTrainLine(TrainLineID, TrainLineDescription),
+    RemoveTrainLine(TrainLineID),
+    SetTaxRates(TaxRates),
+    /// The government takes a loan, within its credit limit
+    GovernmentBorrow(Money),
+    /// The government repays its loans with the money it has
+    GovernmentRepay(Money),
+    /// Reverts a map edit of the `UndoHistory`, what it cost is refunded
+    Undo(UndoID),
+    /// Reapplies a map edit that was undone, what it cost is charged again
+    Redo(UndoID),
 }
 
 impl AsRef<[WorldCommand]> for WorldCommands {
@@ -119,10 +304,26 @@ impl WorldCommands {
         self.commands.push(MapLoadParis)
     }
 
+    pub fn map_load_osm(&mut self, data: OsmData) {
+        self.commands.push(MapLoadOsm(Box::new(data)))
+    }
+
+    pub fn add_bus_line(&mut self, desc: BusLineDescription) {
+        self.commands.push(AddBusLine(desc))
+    }
+
+    pub fn add_train_line(&mut self, desc: TrainLineDescription) {
+        self.commands.push(AddTrainLine(desc))
+    }
+
     pub fn map_load_testfield(&mut self, pos: Vec2, size: u32, spacing: f32) {
         self.commands.push(MapLoadTestField { pos, size, spacing })
     }
 
+    pub fn map_generate_city(&mut self, pos: Vec2, size: f32, seed: u64) {
+        self.commands.push(MapGenerateCity { pos, size, seed })
+    }
+
     pub fn update_transform(&mut self, e: Entity, trans: Transform) {
         self.commands.push(UpdateTransform(e, trans))
     }
@@ -174,6 +375,19 @@ impl WorldCommands {
         self.commands.push(MapBuildHouse(id))
     }
 
+    pub fn map_set_lot_kind(&mut self, lot: LotID, kind: LotKind) {
+        self.commands.push(MapSetLotKind { lot, kind })
+    }
+
+    pub fn terraform(&mut self, kind: TerraformKind, center: Vec2, radius: f32, amount: f32) {
+        self.commands.push(Terraform {
+            kind,
+            center,
+            radius,
+            amount,
+        })
+    }
+
     pub fn map_make_connection(
         &mut self,
         from: MapProject,
@@ -201,13 +415,26 @@ impl WorldCommands {
             light: lp,
         })
     }
+
+    pub fn map_green_wave(&mut self, corridor: Vec<IntersectionID>) {
+        self.commands.push(MapGreenWave(corridor))
+    }
+
+    pub fn set_tax_rates(&mut self, rates: TaxRates) {
+        self.commands.push(SetTaxRates(rates))
+    }
+
+    pub fn government_borrow(&mut self, amount: Money) {
+        self.commands.push(GovernmentBorrow(amount))
+    }
+
+    pub fn government_repay(&mut self, amount: Money) {
+        self.commands.push(GovernmentRepay(amount))
+    }
 }
 
 impl WorldCommand {
     pub(crate) fn apply(&self, goria: &mut Egregoria) {
-        let cost = Government::action_cost(self, goria);
-        goria.write::<Government>().money -= cost;
-
         let mut rep = goria.resources.get_mut::<Replay>().unwrap();
         if rep.enabled {
             let tick = goria.read::<Tick>();
@@ -215,15 +442,69 @@ impl WorldCommand {
         }
         drop(rep);
 
+        let cost = Government::action_cost(self, goria);
+        if cost > Money::ZERO && goria.read::<Government>().is_bankrupt() {
+            log::warn!("the government is bankrupt, it cannot pay for new construction");
+            goria.write::<CommandInverses>().0.push(None);
+            return;
+        }
+
+        let applied = match *self {
+            Undo(id) => revert_entry(goria, id, false).map(Some),
+            Redo(id) => revert_entry(goria, id, true).map(Some),
+            _ => self.apply_effect(goria).map(|commands| {
+                (!commands.is_empty()).then(|| goria.write::<UndoHistory>().push(commands, cost))
+            }),
+        };
+
+        // Only what was applied is paid for
+        if applied.is_some() {
+            goria
+                .write::<Government>()
+                .spend(LedgerCategory::Construction, cost);
+        }
+
+        goria.write::<CommandInverses>().0.push(applied.flatten());
+    }
+
+    /// Applies the command without charging or recording it.
+    /// Returns what reverts it, nothing if it cannot be reverted,
+    /// or None if it could not be applied.
+    fn apply_effect(&self, goria: &mut Egregoria) -> Option<Vec<Inverse>> {
         match *self {
-            MapRemoveIntersection(id) => goria.map_mut().remove_intersection(id),
-            MapRemoveRoad(id) => drop(goria.map_mut().remove_road(id)),
-            MapRemoveBuilding(id) => drop(goria.map_mut().remove_building(id)),
+            MapRemoveIntersection(id) => {
+                let inverse = undo_remove_intersection(&goria.map(), id);
+                goria.map_mut().remove_intersection(id);
+                return inverse;
+            }
+            MapRemoveRoad(id) => {
+                let inverse = undo_remove_road(&goria.map(), id);
+                goria.map_mut().remove_road(id)?;
+                return inverse;
+            }
+            MapRemoveBuilding(id) => {
+                let b = goria.map_mut().remove_building(id)?;
+                return Some(vec![undo_remove_building(goria, b).into()]);
+            }
             MapBuildHouse(id) => {
-                if let Some(build) = goria.map_mut().build_house(id) {
-                    let mut infos = goria.write::<BuildingInfos>();
-                    infos.insert(build);
-                }
+                let build = goria.map_mut().build_house(id)?;
+                goria.write::<BuildingInfos>().insert(build);
+                return Some(vec![MapRemoveBuilding(build).into()]);
+            }
+            MapSetLotKind { lot, kind } => {
+                let mut map = goria.map_mut();
+                let old = map.lots().get(lot)?.kind;
+                map.set_lot_kind(lot, kind);
+                return Some(vec![MapSetLotKind { lot, kind: old }.into()]);
+            }
+            Terraform {
+                kind,
+                center,
+                radius,
+                amount,
+            } => {
+                let edits = goria.map().terrain.terraform(kind, center, radius, amount);
+                return Some(undo_terraform(goria.map_mut().terraform(&edits)));
             }
             MapMakeConnection {
                 from,
@@ -231,11 +512,24 @@ impl WorldCommand {
                 inter,
                 ref pat,
             } => {
-                goria.write::<Map>().make_connection(from, to, inter, pat);
+                let mut map = goria.map_mut();
+                let splits = split_roads(&map, &[from, to]);
+                let (_, r) = map.make_connection(from, to, inter, pat)?;
+                let ends = [map.roads[r].src, map.roads[r].dst];
+
+                // Intersections left empty by the removal are removed along with the road
+                let mut inverse = vec![MapRemoveRoad(r).into()];
+                for (i, rebuild) in splits {
+                    inverse.push(MapRemoveIntersection(ends[i]).into());
+                    inverse.extend(rebuild);
+                }
+                return Some(inverse);
             }
             MapMakeMultipleConnections(ref projects, ref links) => {
                 let mut map = goria.map_mut();
+                let splits = split_roads(&map, projects);
                 let mut inters = BTreeMap::new();
+                let mut inverse = vec![];
                 for (from, to, interpoint, pat) in links {
                     let mut fromproj = projects[*from];
                     let mut toproj = projects[*to];
@@ -248,36 +542,73 @@ impl WorldCommand {
                     }
 
                     if let Some((_, r)) = map.make_connection(fromproj, toproj, *interpoint, pat) {
-                        if fromproj.kind.is_ground() {
+                        if !matches!(fromproj.kind, ProjectKind::Inter(_)) {
                             inters.insert(*from, map.roads[r].src);
                         }
-                        if toproj.kind.is_ground() {
+                        if !matches!(toproj.kind, ProjectKind::Inter(_)) {
                             inters.insert(*to, map.roads[r].dst);
                         }
+                        inverse.push(MapRemoveRoad(r).into());
                     }
                 }
+                inverse.reverse();
+                for (i, rebuild) in splits {
+                    let id = unwrap_cont!(inters.get(&i));
+                    inverse.push(MapRemoveIntersection(*id).into());
+                    inverse.extend(rebuild);
+                }
+                return Some(inverse);
             }
             MapUpdateIntersectionPolicy {
                 inter: id,
                 turn: tp,
                 light: lp,
-            } => goria.map_mut().update_intersection(id, move |i| {
-                i.light_policy = lp;
-                i.turn_policy = tp;
-            }),
+            } => {
+                let (old_turn, old_light) = {
+                    let map = goria.map();
+                    let i = map.intersections().get(id)?;
+                    (i.turn_policy, i.light_policy)
+                };
+                goria.map_mut().update_intersection(id, move |i| {
+                    i.light_policy = lp;
+                    i.turn_policy = tp;
+                });
+                return Some(vec![MapUpdateIntersectionPolicy {
+                    inter: id,
+                    turn: old_turn,
+                    light: old_light,
+                }
+                .into()]);
+            }
+            MapGreenWave(ref corridor) => {
+                let mut map = goria.map_mut();
+                let mut inverse = vec![];
+                for (id, lp) in green_wave(&map, corridor) {
+                    let i = unwrap_cont!(map.intersections().get(id));
+                    inverse.push(
+                        MapUpdateIntersectionPolicy {
+                            inter: id,
+                            turn: i.turn_policy,
+                            light: i.light_policy,
+                        }
+                        .into(),
+                    );
+                    map.update_intersection(id, move |i| i.light_policy = lp);
+                }
+                return Some(inverse);
+            }
             MapBuildSpecialBuilding {
                 pos: obb,
                 kind,
                 gen,
                 ref zone,
             } => {
-                if let Some(id) =
+                let id =
                     goria
                         .write::<Map>()
-                        .build_special_building(&obb, kind, gen, zone.clone())
-                {
-                    goria.write::<BuildingInfos>().insert(id);
-                }
+                        .build_special_building(&obb, kind, gen, zone.clone())?;
+                goria.write::<BuildingInfos>().insert(id);
+                return Some(vec![MapRemoveBuilding(id).into()]);
             }
             SetGameTime(gt) => *goria.write::<GameTime>() = gt,
             AddTrain {
@@ -285,15 +616,61 @@ impl WorldCommand {
                 n_wagons,
                 lane,
             } => {
-                spawn_train(goria, dist, n_wagons, lane, RailWagonKind::Fret);
+                spawn_train(goria, dist, n_wagons, lane, RailWagonKind::Fret)?;
+            }
+            MapLoadParis => {
+                let mut map = goria.map_mut();
+                load_parismap(&mut map);
+                map.generation += Wrapping(1);
+            }
+            MapLoadOsm(ref data) => {
+                let mut map = goria.map_mut();
+                let stats = data.build(&mut map);
+                map.generation += Wrapping(1);
+                drop(map);
+                let mut infos = goria.write::<BuildingInfos>();
+                for id in stats.buildings {
+                    infos.insert(id);
+                }
             }
-            MapLoadParis => load_parismap(&mut goria.map_mut()),
             MapLoadTestField { pos, size, spacing } => {
-                load_testfield(&mut goria.map_mut(), pos, size, spacing)
+                let mut map = goria.map_mut();
+                load_testfield(&mut map, pos, size, spacing);
+                map.generation += Wrapping(1);
+            }
+            MapGenerateCity { pos, size, seed } => {
+                let mut map = goria.map_mut();
+                // The existing roads the city connects to are split, rebuild them on undo
+                let reach = city_reach(size);
+                let rebuild: Vec<_> = map
+                    .spatial_map()
+                    .query_around(pos, reach, ProjectFilter::ROAD)
+                    .filter_map(|kind| match kind {
+                        ProjectKind::Road(id) => Some((id, undo_remove_road(&map, id)?)),
+                        _ => None,
+                    })
+                    .collect();
+
+                let stats = generate_city(&mut map, pos, size, seed);
+
+                let mut inverse: Vec<Inverse> = stats
+                    .roads
+                    .iter()
+                    .map(|&r| MapRemoveRoad(r).into())
+                    .collect();
+                for (id, commands) in rebuild {
+                    if !map.roads().contains_key(id) {
+                        inverse.extend(commands);
+                    }
+                }
+                return Some(inverse);
             }
             ResetSave => {
                 let opts = goria.read::<EgregoriaOptions>().clone();
+                // Keep the entries of the commands applied before the reset in the same tick
+                let inverses = std::mem::take(&mut *goria.write::<CommandInverses>());
                 *goria = Egregoria::new_with_options(opts);
+                *goria.write::<CommandInverses>() = inverses;
             }
             UpdateTransform(e, t) => {
                 if let Some(mut x) = goria.comp_mut(e) {
@@ -308,28 +685,256 @@ impl WorldCommand {
                     rep.commands.push((*tick, Init(opts.clone())));
                 }
 
+                *goria.write::<RandProvider>() = RandProvider::new(opts.seed);
                 if opts.terrain_size > 0 {
-                    generate_terrain(goria, opts.terrain_size);
+                    generate_terrain(goria, opts);
                 }
+                let mut map = goria.map_mut();
+                map.travel_times.enabled = opts.dynamic_rerouting;
+                map.generation += Wrapping(1);
+                drop(map);
 
                 goria
                     .resources
                     .insert::<EgregoriaOptions>(EgregoriaOptions::clone(opts));
             }
+            AddBusLine(ref desc) => {
+                let desc = desc.clone().snapped(&goria.map());
+                let id = goria.write::<BusLines>().add(desc);
+                return Some(vec![RemoveBusLine(id).into()]);
+            }
+            EditBusLine(id, ref desc) => {
+                let desc = desc.clone().snapped(&goria.map());
+                let old = goria.write::<BusLines>().edit(id, desc)?;
+                return Some(vec![EditBusLine(id, old).into()]);
+            }
+            RemoveBusLine(id) => {
+                let line = goria.write::<BusLines>().remove(id)?;
+                return Some(vec![AddBusLine(line.desc).into()]);
+            }
+            AddTrainLine(ref desc) => {
+                let id = goria.write::<TrainLines>().add(desc.clone());
+                return Some(vec![RemoveTrainLine(id).into()]);
+            }
+            EditTrainLine(id, ref desc) => {
+                let old = goria.write::<TrainLines>().edit(id, desc.clone())?;
+                return Some(vec![EditTrainLine(id, old).into()]);
+            }
+            RemoveTrainLine(id) => {
+                let line = goria.write::<TrainLines>().remove(id)?;
+                return Some(vec![AddTrainLine(line.desc).into()]);
+            }
+            GovernmentBorrow(amount) => {
+                goria.write::<Government>().borrow(amount);
+            }
+            GovernmentRepay(amount) => {
+                goria.write::<Government>().repay(amount);
+            }
+            SetTaxRates(rates) => {
+                let old = std::mem::replace(&mut goria.write::<Government>().taxes, rates);
+                return Some(vec![SetTaxRates(old).into()]);
+            }
             UpdateZone { building, ref zone } => {
                 let mut map = goria.map_mut();
+                let old = map.buildings().get(building)?.zone.clone()?;
 
                 map.update_zone(building, move |z| *z = zone.clone());
+                return Some(vec![UpdateZone {
+                    building,
+                    zone: old,
+                }
+                .into()]);
+            }
+            Undo(_) | Redo(_) => {}
+        }
+        Some(vec![])
+    }
+
+    /// Projections on intersections or roads that were rebuilt since the command was recorded
+    /// are moved to the new ones at the same place
+    fn refresh_projections(&mut self, map: &Map) {
+        let refresh = |proj: &mut MapProject| {
+            if proj.kind.check_valid(map) && !proj.kind.is_ground() {
+                return;
             }
+            let filter = match proj.kind {
+                ProjectKind::Road(_) => ProjectFilter::INTER | ProjectFilter::ROAD,
+                _ => ProjectFilter::INTER,
+            };
+            let refreshed = map.project(proj.pos, 1.0, filter);
+            if !refreshed.kind.is_ground() {
+                *proj = refreshed;
+            }
+        };
+        match self {
+            MapMakeConnection { from, to, .. } => {
+                refresh(from);
+                refresh(to);
+            }
+            MapMakeMultipleConnections(projects, _) => projects.iter_mut().for_each(refresh),
+            _ => {}
         }
     }
 }
 
-fn generate_terrain(goria: &mut Egregoria, size: u32) {
+/// Undoes the entry of the history, or redoes it if `undone`.
+/// The entry then holds the commands to go back. If one of them could not be applied, the ones
+/// already applied are rolled back and the entry is forgotten.
+fn revert_entry(goria: &mut Egregoria, id: UndoID, undone: bool) -> Option<UndoID> {
+    let commands = {
+        let history = goria.read::<UndoHistory>();
+        let entry = history.entries.get(&id).filter(|e| e.undone == undone)?;
+        entry.commands.clone()
+    };
+
+    let mut inverses = vec![];
+    for command in commands {
+        match command.apply(goria) {
+            Some(inverse) => inverses.push(inverse),
+            None => {
+                for command in inverses.into_iter().rev().flatten() {
+                    command.apply(goria);
+                }
+                goria.write::<UndoHistory>().entries.remove(&id);
+                return None;
+            }
+        }
+    }
+
+    // revert in the opposite order
+    let inverse = inverses.into_iter().rev().flatten().collect();
+
+    let mut history = goria.write::<UndoHistory>();
+    let entry = history.entries.get_mut(&id)?;
+    entry.commands = inverse;
+    entry.undone = !undone;
+    Some(id)
+}
+
+/// The heights come back first so that the trees are not culled again
+fn undo_terraform((heights, culled): (Vec<HeightEdit>, Vec<Tree>)) -> Vec<Inverse> {
+    let mut undo = vec![Inverse::TerrainSetHeights(heights)];
+    if !culled.is_empty() {
+        undo.push(Inverse::TerrainAddTrees(culled));
+    }
+    undo
+}
+
+/// Reconnects the intersection to the other end of all of its roads.
+/// Other ends only connected to this intersection are removed with it, so they are rebuilt from the ground.
+fn undo_remove_intersection(map: &Map, id: IntersectionID) -> Option<Vec<Inverse>> {
+    let inter = map.intersections.get(id)?;
+
+    let mut projects = vec![MapProject::ground(inter.pos)];
+    let mut project_of = BTreeMap::new();
+    let mut links = vec![];
+
+    for &r in &inter.roads {
+        let road = unwrap_cont!(map.roads.get(r));
+        let other = unwrap_cont!(road.other_end(id));
+        let other_inter = unwrap_cont!(map.intersections.get(other));
+
+        let idx = *project_of.entry(other).or_insert_with(|| {
+            projects.push(inter_project(other_inter, |r| {
+                road_other_end(map, r, other) == Some(id)
+            }));
+            projects.len() - 1
+        });
+
+        let (from, to) = if road.src == id { (0, idx) } else { (idx, 0) };
+        links.push((from, to, road_elbow(road), road.pattern(&map.lanes)));
+    }
+
+    Some(vec![MapMakeMultipleConnections(projects, links).into()])
+}
+
+/// Roads that the projections will split, along with the index of the projection and
+/// the commands that rebuild the road once the split intersection is removed.
+/// Must be called before making the connections.
+fn split_roads(map: &Map, projects: &[MapProject]) -> Vec<(usize, Vec<Inverse>)> {
+    projects
+        .iter()
+        .enumerate()
+        .filter_map(|(i, p)| match p.kind {
+            ProjectKind::Road(id) => Some((i, undo_remove_road(map, id)?)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn undo_remove_road(map: &Map, id: RoadID) -> Option<Vec<Inverse>> {
+    let road = map.roads.get(id)?;
+    let src = map.intersections.get(road.src)?;
+    let dst = map.intersections.get(road.dst)?;
+
+    Some(vec![MapMakeConnection {
+        from: inter_project(src, |r| r == id),
+        to: inter_project(dst, |r| r == id),
+        inter: road_elbow(road),
+        pat: road.pattern(&map.lanes),
+    }
+    .into()])
+}
+
+/// Intersections which lose all of their roads are removed from the map,
+/// so they can only be referred to by their position
+fn inter_project(inter: &Intersection, removed: impl Fn(RoadID) -> bool) -> MapProject {
+    if inter.roads.iter().all(|&r| removed(r)) {
+        return MapProject::ground(inter.pos);
+    }
+    MapProject {
+        pos: inter.pos,
+        kind: ProjectKind::Inter(inter.id),
+    }
+}
+
+fn road_other_end(map: &Map, r: RoadID, from: IntersectionID) -> Option<IntersectionID> {
+    map.roads.get(r)?.other_end(from)
+}
+
+/// Inverse of `RoadSegmentKind::from_elbow`
+fn road_elbow(road: &Road) -> Option<Vec2> {
+    match road.segment {
+        RoadSegmentKind::Straight => None,
+        RoadSegmentKind::Curved((from_derivative, _)) => {
+            Some(road.points.first().xy() + from_derivative * std::f32::consts::SQRT_2)
+        }
+    }
+}
+
+/// The building is rebuilt empty, the souls that lived or worked in it are not brought back.
+/// New ones move in through `add_souls_to_empty_buildings` like in any new building.
+fn undo_remove_building(goria: &Egregoria, b: Building) -> WorldCommand {
+    let gen = match b.kind {
+        BuildingKind::House => BuildingGen::House,
+        BuildingKind::GoodsCompany(id) => {
+            goria.read::<GoodsCompanyRegistry>().descriptions[id].bgen
+        }
+        _ => {
+            let axis = (b.obb.corners[1] - b.obb.corners[0]).normalize();
+            BuildingGen::NoWalkway {
+                door_pos: (b.door_pos.xy() - b.obb.center()).rotated_by(vec2(axis.x, -axis.y)),
+            }
+        }
+    };
+
+    MapBuildSpecialBuilding {
+        pos: b.obb,
+        kind: b.kind,
+        gen,
+        zone: b.zone,
+    }
+}
+
+fn generate_terrain(goria: &mut Egregoria, opts: &EgregoriaOptions) {
     info!("generating terrain..");
     let t = Instant::now();
 
-    goria.map_mut().terrain = Terrain::new(size, size);
+    let size = opts.terrain_size;
+    let mut map = goria.map_mut();
+    map.terrain = Terrain::new(size, size, opts.seed, &opts.terrain);
+    map.generation += Wrapping(1);
+    drop(map);
     info!("took {}s", t.elapsed().as_secs_f32());
 
     let c = vec3(3000.0 + 72.2 / 2.0, 200.0 / 2.0 + 1.0, 0.3);
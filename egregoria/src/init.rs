@@ -1,23 +1,43 @@
-use crate::economy::{init_market, market_update, EcoStats, Government, ItemRegistry, Market};
-use crate::map::Map;
+use crate::economy::{
+    debt_system, government_finances_system, init_market, market_update, migrate_company_accounts,
+    migrate_company_profits, migrate_government_debt, migrate_government_finances,
+    migrate_household_accounts, wages_system, EcoStats, Government, ItemRegistry, Market,
+};
+use crate::engine_interaction::{CommandInverses, UndoHistory};
+use crate::map::{
+    land_value_update_system, landmarks_update_system, migrate_map_travel_times, migrate_map_water,
+    LandValue, Map,
+};
 use crate::map_dynamic::{
-    dispatch_system, itinerary_update, routing_changed_system, routing_update_system,
-    BuildingInfos, Dispatcher, ParkingManagement,
+    actuated_signals_system, dispatch_system, growth_system, itinerary_update, reroute_system,
+    routing_changed_system, routing_service_system, routing_update_system, travel_times_system,
+    BuildingInfos, Dispatcher, ParkingManagement, RoutingService, SignalControllers,
+    TravelTimeObserver, ZoneDemand,
 };
 use crate::physics::coworld_synchronize;
+use crate::souls::desire::{init_desires, migrate_food_desire, DesireRegistry};
 use crate::souls::fret_station::freight_station_system;
 use crate::souls::goods_company::{company_system, GoodsCompanyRegistry};
-use crate::souls::human::update_decision_system;
+use crate::souls::human::{migrate_household_desires, migrate_households, update_decision_system};
+use crate::souls::population::{population_system, PopulationStats};
+use crate::souls::train_station::train_station_system;
+use crate::transportation::bus::{migrate_bus_lines_stops, BusKind, BusLines};
 use crate::transportation::pedestrian_decision_system;
 use crate::transportation::road::{vehicle_decision_system, vehicle_state_update_system};
+use crate::transportation::traffic_stats::{traffic_stats_system, TrafficStats};
 use crate::transportation::train::{
-    locomotive_system, train_reservations_update, TrainReservations,
+    locomotive_system, migrate_train_reservations_platforms, train_reservations_update,
+    TrainReservations,
 };
+use crate::transportation::train_line::{TrainKind, TrainLines};
+use crate::transportation::transit::transit_system;
+use crate::utils::migration::Migration;
 use crate::utils::time::Tick;
 use crate::{
-    add_souls_to_empty_buildings, utils, CollisionWorld, Egregoria, EgregoriaOptions, GameTime,
-    ParCommandBuffer, RandProvider, Replay, RunnableSystem, RNG_SEED, SECONDS_PER_DAY,
-    SECONDS_PER_HOUR,
+    add_souls_to_empty_buildings, migrate_options_rerouting, migrate_options_terrain,
+    migrate_replay_undo, migrate_terrain_inverses, utils, CollisionWorld, Egregoria,
+    EgregoriaOptions, GameTime, ParCommandBuffer, RandProvider, Replay, RunnableSystem, RNG_SEED,
+    SECONDS_PER_DAY, SECONDS_PER_HOUR,
 };
 use common::saveload::Encoder;
 use hecs::World;
@@ -26,31 +46,53 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 pub fn init() {
+    register_system("landmarks_update", landmarks_update_system);
+    register_system("land_value_update", land_value_update_system);
     register_system("dispatch_system", dispatch_system);
     register_system("update_decision_system", update_decision_system);
     register_system("company_system", company_system);
     register_system("pedestrian_decision_system", pedestrian_decision_system);
     register_system("coworld_synchronize", coworld_synchronize);
     register_system("locomotive_system", locomotive_system);
+    register_system("actuated_signals", actuated_signals_system);
     register_system("vehicle_decision_system", vehicle_decision_system);
     register_system("vehicle_state_update_system", vehicle_state_update_system);
+    register_system("traffic_stats", traffic_stats_system);
     register_system("routing_changed_system", routing_changed_system);
     register_system("routing_update_system", routing_update_system);
+    register_system("routing_service", routing_service_system);
     register_system("itinerary_update", itinerary_update);
+    register_system("travel_times", travel_times_system);
+    register_system("reroute", reroute_system);
     register_system("market_update", market_update);
+    register_system("wages", wages_system);
+    register_system("government_finances", government_finances_system);
     register_system("train_reservations_update", train_reservations_update);
     register_system("freight_station", freight_station_system);
+    register_system("train_station", train_station_system);
 
     register_system_goria("add_souls_to_empty_buildings", add_souls_to_empty_buildings);
+    register_system_goria("bus_system", transit_system::<BusKind>);
+    register_system_goria("passenger_train_system", transit_system::<TrainKind>);
+    register_system_goria("debt_system", debt_system);
+    register_system_goria("population_system", population_system);
+    register_system_goria("growth_system", growth_system);
 
     register_resource_noserialize::<GoodsCompanyRegistry>();
     register_resource_noserialize::<ItemRegistry>();
+    register_resource_noserialize::<DesireRegistry>();
     register_resource_noserialize::<ParCommandBuffer>();
+    register_resource_noserialize::<CommandInverses>();
+    register_resource_noserialize::<TrafficStats>();
+    register_resource_noserialize::<RoutingService>();
+    register_resource_noserialize::<ZoneDemand>();
+    register_resource_noserialize::<LandValue>();
     register_resource_noinit::<Market>("market");
     register_resource_noinit::<EcoStats>("ecostats");
     register_resource_noinit::<EgregoriaOptions>("egregoriaoptions");
 
     register_init(init_market);
+    register_init(init_desires);
 
     register_resource("tick", Tick::default);
     register_resource("map", Map::default);
@@ -65,6 +107,48 @@ pub fn init() {
     register_resource("randprovider", || RandProvider::new(RNG_SEED));
     register_resource("dispatcher", Dispatcher::default);
     register_resource("replay", Replay::default);
+    register_resource("bus_lines", BusLines::default);
+    register_resource("population_stats", PopulationStats::default);
+    register_resource("train_lines", TrainLines::default);
+    register_resource("signal_controllers", SignalControllers::default);
+    register_resource("travel_time_observer", TravelTimeObserver::default);
+    register_resource("undo_history", UndoHistory::default);
+
+    // Save migrations go here, chained from oldest to newest, for example:
+    // register_migration(Migration::new("0.5.0", "0.6.0").resource("map", migrate_map));
+    register_migration(
+        Migration::new("0.5.0", "0.5.1")
+            .resource("map", migrate_map_travel_times)
+            .resource("egregoriaoptions", migrate_options_rerouting),
+    );
+    register_migration(
+        Migration::new("0.5.1", "0.5.2").resource("government", migrate_government_finances),
+    );
+    register_migration(
+        Migration::new("0.5.2", "0.5.3")
+            .resource("government", migrate_government_debt)
+            .world(migrate_company_accounts),
+    );
+    register_migration(Migration::new("0.5.3", "0.5.4").world(migrate_company_profits));
+    register_migration(Migration::new("0.5.4", "0.5.5").world(migrate_household_accounts));
+    register_migration(Migration::new("0.5.5", "0.5.6").world(migrate_household_desires));
+    register_migration(Migration::new("0.5.6", "0.5.7").world(migrate_households));
+    register_migration(Migration::new("0.5.7", "0.5.8").resource("map", migrate_map_water));
+    register_migration(
+        Migration::new("0.5.8", "0.5.9").resource("egregoriaoptions", migrate_options_terrain),
+    );
+    register_migration(Migration::new("0.5.9", "0.5.10").resource("replay", migrate_replay_undo));
+    register_migration(
+        Migration::new("0.5.10", "0.5.11")
+            .resource("bus_lines", migrate_bus_lines_stops)
+            .resource("train_reservations", migrate_train_reservations_platforms),
+    );
+    register_migration(Migration::new("0.5.11", "0.5.12").world(migrate_food_desire));
+    register_migration(
+        Migration::new("0.5.12", "0.5.13")
+            .resource("replay", migrate_terrain_inverses)
+            .resource("undo_history", migrate_terrain_inverses),
+    );
 }
 
 pub struct InitFunc {
@@ -74,7 +158,7 @@ pub struct InitFunc {
 pub(crate) struct SaveLoadFunc {
     pub name: &'static str,
     pub save: Box<dyn Fn(&Egregoria) -> Vec<u8> + 'static>,
-    pub load: Box<dyn Fn(&mut Egregoria, Vec<u8>) + 'static>,
+    pub load: Box<dyn Fn(&mut Egregoria, Vec<u8>) -> std::io::Result<()> + 'static>,
 }
 
 pub(crate) struct GSystem {
@@ -84,6 +168,14 @@ pub(crate) struct GSystem {
 pub(crate) static mut INIT_FUNCS: Vec<InitFunc> = Vec::new();
 pub(crate) static mut SAVELOAD_FUNCS: Vec<SaveLoadFunc> = Vec::new();
 pub(crate) static mut GSYSTEMS: Vec<GSystem> = Vec::new();
+pub(crate) static mut MIGRATIONS: Vec<Migration> = Vec::new();
+
+/// Migrations are applied when loading a save whose version is `from`
+pub fn register_migration(m: Migration) {
+    unsafe {
+        MIGRATIONS.push(m);
+    }
+}
 
 fn register_init(s: fn(&mut World, &mut Resources)) {
     unsafe {
@@ -144,9 +236,9 @@ fn register_resource_noinit<T: 'static + Send + Sync + Serialize + DeserializeOw
                 <common::saveload::Bincode as Encoder>::encode(&*uiworld.read::<T>()).unwrap()
             }),
             load: Box::new(move |uiworld, data| {
-                if let Ok(res) = <common::saveload::Bincode as Encoder>::decode::<T>(&data) {
-                    uiworld.insert(res);
-                }
+                let res = <common::saveload::Bincode as Encoder>::decode::<T>(&data)?;
+                uiworld.insert(res);
+                Ok(())
             }),
         });
     }
@@ -1,16 +1,17 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::type_complexity)]
 
-use crate::economy::{Bought, Sold, Workers};
-use crate::engine_interaction::{Selectable, WorldCommand};
-use crate::map::{BuildingKind, Map};
+use crate::economy::{Account, Bought, CompanyProfit, Sold, Workers};
+use crate::engine_interaction::{CommandInverses, Selectable, UndoHistory, WorldCommand};
+use crate::map::{BuildingKind, Map, TerrainOptions};
 use crate::map_dynamic::{Itinerary, ItineraryFollower, ItineraryLeader, Router};
 use crate::physics::CollisionWorld;
 use crate::physics::{Collider, Speed};
 use crate::souls::add_souls_to_empty_buildings;
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::desire::{BuyFood, Desires, Home, Work};
 use crate::souls::goods_company::{GoodsCompany, GoodsCompanyRegistry};
 use crate::souls::human::HumanDecision;
+use crate::souls::population::Household;
 use crate::transportation::train::{Locomotive, LocomotiveReservation};
 use crate::transportation::{Pedestrian, Vehicle};
 use common::saveload::Encoder;
@@ -23,6 +24,7 @@ use std::hash::Hash;
 use std::time::{Duration, Instant};
 use transportation::Location;
 use utils::rand_provider::RandProvider;
+use utils::replay_player::ReplayPlayer;
 use utils::scheduler::SeqSchedule;
 use utils::time::{GameTime, SECONDS_PER_DAY, SECONDS_PER_HOUR};
 
@@ -51,9 +53,13 @@ pub mod transportation;
 pub mod utils;
 
 use crate::engine_interaction::WorldCommand::Init;
-use crate::init::{GSYSTEMS, INIT_FUNCS, SAVELOAD_FUNCS};
+use crate::init::{GSYSTEMS, INIT_FUNCS, MIGRATIONS, SAVELOAD_FUNCS};
 use crate::souls::fret_station::FreightStation;
+use crate::souls::train_station::TrainStation;
+use crate::transportation::bus::Bus;
 use crate::transportation::train::RailWagon;
+use crate::transportation::train_line::PassengerTrain;
+use crate::utils::migration::{migration_path, Migration};
 use crate::utils::scheduler::RunnableSystem;
 use crate::utils::time::Tick;
 use common::FastMap;
@@ -80,9 +86,17 @@ const RNG_SEED: u64 = 123;
 const VERSION: &str = include_str!("../../VERSION");
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EgregoriaOptions {
+    /// Size of the map, in chunks
     pub terrain_size: u32,
     pub save_replay: bool,
+    /// Cars route using the observed travel times and take a new route when theirs gets slow.
+    /// Disabled, routes only depend on the map geometry.
+    pub dynamic_rerouting: bool,
+    /// Seed of the terrain and of the random events of the simulation
+    pub seed: u64,
+    pub terrain: TerrainOptions,
 }
 
 impl Default for EgregoriaOptions {
@@ -90,10 +104,38 @@ impl Default for EgregoriaOptions {
         EgregoriaOptions {
             terrain_size: 50,
             save_replay: true,
+            dynamic_rerouting: true,
+            seed: RNG_SEED,
+            terrain: TerrainOptions::default(),
         }
     }
 }
 
+/// Saves from before `dynamic_rerouting` enable it
+pub(crate) fn migrate_options_rerouting(mut data: Vec<u8>) -> Option<Vec<u8>> {
+    data.extend(common::saveload::Bincode::encode(&true).ok()?);
+    Some(data)
+}
+
+/// Saves from before the seed and the terrain options were generated with the default ones
+pub(crate) fn migrate_options_terrain(mut data: Vec<u8>) -> Option<Vec<u8>> {
+    data.extend(common::saveload::Bincode::encode(&RNG_SEED).ok()?);
+    data.extend(common::saveload::Bincode::encode(&TerrainOptions::default()).ok()?);
+    Some(data)
+}
+
+/// `Undo` and `Redo` used to carry the commands they applied, replays recorded with them
+/// cannot be decoded anymore so they are dropped
+pub(crate) fn migrate_replay_undo(_: Vec<u8>) -> Option<Vec<u8>> {
+    None
+}
+
+/// The terrain inverses of terraforming are no longer commands, which shifted the command
+/// indices. Replays and undo histories recorded before cannot be decoded so they are dropped
+pub(crate) fn migrate_terrain_inverses(_: Vec<u8>) -> Option<Vec<u8>> {
+    None
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Replay {
     pub enabled: bool,
@@ -120,64 +162,46 @@ impl Egregoria {
     }
 
     pub fn from_replay(replay: Replay) -> Egregoria {
-        Self::_new(EgregoriaOptions::default(), Some(replay))
+        let mut player = ReplayPlayer::new(replay, u32::MAX);
+        player.fast_forward_to_end();
+        player.into_live()
     }
 
     pub fn new_with_options(opts: EgregoriaOptions) -> Egregoria {
-        Self::_new(opts, None)
+        let mut goria = Self::empty();
+
+        info!("Seed is {}", opts.seed);
+        info!("{:?}", opts);
+
+        Init(Box::new(opts)).apply(&mut goria);
+
+        let start_commands: Vec<(u32, WorldCommand)> =
+            common::saveload::JSON::decode(START_COMMANDS.as_bytes()).unwrap();
+
+        for (_, command) in start_commands {
+            command.apply(&mut goria);
+        }
+
+        // The start of the world cannot be undone
+        goria.write::<UndoHistory>().forget();
+        goria.write::<CommandInverses>().0.clear();
+
+        goria
     }
 
-    fn _new(opts: EgregoriaOptions, replay: Option<Replay>) -> Egregoria {
+    /// A world with only the initialized resources, before any command (even `Init`) is applied
+    pub(crate) fn empty() -> Egregoria {
         let mut goria = Egregoria {
             world: Default::default(),
             resources: Default::default(),
         };
 
-        info!("Seed is {}", RNG_SEED);
-        info!("{:?}", opts);
-
         unsafe {
             for s in &INIT_FUNCS {
                 (s.f)(&mut goria);
             }
         }
 
-        if let Some(replay) = replay {
-            let mut schedule = Egregoria::schedule();
-            let mut pastt = Tick::default();
-            let mut idx = 0;
-
-            // iterate through tick grouped commands
-            while idx < replay.commands.len() {
-                let curt = replay.commands[idx].0;
-                while pastt < curt {
-                    goria.tick(&mut schedule, &[]);
-                    pastt.0 += 1;
-                }
-
-                let idx_start = idx;
-                while idx < replay.commands.len() && replay.commands[idx].0 == curt {
-                    idx += 1;
-                }
-                let command_slice = &replay.commands[idx_start..idx];
-
-                log::info!("[replay] acttick {:?} ({})", pastt, command_slice.len());
-                goria.tick(&mut schedule, command_slice.iter().map(|(_, c)| c));
-                pastt.0 += 1;
-            }
-
-            return goria;
-        }
-
-        Init(Box::new(opts)).apply(&mut goria);
-
-        let start_commands: Vec<(u32, WorldCommand)> =
-            common::saveload::JSON::decode(START_COMMANDS.as_bytes()).unwrap();
-
-        for (_, command) in start_commands {
-            command.apply(&mut goria);
-        }
-
         goria
     }
 
@@ -207,6 +231,7 @@ impl Egregoria {
 
         {
             profiling::scope!("applying commands");
+            self.write::<CommandInverses>().0.clear();
             for command in commands {
                 command.apply(self);
             }
@@ -222,21 +247,51 @@ impl Egregoria {
         self.resources.get::<Tick>().unwrap().0
     }
 
+    /// Hashes of the world and of every saved resource, keyed by name.
+    /// Resources are serialized one at a time so no full copy of the world is kept around.
     pub fn hashes(&self) -> BTreeMap<String, u64> {
         let mut hashes = BTreeMap::new();
         let ser = common::saveload::Bincode::encode(&SerWorld(&self.world)).unwrap();
         hashes.insert("world".to_string(), common::hash_u64(&*ser));
+        drop(ser);
 
         unsafe {
             for l in &SAVELOAD_FUNCS {
-                let v = (l.save)(self);
-                hashes.insert(l.name.to_string(), common::hash_u64(&*v));
+                hashes.insert(l.name.to_string(), common::hash_u64(&*(l.save)(self)));
             }
         }
 
         hashes
     }
 
+    /// Bincode serialization of the world or of a saved resource, as hashed by `hashes`
+    pub fn serialized_resource(&self, name: &str) -> Option<Vec<u8>> {
+        if name == "world" {
+            return common::saveload::Bincode::encode(&SerWorld(&self.world)).ok();
+        }
+        unsafe {
+            SAVELOAD_FUNCS
+                .iter()
+                .find(|l| l.name == name)
+                .map(|l| (l.save)(self))
+        }
+    }
+
+    /// Bincode serialization of the world and of every saved resource, keyed by name
+    pub fn serialized_resources(&self) -> BTreeMap<String, Vec<u8>> {
+        let mut res = BTreeMap::new();
+        let ser = common::saveload::Bincode::encode(&SerWorld(&self.world)).unwrap();
+        res.insert("world".to_string(), ser);
+
+        unsafe {
+            for l in &SAVELOAD_FUNCS {
+                res.insert(l.name.to_string(), (l.save)(self));
+            }
+        }
+
+        res
+    }
+
     pub fn load_replay_from_disk(save_name: &str) -> Option<Replay> {
         let path = format!("{}_replay", save_name);
         let replay: Replay = common::saveload::JSON::load(&path)?;
@@ -369,18 +424,54 @@ impl<'de> Deserialize<'de> for Egregoria {
         log::info!("deserializing egregoria");
         let t = Instant::now();
 
-        let mut goriadeser = <EgregoriaDeser as Deserialize>::deserialize(deserializer)?;
+        let goriadeser = <EgregoriaDeser as Deserialize>::deserialize(deserializer)?;
 
         log::info!(
             "took {}s to deserialize base deser",
             t.elapsed().as_secs_f32()
         );
 
+        let migrations = unsafe { &MIGRATIONS };
+        let goria = Self::from_deser(goriadeser, migrations).map_err(Error::custom)?;
+
+        log::info!(
+            "took {}s to deserialize in total",
+            t.elapsed().as_secs_f32()
+        );
+
+        Ok(goria)
+    }
+}
+
+impl Egregoria {
+    /// Builds the simulation from a deserialized save, upgrading it with `migrations` first
+    /// if it was made by another version.
+    fn from_deser(
+        mut goriadeser: EgregoriaDeser,
+        migrations: &[Migration],
+    ) -> Result<Self, String> {
         if goriadeser.version != VERSION {
-            return Err(Error::custom(format!(
-                "couldn't load save, incompatible version! save is: {} - game is: {}",
-                goriadeser.version, VERSION
-            )));
+            let path = unwrap_or!(
+                migration_path(migrations, &goriadeser.version, VERSION),
+                return Err(format!(
+                    "couldn't load save, no migration from version {} to {}",
+                    goriadeser.version, VERSION
+                ))
+            );
+
+            for m in path {
+                log::info!("migrating save from {} to {}", m.from, m.to);
+                for (name, f) in &m.resources {
+                    if let Some(data) = goriadeser.res.remove(*name) {
+                        if let Some(migrated) = f(data) {
+                            goriadeser.res.insert(name.to_string(), migrated);
+                        }
+                    }
+                }
+                for f in &m.world {
+                    f(&mut goriadeser.world.0);
+                }
+            }
         }
 
         let mut goria = Self::new(false);
@@ -390,16 +481,12 @@ impl<'de> Deserialize<'de> for Egregoria {
         unsafe {
             for l in &SAVELOAD_FUNCS {
                 if let Some(data) = goriadeser.res.remove(l.name) {
-                    (l.load)(&mut goria, data);
+                    (l.load)(&mut goria, data)
+                        .map_err(|e| format!("couldn't load resource {}: {}", l.name, e))?;
                 }
             }
         }
 
-        log::info!(
-            "took {}s to deserialize in total",
-            t.elapsed().as_secs_f32()
-        );
-
         Ok(goria)
     }
 }
@@ -541,6 +628,13 @@ register!(
         ItineraryFollower => _21,
         LocomotiveReservation => _22,
         FreightStation => _23,
+        Bus => _24,
+        PassengerTrain => _25,
+        TrainStation => _26,
+        Account => _27,
+        CompanyProfit => _28,
+        Desires => _29,
+        Household => _30,
 );
 
 const START_COMMANDS: &str = r#"
@@ -0,0 +1,329 @@
+#![allow(clippy::indexing_slicing)] // cells are indexed modulo the chunk resolution
+
+use crate::map::{
+    Building, BuildingKind, ChunkID, LaneKind, Lot, Map, Terrain, CELL_SIZE, CHUNK_RESOLUTION,
+    CHUNK_SIZE,
+};
+use crate::souls::goods_company::{CompanyKind, GoodsCompanyRegistry};
+use crate::transportation::bus::BusLines;
+use geom::{vec2, Vec2};
+use hecs::World;
+use resources::Resources;
+use std::collections::BTreeMap;
+
+/// The values are stored in hundredths so that adding and removing a source is exact,
+/// and the field stays the same whether it was updated step by step or built at once
+const SCALE: f32 = 100.0;
+
+/// Per worker of a company
+const JOB_WEIGHT: i32 = 50;
+const JOB_RADIUS: i32 = 600;
+const SHOP_WEIGHT: i32 = 2000;
+const SHOP_RADIUS: i32 = 400;
+const TRAIN_STATION_WEIGHT: i32 = 4000;
+const TRAIN_STATION_RADIUS: i32 = 600;
+const BUS_STOP_WEIGHT: i32 = 1500;
+const BUS_STOP_RADIUS: i32 = 300;
+/// Per noisy lane, sampled every `NOISE_STEP` meters along the road
+const NOISE_WEIGHT: i32 = -300;
+const NOISE_RADIUS: i32 = 80;
+const NOISE_STEP: f32 = 40.0;
+/// Roads with fewer driving lanes than this are quiet, rails are always noisy
+const NOISY_ROAD_LANES: usize = 4;
+/// Per tree in the cell
+const TREE_WEIGHT: i32 = 100;
+const MAX_TREE_VALUE: i32 = 2000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LandValueLayer {
+    /// Closeness to workplaces, weighted by their number of workers
+    Jobs,
+    /// Closeness to stores
+    Shops,
+    /// Closeness to train stations and bus stops
+    Transit,
+    /// Big roads and rails nearby, always negative
+    Noise,
+    /// Trees in the cell
+    Nature,
+}
+
+impl LandValueLayer {
+    pub const ALL: [LandValueLayer; 5] = [
+        LandValueLayer::Jobs,
+        LandValueLayer::Shops,
+        LandValueLayer::Transit,
+        LandValueLayer::Noise,
+        LandValueLayer::Nature,
+    ];
+}
+
+const N_LAYERS: usize = LandValueLayer::ALL.len();
+
+/// Makes the land around it more or less attractive, fading linearly up to `radius` meters
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Source {
+    layer: LandValueLayer,
+    x: i32,
+    y: i32,
+    radius: i32,
+    weight: i32,
+}
+
+impl Source {
+    fn new(layer: LandValueLayer, pos: Vec2, radius: i32, weight: i32) -> Self {
+        Self {
+            layer,
+            x: pos.x as i32,
+            y: pos.y as i32,
+            radius,
+            weight,
+        }
+    }
+}
+
+struct LandValueChunk {
+    cells: [[[i32; N_LAYERS]; CHUNK_RESOLUTION]; CHUNK_RESOLUTION],
+    /// `Chunk::dirt_id` of the terrain chunk the trees were counted from
+    trees_dirt_id: u32,
+}
+
+/// How attractive the land is, on the same grid as the `Terrain` chunks.
+/// Built from the buildings, roads and trees of the map and the bus stops, it is updated
+/// at the start of the tick by adding the sources that appeared and removing the ones that
+/// disappeared since the last update.
+#[derive(Default)]
+pub struct LandValue {
+    chunks: BTreeMap<ChunkID, LandValueChunk>,
+    /// Sources from the map, sorted
+    map_sources: Vec<Source>,
+    /// Sources from the bus lines, sorted
+    bus_sources: Vec<Source>,
+    /// `Map::dirt_id` the map sources are up to date with
+    dirt_id: u32,
+    /// `Terrain::dirt_id` the trees are up to date with
+    terrain_dirt_id: u32,
+    /// `Map::generation` the field was built for, None before the first update
+    generation: Option<u32>,
+}
+
+impl LandValue {
+    /// Sum of all the layers at this position, 0 outside of the terrain
+    pub fn get(&self, pos: Vec2) -> f32 {
+        self.cell(pos)
+            .map_or(0.0, |c| c.iter().sum::<i32>() as f32 / SCALE)
+    }
+
+    pub fn layer(&self, pos: Vec2, layer: LandValueLayer) -> f32 {
+        self.cell(pos)
+            .map_or(0.0, |c| c[layer as usize] as f32 / SCALE)
+    }
+
+    pub fn lot(&self, lot: &Lot) -> f32 {
+        self.get(lot.shape.center())
+    }
+
+    pub fn building(&self, building: &Building) -> f32 {
+        self.get(building.obb.center())
+    }
+
+    fn cell(&self, pos: Vec2) -> Option<&[i32; N_LAYERS]> {
+        if pos.x < 0.0 || pos.y < 0.0 {
+            return None;
+        }
+        let (x, y) = ((pos.x / CELL_SIZE) as usize, (pos.y / CELL_SIZE) as usize);
+        let res = CHUNK_RESOLUTION;
+        let chunk = self.chunks.get(&((x / res) as u32, (y / res) as u32))?;
+        Some(&chunk.cells[y % res][x % res])
+    }
+
+    pub(crate) fn update(&mut self, map: &Map, registry: &GoodsCompanyRegistry, bus: &BusLines) {
+        let terrain = &map.terrain;
+        if self.generation != Some(map.generation.0) {
+            // New map or terrain, everything has to be built again
+            *self = Self {
+                chunks: terrain
+                    .chunks
+                    .keys()
+                    .map(|&id| {
+                        let chunk = LandValueChunk {
+                            cells: [[[0; N_LAYERS]; CHUNK_RESOLUTION]; CHUNK_RESOLUTION],
+                            trees_dirt_id: 0,
+                        };
+                        (id, chunk)
+                    })
+                    .collect(),
+                generation: Some(map.generation.0),
+                ..Self::default()
+            };
+        }
+
+        if self.dirt_id != map.dirt_id.0 {
+            self.dirt_id = map.dirt_id.0;
+            let sources = map_sources(map, registry);
+            apply_diff(&mut self.chunks, &self.map_sources, &sources);
+            self.map_sources = sources;
+        }
+
+        let sources = bus_sources(bus);
+        if sources != self.bus_sources {
+            apply_diff(&mut self.chunks, &self.bus_sources, &sources);
+            self.bus_sources = sources;
+        }
+
+        if self.terrain_dirt_id != terrain.dirt_id.0 {
+            self.terrain_dirt_id = terrain.dirt_id.0;
+            self.update_trees(terrain);
+        }
+    }
+
+    /// Counts the trees again in the chunks where some were removed
+    fn update_trees(&mut self, terrain: &Terrain) {
+        let nature = LandValueLayer::Nature as usize;
+        for (id, tchunk) in &terrain.chunks {
+            let chunk = unwrap_cont!(self.chunks.get_mut(id));
+            if chunk.trees_dirt_id == tchunk.dirt_id.0 {
+                continue;
+            }
+            chunk.trees_dirt_id = tchunk.dirt_id.0;
+
+            for row in chunk.cells.iter_mut() {
+                for cell in row.iter_mut() {
+                    cell[nature] = 0;
+                }
+            }
+            let origin = vec2(id.0 as f32, id.1 as f32) * CHUNK_SIZE as f32;
+            for tree in &tchunk.trees {
+                let p = (tree.pos - origin) / CELL_SIZE;
+                let (x, y) = (p.x as usize, p.y as usize);
+                if x < CHUNK_RESOLUTION && y < CHUNK_RESOLUTION {
+                    let v = &mut chunk.cells[y][x][nature];
+                    *v = (*v + TREE_WEIGHT).min(MAX_TREE_VALUE);
+                }
+            }
+        }
+    }
+}
+
+/// Removes the sources that are only in `old` and adds the ones only in `new`
+fn apply_diff(chunks: &mut BTreeMap<ChunkID, LandValueChunk>, old: &[Source], new: &[Source]) {
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        match (old.get(i), new.get(j)) {
+            (Some(a), Some(b)) if a == b => {
+                i += 1;
+                j += 1;
+            }
+            (Some(a), Some(b)) if a < b => {
+                apply(chunks, a, -1);
+                i += 1;
+            }
+            (Some(a), None) => {
+                apply(chunks, a, -1);
+                i += 1;
+            }
+            (_, Some(b)) => {
+                apply(chunks, b, 1);
+                j += 1;
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+fn apply(chunks: &mut BTreeMap<ChunkID, LandValueChunk>, s: &Source, sign: i32) {
+    let cell = CELL_SIZE as i32;
+    let res = CHUNK_RESOLUTION as i32;
+    let center = vec2(s.x as f32, s.y as f32);
+    let radius = s.radius as f32;
+
+    for y in ((s.y - s.radius) / cell).max(0)..=(s.y + s.radius) / cell {
+        for x in ((s.x - s.radius) / cell).max(0)..=(s.x + s.radius) / cell {
+            let chunk = unwrap_cont!(chunks.get_mut(&((x / res) as u32, (y / res) as u32)));
+            let pos = vec2(x as f32 + 0.5, y as f32 + 0.5) * CELL_SIZE;
+            let d = pos.distance(center);
+            if d >= radius {
+                continue;
+            }
+            let v = (s.weight as f32 * (1.0 - d / radius)) as i32;
+            chunk.cells[(y % res) as usize][(x % res) as usize][s.layer as usize] += sign * v;
+        }
+    }
+}
+
+fn map_sources(map: &Map, registry: &GoodsCompanyRegistry) -> Vec<Source> {
+    use LandValueLayer::*;
+
+    let mut sources = vec![];
+    for b in map.buildings().values() {
+        let pos = b.obb.center();
+        match b.kind {
+            BuildingKind::GoodsCompany(id) => {
+                let descr = unwrap_cont!(registry.descriptions.get(id));
+                if descr.n_workers > 0 {
+                    let weight = descr.n_workers * JOB_WEIGHT;
+                    sources.push(Source::new(Jobs, pos, JOB_RADIUS, weight));
+                }
+                if matches!(descr.kind, CompanyKind::Store) {
+                    sources.push(Source::new(Shops, pos, SHOP_RADIUS, SHOP_WEIGHT));
+                }
+            }
+            BuildingKind::TrainStation => {
+                sources.push(Source::new(
+                    Transit,
+                    pos,
+                    TRAIN_STATION_RADIUS,
+                    TRAIN_STATION_WEIGHT,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    for road in map.roads().values() {
+        let mut driving = 0;
+        let mut rails = 0;
+        for (_, kind) in road.lanes_iter() {
+            match kind {
+                LaneKind::Driving | LaneKind::Bus => driving += 1,
+                LaneKind::Rail => rails += 1,
+                _ => {}
+            }
+        }
+        let mut noisy = rails;
+        if driving >= NOISY_ROAD_LANES {
+            noisy += driving;
+        }
+        if noisy == 0 {
+            continue;
+        }
+        let weight = NOISE_WEIGHT * noisy as i32;
+        for (p, _) in road.points().equipoints_dir(NOISE_STEP, false) {
+            sources.push(Source::new(Noise, p.xy(), NOISE_RADIUS, weight));
+        }
+    }
+
+    sources.sort_unstable();
+    sources
+}
+
+fn bus_sources(bus: &BusLines) -> Vec<Source> {
+    let transit = LandValueLayer::Transit;
+    let mut sources: Vec<_> = bus
+        .iter()
+        .flat_map(|(_, line)| &line.desc.stops)
+        .map(|stop| Source::new(transit, stop.xy(), BUS_STOP_RADIUS, BUS_STOP_WEIGHT))
+        .collect();
+    sources.sort_unstable();
+    sources
+}
+
+/// Keeps the land value up to date with the map, runs at the start of the tick
+#[profiling::function]
+pub fn land_value_update_system(_: &mut World, resources: &mut Resources) {
+    resources.get_mut::<LandValue>().unwrap().update(
+        &resources.get::<Map>().unwrap(),
+        &resources.get::<GoodsCompanyRegistry>().unwrap(),
+        &resources.get::<BusLines>().unwrap(),
+    );
+}
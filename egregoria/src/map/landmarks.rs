@@ -0,0 +1,228 @@
+#![allow(clippy::indexing_slicing)] // node indices come from the tables themselves
+
+use crate::map::{free_flow_time, IntersectionID, LaneID, LaneKind, Map};
+use hecs::World;
+use ordered_float::OrderedFloat;
+use resources::Resources;
+use slotmap::{Key, SecondaryMap};
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BinaryHeap;
+use std::hash::{Hash, Hasher};
+
+/// Number of landmarks of each graph, more landmarks give tighter bounds but take more memory
+/// and make the rebuilds longer
+const N_LANDMARKS: usize = 8;
+
+/// Shortest distances from and to a few landmarks, used as an A* heuristic (ALT).
+/// For any landmark L, the triangle inequality gives d(a, b) >= d(L, b) - d(L, a)
+/// and d(a, b) >= d(a, L) - d(b, L), which is a much tighter bound than the straight line.
+///
+/// The landmarks are rebuilt at the start of the tick when the lanes or turns changed,
+/// and are not used in between so that pathfinding stays deterministic.
+#[derive(Default)]
+pub struct Landmarks {
+    /// `Map::dirt_id` the landmarks are up to date with
+    dirt_id: u32,
+    graph_hash: u64,
+    /// Directed graph of lanes connected by turns, in seconds at the speed limit
+    lanes: LandmarkTable<LaneID>,
+    /// Undirected graph of intersections connected by walking lanes, in meters
+    walking: LandmarkTable<IntersectionID>,
+}
+
+struct LandmarkTable<K: Key> {
+    index: SecondaryMap<K, u32>,
+    /// `from[l][i]` is the distance from the landmark `l` to the node `i`
+    from: Vec<Vec<f32>>,
+    /// `to[l][i]` is the distance from the node `i` to the landmark `l`
+    to: Vec<Vec<f32>>,
+}
+
+impl<K: Key> Default for LandmarkTable<K> {
+    fn default() -> Self {
+        Self {
+            index: SecondaryMap::new(),
+            from: vec![],
+            to: vec![],
+        }
+    }
+}
+
+impl Landmarks {
+    /// Lower bound of the time to go from the end of lane `a` to the end of lane `b`, in seconds
+    pub fn lane_bound(&self, a: LaneID, b: LaneID) -> Option<f32> {
+        self.lanes.bound(a, b)
+    }
+
+    /// Lower bound of the walking distance between two intersections, in meters
+    pub fn walking_bound(&self, a: IntersectionID, b: IntersectionID) -> Option<f32> {
+        self.walking.bound(a, b)
+    }
+
+    pub(crate) fn is_up_to_date(&self, map: &Map) -> bool {
+        self.dirt_id == map.dirt_id.0
+    }
+
+    /// Rebuilds the tables if the lanes or turns changed since the last update
+    pub(crate) fn update(&mut self, map: &Map) {
+        if self.is_up_to_date(map) {
+            return;
+        }
+        self.dirt_id = map.dirt_id.0;
+
+        // Most map changes (buildings, lots..) don't touch the road graph
+        let hash = graph_hash(map);
+        if hash == self.graph_hash {
+            return;
+        }
+        self.graph_hash = hash;
+
+        let (lanes, walking) = rayon::join(|| build_lanes(map), || build_walking(map));
+        self.lanes = lanes;
+        self.walking = walking;
+    }
+}
+
+impl<K: Key> LandmarkTable<K> {
+    fn bound(&self, a: K, b: K) -> Option<f32> {
+        let a = *self.index.get(a)? as usize;
+        let b = *self.index.get(b)? as usize;
+
+        let mut best = 0.0f32;
+        for (from, to) in self.from.iter().zip(&self.to) {
+            // Infinite distances mean the landmark can't be reached, they don't bound anything
+            let v = from[b] - from[a];
+            if v.is_finite() {
+                best = best.max(v);
+            }
+            let v = to[a] - to[b];
+            if v.is_finite() {
+                best = best.max(v);
+            }
+        }
+        Some(best)
+    }
+
+    /// Picks the landmarks one by one, each one as far as possible from the previous ones
+    fn build(index: SecondaryMap<K, u32>, edges: Vec<(u32, u32, f32)>) -> Self {
+        let n_nodes = index.len();
+        let mut fwd = vec![vec![]; n_nodes];
+        let mut bwd = vec![vec![]; n_nodes];
+        for (a, b, cost) in edges {
+            fwd[a as usize].push((b, cost));
+            bwd[b as usize].push((a, cost));
+        }
+
+        let mut from = vec![];
+        let mut to = vec![];
+        let mut closest = vec![f32::INFINITY; n_nodes];
+        let mut next = 0;
+
+        while from.len() < N_LANDMARKS.min(n_nodes) {
+            let (f, t) = rayon::join(|| dijkstra(&fwd, next), || dijkstra(&bwd, next));
+            for (c, d) in closest.iter_mut().zip(&f) {
+                *c = c.min(*d);
+            }
+            from.push(f);
+            to.push(t);
+
+            // Unreachable nodes come first so that every connected part gets a landmark
+            let (i, d) = unwrap_or!(
+                closest
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|&(i, &d)| (OrderedFloat(d), Reverse(i))),
+                break
+            );
+            if *d <= 0.0 {
+                break;
+            }
+            next = i;
+        }
+
+        Self { index, from, to }
+    }
+}
+
+fn index_of<K: Key>(keys: impl Iterator<Item = K>) -> SecondaryMap<K, u32> {
+    keys.enumerate().map(|(i, k)| (k, i as u32)).collect()
+}
+
+fn dijkstra(adj: &[Vec<(u32, f32)>], source: usize) -> Vec<f32> {
+    let mut dist = vec![f32::INFINITY; adj.len()];
+    let mut heap = BinaryHeap::new();
+    dist[source] = 0.0;
+    heap.push(Reverse((OrderedFloat(0.0), source as u32)));
+
+    while let Some(Reverse((OrderedFloat(d), node))) = heap.pop() {
+        let node = node as usize;
+        if d > dist[node] {
+            continue;
+        }
+        for &(next, cost) in &adj[node] {
+            let nd = d + cost;
+            if nd < dist[next as usize] {
+                dist[next as usize] = nd;
+                heap.push(Reverse((OrderedFloat(nd), next)));
+            }
+        }
+    }
+    dist
+}
+
+/// Same graph and costs as `vehicle_path`: going through a turn costs the time to drive its
+/// destination lane
+fn build_lanes(map: &Map) -> LandmarkTable<LaneID> {
+    let index = index_of(map.lanes.keys());
+
+    let mut edges = vec![];
+    for (id, lane) in &map.lanes {
+        let inter = unwrap_cont!(map.intersections.get(lane.dst));
+        for (turn, _) in inter.turns_from(id) {
+            let dst = unwrap_cont!(map.lanes.get(turn.dst));
+            edges.push((index[id], index[turn.dst], free_flow_time(dst)));
+        }
+    }
+
+    LandmarkTable::build(index, edges)
+}
+
+fn build_walking(map: &Map) -> LandmarkTable<IntersectionID> {
+    let index = index_of(map.intersections.keys());
+
+    let mut edges = vec![];
+    for lane in map.lanes.values() {
+        if lane.kind != LaneKind::Walking {
+            continue;
+        }
+        let (src, dst) = unwrap_cont!(index.get(lane.src).zip(index.get(lane.dst)));
+        let length = lane.points.length();
+        edges.push((*src, *dst, length));
+        edges.push((*dst, *src, length));
+    }
+
+    LandmarkTable::build(index, edges)
+}
+
+fn graph_hash(map: &Map) -> u64 {
+    let mut h = DefaultHasher::new();
+    for (id, lane) in &map.lanes {
+        id.hash(&mut h);
+        lane.kind.hash(&mut h);
+        lane.points.length().to_bits().hash(&mut h);
+        lane.speed_limit.to_bits().hash(&mut h);
+    }
+    for inter in map.intersections.values() {
+        for turn in inter.turns() {
+            turn.id.hash(&mut h);
+        }
+    }
+    h.finish()
+}
+
+/// Keeps the landmarks up to date, runs first so that every path of the tick uses them
+#[profiling::function]
+pub fn landmarks_update_system(_: &mut World, resources: &mut Resources) {
+    resources.get_mut::<Map>().unwrap().update_landmarks();
+}
@@ -1,15 +1,34 @@
-use crate::map::{Intersection, LaneID, Lanes, Roads, TrafficControl, TrafficLightSchedule};
+use crate::map::{
+    Intersection, IntersectionID, LaneID, LaneKind, Lanes, Map, RoadID, Roads, TrafficBehavior,
+    TrafficControl, TrafficLightSchedule,
+};
 use egui_inspect::{egui, egui::Ui, Inspect, InspectArgs};
 use serde::{Deserialize, Serialize};
 
+/// Saved with the map as a bincode variant index: new policies must be appended so that
+/// older saves still load without a migration
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LightPolicy {
     NoLights,
     StopSigns,
     Lights,
     Auto,
+    /// Lights extend the green while vehicles are detected, see `SignalControllers`
+    Actuated,
+    /// Fixed time lights synchronized along a corridor, see `green_wave`.
+    /// `main_road` turns green `offset` seconds into the cycle
+    GreenWave {
+        main_road: RoadID,
+        offset: u32,
+    },
 }
 
+/// Length of one phase of fixed time lights, in seconds
+const CYCLE_SIZE: usize = 14;
+const ORANGE_LENGTH: usize = 4;
+/// Used when a road has no driving lanes to read the speed limit from, m/s
+const DEFAULT_WAVE_SPEED: f32 = 9.0;
+
 impl Default for LightPolicy {
     fn default() -> Self {
         LightPolicy::Auto
@@ -18,11 +37,11 @@ impl Default for LightPolicy {
 
 impl LightPolicy {
     pub fn apply(self, inter: &Intersection, lanes: &mut Lanes, roads: &Roads) {
-        let in_road_lanes: Vec<Vec<LaneID>> = inter
+        let in_road_lanes: Vec<(RoadID, Vec<LaneID>)> = inter
             .roads
             .iter()
             .map(|&x| {
-                roads
+                let lanes = roads
                     .get(x)
                     .into_iter()
                     .flat_map(|r| {
@@ -31,12 +50,13 @@ impl LightPolicy {
                             .filter(|(_, kind)| kind.needs_light())
                             .map(|&(id, _)| id)
                     })
-                    .collect::<Vec<_>>()
+                    .collect::<Vec<_>>();
+                (x, lanes)
             })
-            .filter(|v| !v.is_empty())
+            .filter(|(_, v)| !v.is_empty())
             .collect();
 
-        for incoming_lanes in &in_road_lanes {
+        for (_, incoming_lanes) in &in_road_lanes {
             for &lane in incoming_lanes {
                 unwrap_cont!(lanes.get_mut(lane)).control = TrafficControl::Always;
             }
@@ -48,7 +68,7 @@ impl LightPolicy {
                 Self::stop_signs(in_road_lanes, lanes);
             }
             LightPolicy::Lights => {
-                Self::lights(in_road_lanes, inter, lanes);
+                Self::lights(in_road_lanes, inter, lanes, None);
             }
             LightPolicy::Auto => {
                 if in_road_lanes.len() <= 2 {
@@ -56,34 +76,55 @@ impl LightPolicy {
                 }
 
                 if inter.turn_policy.left_turns {
-                    Self::lights(in_road_lanes, inter, lanes);
+                    Self::lights(in_road_lanes, inter, lanes, None);
                 } else {
                     Self::stop_signs(in_road_lanes, lanes);
                 }
             }
+            LightPolicy::Actuated => {
+                Self::actuated(in_road_lanes, lanes);
+            }
+            LightPolicy::GreenWave { main_road, offset } => {
+                Self::lights(in_road_lanes, inter, lanes, Some((main_road, offset)));
+            }
         }
     }
 
-    fn stop_signs(in_road_lanes: Vec<Vec<LaneID>>, lanes: &mut Lanes) {
-        for incoming_lanes in in_road_lanes {
+    fn stop_signs(in_road_lanes: Vec<(RoadID, Vec<LaneID>)>, lanes: &mut Lanes) {
+        for (_, incoming_lanes) in in_road_lanes {
             for lane in incoming_lanes {
                 unwrap_cont!(lanes.get_mut(lane)).control = TrafficControl::StopSign;
             }
         }
     }
 
-    fn lights(in_road_lanes: Vec<Vec<LaneID>>, inter: &Intersection, lanes: &mut Lanes) {
+    /// Opposite roads share a phase, `sync` makes the phase of a road start at a given time
+    fn lights(
+        in_road_lanes: Vec<(RoadID, Vec<LaneID>)>,
+        inter: &Intersection,
+        lanes: &mut Lanes,
+        sync: Option<(RoadID, u32)>,
+    ) {
         let n_cycles = (in_road_lanes.len() + 1) / 2;
-        let cycle_size = 14;
-        let orange_length = 4;
+        let cycle_size = CYCLE_SIZE;
+        let orange_length = ORANGE_LENGTH;
 
         let total_length = cycle_size * n_cycles;
 
-        let offset = inter.id.as_ffi();
-        let inter_offset: usize =
-            (common::rand::rand(offset as f32) * total_length as f32) as usize;
+        let synced_phase = sync.and_then(|(road, offset)| {
+            let i = in_road_lanes.iter().position(|(r, _)| *r == road)?;
+            Some((i % n_cycles, offset as usize))
+        });
+
+        let inter_offset: usize = match synced_phase {
+            Some((phase, offset)) => total_length - (offset + cycle_size * phase) % total_length,
+            None => {
+                let offset = inter.id.as_ffi();
+                (common::rand::rand(offset as f32) * total_length as f32) as usize
+            }
+        };
 
-        for (i, incoming_lanes) in in_road_lanes.into_iter().enumerate() {
+        for (i, (_, incoming_lanes)) in in_road_lanes.into_iter().enumerate() {
             let light = TrafficControl::Light(TrafficLightSchedule::from_basic(
                 cycle_size - orange_length,
                 orange_length,
@@ -96,6 +137,76 @@ impl LightPolicy {
             }
         }
     }
+
+    /// Phases are grouped like fixed time lights, the first one starts green
+    fn actuated(in_road_lanes: Vec<(RoadID, Vec<LaneID>)>, lanes: &mut Lanes) {
+        let n_cycles = (in_road_lanes.len() + 1) / 2;
+
+        for (i, (_, incoming_lanes)) in in_road_lanes.into_iter().enumerate() {
+            let phase = (i % n_cycles) as u8;
+            let light = TrafficControl::Actuated {
+                phase,
+                behavior: if phase == 0 {
+                    TrafficBehavior::GREEN
+                } else {
+                    TrafficBehavior::RED
+                },
+            };
+
+            for lane in incoming_lanes {
+                unwrap_cont!(lanes.get_mut(lane)).control = light;
+            }
+        }
+    }
+}
+
+/// Computes the policies that make the lights of a corridor turn green as a platoon
+/// coming from the first intersection reaches them, driving at the speed limit.
+/// Consecutive intersections must be connected by a road.
+/// Intersections with less than 3 roads don't need lights and are skipped.
+pub fn green_wave(map: &Map, corridor: &[IntersectionID]) -> Vec<(IntersectionID, LightPolicy)> {
+    let mut policies = vec![];
+    let mut travel_time = 0.0;
+
+    for (i, &id) in corridor.iter().enumerate() {
+        let inter = unwrap_cont!(map.intersections.get(id));
+
+        let connecting = |other: IntersectionID| {
+            inter.roads.iter().copied().find(|&r| {
+                let road = unwrap_ret!(map.roads.get(r), false);
+                road.src == other || road.dst == other
+            })
+        };
+
+        let upstream = i.checked_sub(1).and_then(|j| connecting(corridor[j]));
+        if let Some(road) = upstream.and_then(|r| map.roads.get(r)) {
+            let speed = road
+                .lanes_iter()
+                .filter(|(_, kind)| *kind == LaneKind::Driving)
+                .filter_map(|(lane, _)| map.lanes.get(lane))
+                .map(|lane| lane.speed_limit)
+                .reduce(f32::max)
+                .unwrap_or(DEFAULT_WAVE_SPEED);
+            travel_time += road.length() / speed;
+        }
+
+        let downstream = corridor.get(i + 1).and_then(|&next| connecting(next));
+        let main_road = unwrap_cont!(upstream.or(downstream));
+
+        if inter.roads.len() <= 2 {
+            continue;
+        }
+
+        policies.push((
+            id,
+            LightPolicy::GreenWave {
+                main_road,
+                offset: travel_time.round() as u32,
+            },
+        ));
+    }
+
+    policies
 }
 
 impl Inspect<LightPolicy> for LightPolicy {
@@ -110,11 +221,14 @@ impl Inspect<LightPolicy> for LightPolicy {
         _: &InspectArgs,
     ) -> bool {
         let p = data;
+        let cur = *p;
         let mut id = match p {
             LightPolicy::NoLights => 0,
             LightPolicy::StopSigns => 1,
             LightPolicy::Lights => 2,
             LightPolicy::Auto => 3,
+            LightPolicy::Actuated => 4,
+            LightPolicy::GreenWave { .. } => 5,
         };
 
         let tostr = |x: LightPolicy| match x {
@@ -122,18 +236,29 @@ impl Inspect<LightPolicy> for LightPolicy {
             LightPolicy::StopSigns => "Stop signs",
             LightPolicy::Lights => "Lights",
             LightPolicy::Auto => "Auto",
+            LightPolicy::Actuated => "Actuated",
+            LightPolicy::GreenWave { .. } => "Green wave",
         };
 
+        // Green waves are set up along a corridor, they can only be kept from here
         let get = |i| match i {
             0 => LightPolicy::NoLights,
             1 => LightPolicy::StopSigns,
             2 => LightPolicy::Lights,
             3 => LightPolicy::Auto,
+            4 => LightPolicy::Actuated,
+            5 => cur,
             _ => unreachable!(),
         };
 
+        let n = if matches!(cur, LightPolicy::GreenWave { .. }) {
+            6
+        } else {
+            5
+        };
+
         let changed = egui::ComboBox::from_label(label)
-            .show_index(ui, &mut id, 4, |i| tostr(get(i)).to_string())
+            .show_index(ui, &mut id, n, |i| tostr(get(i)).to_string())
             .changed();
         if changed {
             *p = get(id);
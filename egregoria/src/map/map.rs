@@ -1,15 +1,16 @@
 use crate::map::serializing::SerializedMap;
 use crate::map::{
-    Building, BuildingGen, BuildingID, BuildingKind, Intersection, IntersectionID, Lane, LaneID,
-    LaneKind, LanePattern, Lot, LotID, LotKind, ParkingSpotID, ParkingSpots, ProjectFilter,
-    ProjectKind, Road, RoadID, RoadSegmentKind, SpatialMap, Terrain,
+    Building, BuildingGen, BuildingID, BuildingKind, HeightEdit, Intersection, IntersectionID,
+    Landmarks, Lane, LaneID, LaneKind, LanePattern, Lot, LotID, LotKind, ParkingSpotID,
+    ParkingSpots, ProjectFilter, ProjectKind, Road, RoadID, RoadSegmentKind, SpatialMap, Terrain,
+    TravelTimes, Tree, CELL_SIZE, CHUNK_RESOLUTION,
 };
-use geom::{Circle, Intersect, Shape, Spline3, Vec2, Vec3};
+use geom::{vec2, Circle, Intersect, Shape, Spline3, Vec2, Vec3, AABB};
 use geom::{Polygon, OBB};
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use slotmap::DenseSlotMap;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::num::Wrapping;
 
 pub type Roads = DenseSlotMap<RoadID, Road>;
@@ -18,6 +19,9 @@ pub type Intersections = DenseSlotMap<IntersectionID, Intersection>;
 pub type Buildings = DenseSlotMap<BuildingID, Building>;
 pub type Lots = DenseSlotMap<LotID, Lot>;
 
+/// Intersections closer than this to the terrain follow it when it is edited, in meters
+const GROUNDED_TOLERANCE: f32 = 0.5;
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct MapProject {
     pub pos: Vec3,
@@ -35,6 +39,11 @@ pub struct Map {
     pub terrain: Terrain,
     pub parking: ParkingSpots,
     pub dirt_id: Wrapping<u32>,
+    /// Bumped when the map is loaded or its terrain generated, the data derived from the map
+    /// that is not updated incrementally has to be built again. Not saved.
+    pub generation: Wrapping<u32>,
+    pub(crate) travel_times: TravelTimes,
+    pub(crate) landmarks: Landmarks,
 }
 
 defer_serialize!(Map, SerializedMap);
@@ -57,8 +66,11 @@ impl Map {
             lots: Lots::default(),
             terrain: Terrain::default(),
             dirt_id: Wrapping(1),
+            generation: Wrapping(0),
             spatial_map: SpatialMap::default(),
             bkinds: Default::default(),
+            travel_times: TravelTimes::default(),
+            landmarks: Landmarks::default(),
         }
     }
 
@@ -130,6 +142,14 @@ impl Map {
             None => RoadSegmentKind::Straight,
         };
 
+        // Over water, the road has to be a bridge or a tunnel
+        let is_rail = pattern.lanes().any(|(kind, _, _)| kind.is_rail());
+        let points = Road::segment_points(from.pos, to.pos, connection_segment, is_rail);
+        if !self.terrain.can_cross_water(&points) {
+            log::warn!("did not connect: the road is too close to the water it crosses");
+            return None;
+        }
+
         let mut mk_inter = |proj: MapProject| {
             Some(match proj.kind {
                 ProjectKind::Ground => self.add_intersection(proj.pos),
@@ -270,6 +290,93 @@ impl Map {
         Some(road)
     }
 
+    /// Edits the terrain and returns the edits that revert it, with the trees it put under water.
+    /// The intersections that were on the ground follow it along with their roads,
+    /// the elevated ones such as bridges don't move. Buildings and lots follow the ground.
+    pub fn terraform(&mut self, edits: &[HeightEdit]) -> (Vec<HeightEdit>, Vec<Tree>) {
+        info!("terraform {} points", edits.len());
+        self.dirt_id += Wrapping(1);
+
+        let res = CHUNK_RESOLUTION as f32;
+        let Some(area) = edits
+            .iter()
+            .map(|e| {
+                let chunk = vec2(e.chunk.0 as f32, e.chunk.1 as f32) * res;
+                (chunk + vec2(e.x as f32, e.y as f32)) * CELL_SIZE
+            })
+            .map(|p| AABB::new(p, p))
+            .reduce(|a, b| a.union(b))
+        else {
+            return (vec![], vec![]);
+        };
+        let area = area.expand(CELL_SIZE * 2.0);
+
+        let grounded: Vec<IntersectionID> = self
+            .intersections
+            .values()
+            .filter(|i| area.contains(i.pos.xy()))
+            .filter(|i| {
+                self.terrain
+                    .height(i.pos.xy())
+                    .map_or(false, |h| (h - i.pos.z).abs() < GROUNDED_TOLERANCE)
+            })
+            .map(|i| i.id)
+            .collect();
+
+        let (inverse, culled) = self.terrain.apply_edits(edits);
+
+        let mut moved_roads = BTreeSet::new();
+        for id in grounded {
+            let inter = unwrap_cont!(self.intersections.get_mut(id));
+            inter.pos.z = unwrap_cont!(self.terrain.height(inter.pos.xy()));
+            moved_roads.extend(inter.roads.iter().copied());
+        }
+
+        let mut to_invalidate = BTreeSet::new();
+        for id in moved_roads {
+            let road = unwrap_cont!(self.roads.get_mut(id));
+            let src = unwrap_cont!(self.intersections.get(road.src));
+            let dst = unwrap_cont!(self.intersections.get(road.dst));
+            road.regenerate_points(src, dst);
+            to_invalidate.insert(road.src);
+            to_invalidate.insert(road.dst);
+            self.spatial_map.update(id, road.boldline());
+        }
+        for id in to_invalidate {
+            self.invalidate(id);
+        }
+
+        for lot in self.lots.values_mut() {
+            if area.contains(lot.shape.center()) {
+                lot.height = self
+                    .terrain
+                    .height(lot.shape.center())
+                    .unwrap_or(lot.height);
+            }
+        }
+
+        for b in self.buildings.values_mut() {
+            if !area.contains(b.obb.center()) {
+                continue;
+            }
+            let h = unwrap_cont!(self.terrain.height(b.obb.center()));
+            let dz = h - b.height;
+            if dz == 0.0 {
+                continue;
+            }
+            for (poly, _) in &mut b.mesh.faces {
+                for v in poly {
+                    v.z += dz;
+                }
+            }
+            b.door_pos.z += dz;
+            b.height = h;
+        }
+
+        self.check_invariants();
+        (inverse, culled)
+    }
+
     pub fn set_lot_kind(&mut self, lot: LotID, kind: LotKind) {
         match self.lots.get_mut(lot) {
             Some(lot) => {
@@ -280,11 +387,18 @@ impl Map {
         }
     }
 
+    pub fn update_landmarks(&mut self) {
+        let mut landmarks = std::mem::take(&mut self.landmarks);
+        landmarks.update(self);
+        self.landmarks = landmarks;
+    }
+
     pub fn clear(&mut self) {
         info!("clear");
         let before = std::mem::replace(self, Self::empty());
         self.terrain = before.terrain;
         self.dirt_id = before.dirt_id + Wrapping(1);
+        self.generation = before.generation + Wrapping(1);
 
         self.check_invariants();
     }
@@ -558,6 +672,13 @@ impl Map {
     pub fn lanes(&self) -> &Lanes {
         &self.lanes
     }
+    pub fn travel_times(&self) -> &TravelTimes {
+        &self.travel_times
+    }
+    /// None when the roads changed since the last `update_landmarks`
+    pub fn landmarks(&self) -> Option<&Landmarks> {
+        Some(&self.landmarks).filter(|l| l.is_up_to_date(self))
+    }
     pub fn intersections(&self) -> &Intersections {
         &self.intersections
     }
@@ -20,13 +20,19 @@ pub use objects::*;
 
 pub mod procgen {
     mod building;
+    mod city;
     pub mod heightmap;
+    mod osm;
     mod presets;
 
     pub use building::*;
+    pub use city::*;
+    pub use osm::*;
     pub use presets::*;
 }
 
+mod land_value;
+mod landmarks;
 mod light_policy;
 #[allow(clippy::module_inception)]
 mod map;
@@ -35,19 +41,25 @@ mod serializing;
 mod spatial_map;
 mod terrain;
 mod traffic_control;
+mod travel_times;
 mod traversable;
 mod turn_policy;
 
 // Use self or else it would be ambiguous with "pathfinding" crate
 pub use self::pathfinding::*;
+pub use land_value::*;
+pub use landmarks::*;
 pub use light_policy::*;
 pub use map::*;
 pub use spatial_map::*;
 pub use terrain::*;
 pub use traffic_control::*;
+pub use travel_times::*;
 pub use traversable::*;
 pub use turn_policy::*;
 
+pub(crate) use serializing::{migrate_map_travel_times, migrate_map_water};
+
 pub use ::pathfinding as pathfinding_crate;
 
 pub const CROSSWALK_WIDTH: f32 = 2.0;
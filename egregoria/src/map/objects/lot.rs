@@ -9,10 +9,12 @@ new_key_type! {
     pub struct LotID;
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LotKind {
     Unassigned,
     Residential,
+    Commercial,
+    Industrial,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
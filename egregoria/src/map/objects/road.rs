@@ -72,9 +72,9 @@ impl Road {
         spatial: &mut SpatialMap,
     ) -> RoadID {
         let width = lane_pattern.width();
-        let points = Self::generate_points(
-            src,
-            dst,
+        let points = Self::segment_points(
+            src.pos,
+            dst.pos,
             segment,
             lane_pattern.lanes().any(|(a, _, _)| a.is_rail()),
         );
@@ -113,6 +113,13 @@ impl Road {
         road.id
     }
 
+    /// Generates the points again after one of the intersections moved,
+    /// `update_lanes` must be called afterwards
+    pub(crate) fn regenerate_points(&mut self, src: &Intersection, dst: &Intersection) {
+        let precise = self.lanes_iter().any(|(_, kind)| kind.is_rail());
+        self.points = Self::segment_points(src.pos, dst.pos, self.segment, precise);
+    }
+
     pub fn is_one_way(&self) -> bool {
         self.lanes_forward.is_empty() || self.lanes_backward.is_empty()
     }
@@ -282,14 +289,13 @@ impl Road {
         }
     }
 
-    fn generate_points(
-        src: &Intersection,
-        dst: &Intersection,
+    /// Points of a road going from `from` to `to`, more of them if `precise` for the rails
+    pub fn segment_points(
+        from: Vec3,
+        to: Vec3,
         segment: RoadSegmentKind,
         precise: bool,
     ) -> PolyLine3 {
-        let from = src.pos;
-        let to = dst.pos;
         let diff = to - from;
 
         let spline = match segment {
@@ -1,5 +1,6 @@
 use crate::map::{
-    LaneID, LaneKind, LanePatternBuilder, Map, Traversable, TraverseDirection, TraverseKind, TurnID,
+    free_flow_time, LaneID, LaneKind, LanePatternBuilder, Map, TravelTimes, Traversable,
+    TraverseDirection, TraverseKind, TurnID,
 };
 use geom::{PolyLine3, Vec3};
 use ordered_float::OrderedFloat;
@@ -12,11 +13,12 @@ pub trait Pathfinder {
     fn local_route(&self, map: &Map, lane: LaneID, start: Vec3, end: Vec3) -> Option<PolyLine3>;
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum PathKind {
     Pedestrian,
     Vehicle,
     Rail,
+    Bus,
 }
 
 impl Pathfinder for PathKind {
@@ -25,6 +27,7 @@ impl Pathfinder for PathKind {
             PathKind::Pedestrian => PedestrianPath.path(map, start, end),
             PathKind::Vehicle => CarPath.path(map, start, end),
             PathKind::Rail => RailPath.path(map, start, end),
+            PathKind::Bus => BusPath.path(map, start, end),
         }
     }
 
@@ -33,6 +36,7 @@ impl Pathfinder for PathKind {
             PathKind::Pedestrian => PedestrianPath.nearest_lane(map, pos),
             PathKind::Vehicle => CarPath.nearest_lane(map, pos),
             PathKind::Rail => RailPath.nearest_lane(map, pos),
+            PathKind::Bus => BusPath.nearest_lane(map, pos),
         }
     }
 
@@ -41,6 +45,7 @@ impl Pathfinder for PathKind {
             PathKind::Pedestrian => PedestrianPath.local_route(map, lane, start, end),
             PathKind::Vehicle => CarPath.local_route(map, lane, start, end),
             PathKind::Rail => RailPath.local_route(map, lane, start, end),
+            PathKind::Bus => BusPath.local_route(map, lane, start, end),
         }
     }
 }
@@ -52,18 +57,28 @@ impl Pathfinder for PedestrianPath {
         let inters = &map.intersections;
         let lanes = &map.lanes;
 
-        let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
+        let end_lane = lanes.get(end)?;
+        let end_pos = inters.get(end_lane.dst)?.pos;
+        let (end_src, end_dst) = (end_lane.src, end_lane.dst);
+        let landmarks = map.landmarks();
 
         let heuristic = |t: &Traversable| {
-            let pos = unwrap_ret!(
-                inters.get(unwrap_ret!(
-                    t.destination_intersection(lanes),
-                    OrderedFloat(f32::INFINITY)
-                )),
+            let inter = unwrap_ret!(
+                t.destination_intersection(lanes),
                 OrderedFloat(f32::INFINITY)
-            )
-            .pos;
-
+            );
+
+            // The end lane can be reached from both sides
+            if let Some(l) = landmarks {
+                if let Some((a, b)) = l
+                    .walking_bound(inter, end_src)
+                    .zip(l.walking_bound(inter, end_dst))
+                {
+                    return OrderedFloat(a.min(b));
+                }
+            }
+
+            let pos = unwrap_ret!(inters.get(inter), OrderedFloat(f32::INFINITY)).pos;
             OrderedFloat(pos.distance(end_pos) * 1.3) // Inexact but (much) faster
         };
 
@@ -143,75 +158,40 @@ impl Pathfinder for RailPath {
     }
 }
 
-struct CarPath;
-
-impl Pathfinder for CarPath {
-    fn path(&self, map: &Map, start: Traversable, end: LaneID) -> Option<Vec<Traversable>> {
-        let inters = &map.intersections;
-        let lanes = &map.lanes;
-
-        let start_lane = start.destination_lane();
-
-        let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
+struct BusPath;
 
-        let dummy = LaneID::null();
-
-        const HEURISTIC_SPEED: f32 = LanePatternBuilder::new().speed_limit;
-
-        let heuristic = |&p: &LaneID| {
-            let pos = unwrap_ret!(
-                inters.get(unwrap_ret!(lanes.get(p), OrderedFloat(f32::INFINITY)).dst),
-                OrderedFloat(f32::INFINITY)
-            )
-            .pos;
-            OrderedFloat(pos.distance(end_pos) * 1.2 / HEURISTIC_SPEED) // Inexact but (much) faster
-        };
-
-        let successors = |&p: &LaneID| {
-            let l;
-            let p = if p == dummy {
-                l = lanes.get(start_lane);
-                start_lane
-            } else {
-                l = lanes.get(p);
-                p
-            };
-            l.and_then(|x| inters.get(x.dst))
-                .into_iter()
-                .flat_map(move |inter| {
-                    inter.turns_from(p).map(move |(x, _)| {
-                        let cost = lanes
-                            .get(x.dst)
-                            .map(|p| p.points.length() / p.speed_limit)
-                            .unwrap_or(f32::INFINITY);
-                        (x.dst, OrderedFloat(cost))
-                    })
-                })
-        };
+/// Cost factor of the bus lanes for buses
+const BUS_LANE_FACTOR: f32 = 0.6;
 
-        let (v, _) =
-            pathfinding::directed::astar::astar(&dummy, successors, heuristic, |p| *p == end)?;
+impl Pathfinder for BusPath {
+    fn path(&self, map: &Map, start: Traversable, end: LaneID) -> Option<Vec<Traversable>> {
+        // Bus lanes are preferred over regular lanes
+        vehicle_path(map, start, end, None, BUS_LANE_FACTOR, |kind| match kind {
+            LaneKind::Bus => Some(BUS_LANE_FACTOR),
+            _ => Some(1.0),
+        })
+    }
 
-        let mut path = Vec::with_capacity(v.len() * 2);
-        path.push(start);
+    fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
+        map.nearest_lane(pos, LaneKind::Bus, Some(10.0))
+            .or_else(|| CarPath.nearest_lane(map, pos))
+    }
 
-        let mut last_id = start_lane;
+    fn local_route(&self, map: &Map, lane: LaneID, start: Vec3, end: Vec3) -> Option<PolyLine3> {
+        CarPath.local_route(map, lane, start, end)
+    }
+}
 
-        for lane in v.into_iter().skip(1) {
-            let inter_end = &inters.get(lanes.get(lane)?.src)?;
-            let id = TurnID::new(inter_end.id, last_id, lane, false);
-            path.push(Traversable::new(
-                TraverseKind::Turn(id),
-                TraverseDirection::Forward,
-            ));
-            path.push(Traversable::new(
-                TraverseKind::Lane(lane),
-                TraverseDirection::Forward,
-            ));
+struct CarPath;
 
-            last_id = lane;
-        }
-        Some(path)
+impl Pathfinder for CarPath {
+    fn path(&self, map: &Map, start: Traversable, end: LaneID) -> Option<Vec<Traversable>> {
+        // Cars avoid the lanes and turns that were observed to be slow
+        let times = Some(&map.travel_times).filter(|t| t.enabled);
+        vehicle_path(map, start, end, times, 1.0, |kind| match kind {
+            LaneKind::Bus => None,
+            _ => Some(1.0),
+        })
     }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
@@ -239,3 +219,187 @@ impl Pathfinder for CarPath {
         Some(PolyLine3::new(v))
     }
 }
+
+/// A* over lanes, `cost_factor` multiplies the travel time of a lane or forbids it when None.
+/// Travel times come from `times` when given, and from the lanes speed limit otherwise.
+/// Landmark bounds assume a cost factor of 1, they are scaled by `min_factor`, the smallest
+/// factor `cost_factor` returns, so that the heuristic never overestimates.
+fn vehicle_path(
+    map: &Map,
+    start: Traversable,
+    end: LaneID,
+    times: Option<&TravelTimes>,
+    min_factor: f32,
+    cost_factor: impl Fn(LaneKind) -> Option<f32>,
+) -> Option<Vec<Traversable>> {
+    let inters = &map.intersections;
+    let lanes = &map.lanes;
+
+    let start_lane = start.destination_lane();
+
+    let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
+
+    let dummy = LaneID::null();
+    let cost_factor = &cost_factor;
+
+    const HEURISTIC_SPEED: f32 = LanePatternBuilder::new().speed_limit;
+
+    let landmarks = map.landmarks();
+
+    let heuristic = |&p: &LaneID| {
+        if let Some(h) = landmarks.and_then(|l| l.lane_bound(p, end)) {
+            return OrderedFloat(h * min_factor);
+        }
+        let pos = unwrap_ret!(
+            inters.get(unwrap_ret!(lanes.get(p), OrderedFloat(f32::INFINITY)).dst),
+            OrderedFloat(f32::INFINITY)
+        )
+        .pos;
+        OrderedFloat(pos.distance(end_pos) * 1.2 / HEURISTIC_SPEED) // Inexact but (much) faster
+    };
+
+    let successors = |&p: &LaneID| {
+        let l;
+        let p = if p == dummy {
+            l = lanes.get(start_lane);
+            start_lane
+        } else {
+            l = lanes.get(p);
+            p
+        };
+        l.and_then(|x| inters.get(x.dst))
+            .into_iter()
+            .flat_map(move |inter| {
+                inter.turns_from(p).filter_map(move |(x, _)| {
+                    let lane = lanes.get(x.dst)?;
+                    let time = match times {
+                        Some(times) => times.turn_time(x) + times.lane_time(lane),
+                        None => free_flow_time(lane),
+                    };
+                    let cost = time * cost_factor(lane.kind)?;
+                    Some((x.dst, OrderedFloat(cost)))
+                })
+            })
+    };
+
+    let (v, _) = pathfinding::directed::astar::astar(&dummy, successors, heuristic, |p| *p == end)?;
+
+    let mut path = Vec::with_capacity(v.len() * 2);
+    path.push(start);
+
+    let mut last_id = start_lane;
+
+    for lane in v.into_iter().skip(1) {
+        let inter_end = &inters.get(lanes.get(lane)?.src)?;
+        let id = TurnID::new(inter_end.id, last_id, lane, false);
+        path.push(Traversable::new(
+            TraverseKind::Turn(id),
+            TraverseDirection::Forward,
+        ));
+        path.push(Traversable::new(
+            TraverseKind::Lane(lane),
+            TraverseDirection::Forward,
+        ));
+
+        last_id = lane;
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PathKind, Pathfinder};
+    use crate::map::procgen::{load_parismap, load_testfield};
+    use crate::map::{LaneID, LaneKind, Map, Traversable, TraverseDirection, TraverseKind};
+    use common::rand::rand2;
+    use easybench::bench;
+    use geom::Vec2;
+    use std::time::Instant;
+
+    fn random_pairs(m: &Map, kind: LaneKind) -> Vec<(LaneID, LaneID)> {
+        let lanes: Vec<LaneID> = m
+            .lanes
+            .values()
+            .filter(|l| l.kind == kind)
+            .map(|l| l.id)
+            .collect();
+        let pick = |i: usize, seed: f32| {
+            lanes[(rand2(i as f32, seed) * lanes.len() as f32) as usize % lanes.len()]
+        };
+        (0..200).map(|i| (pick(i, 1.0), pick(i, 2.0))).collect()
+    }
+
+    fn path(m: &Map, kind: PathKind, (a, b): (LaneID, LaneID)) -> Option<Vec<Traversable>> {
+        let start = Traversable::new(TraverseKind::Lane(a), TraverseDirection::Forward);
+        kind.path(m, start, b)
+    }
+
+    const KINDS: [(PathKind, LaneKind); 2] = [
+        (PathKind::Vehicle, LaneKind::Driving),
+        (PathKind::Pedestrian, LaneKind::Walking),
+    ];
+
+    /// Runs `f` without the landmarks, so that plain A* is used
+    fn with_astar<T>(m: &mut Map, f: impl FnOnce(&Map) -> T) -> T {
+        let landmarks = std::mem::take(&mut m.landmarks);
+        assert!(m.landmarks().is_none());
+        let v = f(m);
+        m.landmarks = landmarks;
+        v
+    }
+
+    fn check_alt_is_optimal(m: &mut Map, kind: PathKind, pairs: &[(LaneID, LaneID)]) {
+        let alt: Vec<_> = pairs.iter().map(|&p| path(m, kind, p)).collect();
+        let astar: Vec<_> = with_astar(m, |m| pairs.iter().map(|&p| path(m, kind, p)).collect());
+
+        for (alt, astar) in alt.iter().zip(&astar) {
+            assert_eq!(alt.is_some(), astar.is_some());
+            if let (PathKind::Vehicle, Some(alt), Some(astar)) = (kind, alt, astar) {
+                // The landmark bounds are exact lower bounds, unlike the straight line heuristic
+                let cost = |p: &[Traversable]| m.travel_times.path_time(m, p).unwrap();
+                assert!(cost(&alt[1..]) <= cost(&astar[1..]) + 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn alt_paths_are_as_short_as_astar() {
+        let mut m = Map::empty();
+        load_testfield(&mut m, Vec2::ZERO, 10, 100.0);
+        m.update_landmarks();
+        assert!(m.landmarks().is_some());
+
+        for (kind, lane_kind) in KINDS {
+            let pairs = random_pairs(&m, lane_kind);
+            check_alt_is_optimal(&mut m, kind, &pairs);
+        }
+    }
+
+    #[test]
+    #[ignore = "loads the Paris map, run with --ignored --nocapture"]
+    fn bench_pathfinding() {
+        let mut m = Map::default();
+        load_parismap(&mut m);
+
+        let t = Instant::now();
+        m.update_landmarks();
+        println!("landmarks built in {:?}", t.elapsed());
+
+        for (kind, lane_kind) in KINDS {
+            let pairs = random_pairs(&m, lane_kind);
+            let bench_paths = |m: &Map| {
+                let mut i = 0;
+                bench(|| {
+                    i += 1;
+                    path(m, kind, pairs[i % pairs.len()])
+                })
+            };
+
+            let alt = bench_paths(&m);
+            let astar = with_astar(&mut m, bench_paths);
+            println!("{:?}\n  A*:  {}\n  ALT: {}", kind, astar, alt);
+
+            check_alt_is_optimal(&mut m, kind, &pairs);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use crate::map::{
+    IntersectionID, LanePattern, LanePatternBuilder, Lot, Map, MapProject, ProjectFilter,
+    ProjectKind, RoadID,
+};
+use crate::utils::rand_provider::RandProvider;
+use common::FastSet;
+use geom::{Vec2, Vec3, OBB};
+use std::collections::VecDeque;
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+/// Largest diameter of a generated city, in meters
+pub const MAX_CITY_SIZE: f32 = 3000.0;
+/// Proposed roads handled before the generation stops, even if the city could still grow
+const MAX_PROPOSALS: usize = 10_000;
+/// Steepest slope (rise over run) a generated road can climb
+const MAX_SLOPE: f32 = 0.12;
+/// Proposed ends closer than this to an intersection or a road join it instead
+const SNAP_DIST: f32 = 35.0;
+/// Turns tried in order when the straight way is too steep or blocked, so roads follow contours
+const TURNS: [f32; 5] = [0.0, 0.3, -0.3, 0.6, -0.6];
+/// Random deviation of a road continuing straight, in radians
+const MAX_DEVIATION: f32 = 0.15;
+const WATER_CHECK_STEP: f32 = 10.0;
+
+const ARTERIAL_BRANCH_CHANCE: f32 = 0.2;
+const STREET_SEED_CHANCE: f32 = 0.9;
+const STREET_CONTINUE_CHANCE: f32 = 0.9;
+const STREET_BRANCH_CHANCE: f32 = 0.4;
+/// How many segments a local street can be away from the arterial it started from
+const STREET_MAX_DEPTH: u32 = 6;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RoadClass {
+    Arterial,
+    Street,
+}
+
+impl RoadClass {
+    fn length(self) -> f32 {
+        match self {
+            RoadClass::Arterial => 160.0,
+            RoadClass::Street => 90.0,
+        }
+    }
+
+    fn pattern(self) -> LanePattern {
+        match self {
+            RoadClass::Arterial => LanePatternBuilder::new()
+                .n_lanes(2)
+                .parking(false)
+                .speed_limit(15.0)
+                .build(),
+            RoadClass::Street => LanePatternBuilder::new().build(),
+        }
+    }
+}
+
+/// A road waiting to be grown from an existing intersection
+struct Proposal {
+    from: IntersectionID,
+    dir: Vec2,
+    class: RoadClass,
+    depth: u32,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CityStats {
+    /// The new roads, including the halves of the existing roads that were split
+    pub roads: Vec<RoadID>,
+    pub lots: usize,
+}
+
+struct CityGen {
+    rng: RandProvider,
+    center: Vec2,
+    radius: f32,
+}
+
+/// Diameter of the city generated for the requested `size`, clamped to `MAX_CITY_SIZE`
+pub fn city_size(size: f32) -> f32 {
+    if !size.is_finite() {
+        return 0.0;
+    }
+    size.clamp(0.0, MAX_CITY_SIZE)
+}
+
+/// Existing roads closer than this to the center can be split by the generated city
+pub fn city_reach(size: f32) -> f32 {
+    city_size(size) * 0.5 + SNAP_DIST
+}
+
+/// Grows a road network within `size` meters around `center`, deterministically from `seed`.
+/// Arterials are grown first from the center, then local streets branch off them.
+/// Roads avoid water and steep slopes, and lots are generated along every new road.
+/// The size is clamped with `city_size` and at most `MAX_PROPOSALS` roads are tried.
+pub fn generate_city(map: &mut Map, center: Vec2, size: f32, seed: u64) -> CityStats {
+    let time = std::time::Instant::now();
+    let mut stats = CityStats::default();
+    let size = city_size(size);
+    if !center.is_finite() || size == 0.0 {
+        return stats;
+    }
+
+    let Some(h) = map.terrain.height(center) else {
+        log::warn!("cannot generate a city outside of the terrain");
+        return stats;
+    };
+    if map.terrain.is_underwater(center) {
+        log::warn!("cannot generate a city in the water");
+        return stats;
+    }
+
+    let proj = map.project(
+        center.z(h),
+        SNAP_DIST,
+        ProjectFilter::INTER | ProjectFilter::ROAD,
+    );
+    let start = match proj.kind {
+        ProjectKind::Inter(id) => id,
+        ProjectKind::Road(id) => unwrap_ret!(map.split_road(id, proj.pos), stats),
+        _ => map.add_intersection(proj.pos),
+    };
+
+    let existing_roads: FastSet<RoadID> = map.roads.keys().collect();
+    let existing_lots = map.lots.len();
+
+    let mut gen = CityGen {
+        rng: RandProvider::new(seed),
+        center,
+        radius: size * 0.5,
+    };
+
+    let mut arterials = VecDeque::new();
+    let mut streets = VecDeque::new();
+
+    let angle = gen.rng.next_f32() * TAU;
+    for i in 0..4 {
+        arterials.push_back(Proposal {
+            from: start,
+            dir: Vec2::X.rotated_by_angle(angle + i as f32 * FRAC_PI_2),
+            class: RoadClass::Arterial,
+            depth: 0,
+        });
+    }
+
+    let mut n_proposals = 0;
+    while let Some(p) = arterials.pop_front().or_else(|| streets.pop_front()) {
+        n_proposals += 1;
+        if n_proposals > MAX_PROPOSALS {
+            log::warn!(
+                "stopped generating the city after {} proposed roads",
+                MAX_PROPOSALS
+            );
+            break;
+        }
+        let Some((to, dir)) = gen.grow(map, &p) else {
+            continue;
+        };
+        let perp = dir.perpendicular();
+        let propose = |queue: &mut VecDeque<_>, dir: Vec2, class, depth| {
+            queue.push_back(Proposal {
+                from: to,
+                dir,
+                class,
+                depth,
+            })
+        };
+
+        match p.class {
+            RoadClass::Arterial => {
+                let straight = gen.deviate(dir);
+                propose(&mut arterials, straight, RoadClass::Arterial, 0);
+                if gen.chance(ARTERIAL_BRANCH_CHANCE) {
+                    let branch = if gen.chance(0.5) { perp } else { -perp };
+                    propose(&mut arterials, branch, RoadClass::Arterial, 0);
+                }
+                for side in [1.0, -1.0] {
+                    if gen.chance(STREET_SEED_CHANCE) {
+                        propose(&mut streets, perp * side, RoadClass::Street, 0);
+                    }
+                }
+            }
+            RoadClass::Street => {
+                if p.depth >= STREET_MAX_DEPTH {
+                    continue;
+                }
+                if gen.chance(STREET_CONTINUE_CHANCE) {
+                    let straight = gen.deviate(dir);
+                    propose(&mut streets, straight, RoadClass::Street, p.depth + 1);
+                }
+                for side in [1.0, -1.0] {
+                    if gen.chance(STREET_BRANCH_CHANCE) {
+                        propose(&mut streets, perp * side, RoadClass::Street, p.depth + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    if map
+        .intersections
+        .get(start)
+        .map_or(false, |i| i.roads.is_empty())
+    {
+        map.remove_intersection(start);
+    }
+
+    // Later roads removed the lots of the earlier ones they got close to, fill the gaps
+    let new_roads: Vec<RoadID> = map
+        .roads
+        .keys()
+        .filter(|id| !existing_roads.contains(id))
+        .collect();
+    for &id in &new_roads {
+        Lot::generate_along_road(map, id);
+    }
+
+    stats.lots = map.lots.len().saturating_sub(existing_lots);
+    stats.roads = new_roads;
+
+    info!(
+        "generating city took {}ms: {} roads, {} lots",
+        time.elapsed().as_secs_f32() * 1000.0,
+        stats.roads.len(),
+        stats.lots
+    );
+
+    map.check_invariants();
+
+    stats
+}
+
+impl CityGen {
+    fn chance(&mut self, p: f32) -> bool {
+        self.rng.next_f32() < p
+    }
+
+    fn deviate(&mut self, dir: Vec2) -> Vec2 {
+        dir.rotated_by_angle((self.rng.next_f32() * 2.0 - 1.0) * MAX_DEVIATION)
+    }
+
+    /// Builds the proposed road, turning it if needed. Returns the intersection it ends at
+    /// and its direction when the road can keep growing from there
+    fn grow(&self, map: &mut Map, p: &Proposal) -> Option<(IntersectionID, Vec2)> {
+        let from = map.intersections.get(p.from)?.pos;
+        let len = p.class.length();
+
+        for turn in TURNS {
+            let dir = p.dir.rotated_by_angle(turn);
+            let to = from.xy() + dir * len;
+            if to.distance(self.center) > self.radius {
+                continue;
+            }
+            let Some(h) = map.terrain.height(to) else {
+                continue;
+            };
+
+            let proj = map.project(
+                to.z(h),
+                SNAP_DIST,
+                ProjectFilter::INTER | ProjectFilter::ROAD,
+            );
+            let snapped = match proj.kind {
+                ProjectKind::Ground => false,
+                ProjectKind::Inter(id) if id != p.from => true,
+                ProjectKind::Road(id) => {
+                    let r = map.roads.get(id)?;
+                    if r.src == p.from || r.dst == p.from {
+                        continue;
+                    }
+                    true
+                }
+                _ => continue,
+            };
+
+            if !is_buildable(map, from, proj.pos, len) {
+                continue;
+            }
+
+            let fromproj = MapProject {
+                pos: from,
+                kind: ProjectKind::Inter(p.from),
+            };
+            let Some((id, _)) = map.make_connection(fromproj, proj, None, &p.class.pattern())
+            else {
+                continue;
+            };
+
+            if snapped {
+                return None;
+            }
+            return Some((id, dir));
+        }
+
+        None
+    }
+}
+
+/// Whether a straight road from `a` to `b` is not too steep, stays out of the water
+/// and does not run into other roads or buildings on the way
+fn is_buildable(map: &Map, a: Vec3, b: Vec3, len: f32) -> bool {
+    let d = a.xy().distance(b.xy());
+    if d < len * 0.3 || (b.z - a.z).abs() > d * MAX_SLOPE {
+        return false;
+    }
+
+    let n = (d / WATER_CHECK_STEP).ceil() as usize;
+    if (0..=n).any(|i| {
+        let p = a.xy() + (b.xy() - a.xy()) * (i as f32 / n as f32);
+        map.terrain.is_underwater(p)
+    }) {
+        return false;
+    }
+
+    let Some(dir) = (b.xy() - a.xy()).try_normalize() else {
+        return false;
+    };
+    let free_len = d - SNAP_DIST;
+    if free_len <= 0.0 {
+        return true;
+    }
+    let obb = OBB::new((a.xy() + b.xy()) * 0.5, dir, free_len, 4.0);
+    map.spatial_map
+        .query(
+            obb,
+            ProjectFilter::INTER | ProjectFilter::ROAD | ProjectFilter::BUILDING,
+        )
+        .next()
+        .is_none()
+}
@@ -1,3 +1,4 @@
+use crate::utils::rand_provider::RandProvider;
 use geom::{vec2, vec3, Vec2, Vec3};
 
 fn permute(x: f32) -> f32 {
@@ -59,45 +60,64 @@ pub(crate) fn simplex_noise(pos: Vec2) -> (f32, Vec2) {
 }
 
 const FBM_MAG: f32 = 0.4;
+/// The simplex noise repeats itself every 289 units
+const NOISE_PERIOD: f32 = 289.0;
 
-fn fnoise(ampl: f32, in_wv: Vec2) -> (f32, Vec2) {
-    let mut dec = Vec2::splat(70.69) + in_wv * ampl;
+/// The height goes up away from this line so that the sea is around it
+const SEA_Y: f32 = 25000.0;
 
-    let mut noise: f32 = 0.0;
-    let mut amplitude: f32 = 1.0;
-    let mut grad: Vec2 = Vec2::ZERO;
-
-    for _ in 0..4 {
-        let (n, g) = simplex_noise(dec);
-        noise += amplitude * n;
-        grad += g;
+/// Noise the terrain is generated from, each seed gives a different world
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Heightmap {
+    offset: Vec2,
+    octaves: u32,
+}
 
-        dec *= 1.0 / FBM_MAG;
-        amplitude *= FBM_MAG;
+impl Heightmap {
+    pub(crate) fn new(seed: u64, octaves: u32) -> Self {
+        let mut rng = RandProvider::new(seed);
+        let offset = vec2(rng.next_f32(), rng.next_f32()) * NOISE_PERIOD;
+        Self { offset, octaves }
     }
 
-    (noise, grad * ampl)
-}
+    fn fnoise(&self, ampl: f32, in_wv: Vec2) -> (f32, Vec2) {
+        let mut dec = Vec2::splat(70.69) + self.offset + in_wv * ampl;
+
+        let mut noise: f32 = 0.0;
+        let mut amplitude: f32 = 1.0;
+        let mut grad: Vec2 = Vec2::ZERO;
+
+        for _ in 0..self.octaves {
+            let (n, g) = simplex_noise(dec);
+            noise += amplitude * n;
+            grad += g;
 
-pub(crate) fn height(p: Vec2) -> (f32, Vec2) {
-    //p -= vec2(-2000.0, 2000.0);
-
-    let (noise, mut grad) = fnoise(0.00003, p);
-    let ratio = 0.00005;
-    let mut noise = noise - 0.1 + (p.y - 25000.0).abs() * ratio;
-    grad += vec2(0.0, (p.y - 25000.0).signum() * ratio);
-    if noise < -0.0 {
-        noise = noise * noise;
-        grad = 2.0 * noise * grad;
-    } else if noise > 1.0 {
-        noise = 1.0;
-        grad = Vec2::ZERO;
+            dec *= 1.0 / FBM_MAG;
+            amplitude *= FBM_MAG;
+        }
+
+        (noise, grad * ampl)
     }
-    (noise, grad)
-}
 
-pub(crate) fn tree_density(mut p: Vec2) -> f32 {
-    p -= vec2(-20000.0, 20000.0);
-    let major = simplex_noise((p - vec2(-1000.0, 10000.0)) * 0.0003).0 * 0.5 + 0.5;
-    (-major * 1.0 + simplex_noise(p * 0.0003).0 * 1.5 + 0.5).max(0.0) + -0.1
+    pub(crate) fn height(&self, p: Vec2) -> (f32, Vec2) {
+        let (noise, mut grad) = self.fnoise(0.00003, p);
+        let ratio = 0.00005;
+        let mut noise = noise - 0.1 + (p.y - SEA_Y).abs() * ratio;
+        grad += vec2(0.0, (p.y - SEA_Y).signum() * ratio);
+        if noise < -0.0 {
+            noise = noise * noise;
+            grad = 2.0 * noise * grad;
+        } else if noise > 1.0 {
+            noise = 1.0;
+            grad = Vec2::ZERO;
+        }
+        (noise, grad)
+    }
+
+    pub(crate) fn tree_density(&self, mut p: Vec2) -> f32 {
+        p -= vec2(-20000.0, 20000.0);
+        let offset = self.offset / 0.0003;
+        let major = simplex_noise((p + offset - vec2(-1000.0, 10000.0)) * 0.0003).0 * 0.5 + 0.5;
+        (-major * 1.0 + simplex_noise((p + offset) * 0.0003).0 * 1.5 + 0.5).max(0.0) + -0.1
+    }
 }
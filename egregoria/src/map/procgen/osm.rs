@@ -0,0 +1,602 @@
+use crate::map::{
+    BuildingGen, BuildingID, BuildingKind, IntersectionID, LanePattern, LanePatternBuilder, Map,
+    MapProject, ProjectKind,
+};
+use common::FastMap;
+use flat_spatial::Grid;
+use geom::{vec2, Vec2, OBB};
+use serde::{Deserialize, Serialize};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use xml::reader::{EventReader, XmlEvent};
+
+const EARTH_RADIUS: f64 = 6_371_000.0;
+/// Footprints are built as houses, bigger ones (apartment blocks, malls, ...) are skipped.
+/// Same as the biggest lots generated along roads
+const MAX_HOUSE_SIZE: f32 = 40.0;
+
+#[derive(Debug)]
+pub enum OsmError {
+    Io(std::io::Error),
+    Xml(xml::reader::Error),
+    /// `.osm.pbf` extracts are not supported, convert them to xml first (e.g. with osmium)
+    PbfUnsupported,
+}
+
+impl std::fmt::Display for OsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OsmError::Io(e) => write!(f, "io error: {}", e),
+            OsmError::Xml(e) => write!(f, "invalid osm xml: {}", e),
+            OsmError::PbfUnsupported => write!(f, "pbf extracts are not supported, use .osm xml"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsmOptions {
+    /// Where the origin of the extract ends up in the world
+    pub pos: Vec2,
+    /// (lat, lon) projected to `pos`. Defaults to the center of the extract bounds
+    pub origin: Option<(f64, f64)>,
+    /// Nodes of a way closer than this to the previous kept node are dropped, in meters
+    pub simplify_dist: f32,
+    /// Junctions closer than this are merged together, in meters
+    pub merge_dist: f32,
+    pub buildings: bool,
+}
+
+impl Default for OsmOptions {
+    fn default() -> Self {
+        Self {
+            pos: Vec2::ZERO,
+            origin: None,
+            simplify_dist: 30.0,
+            merge_dist: 15.0,
+            buildings: true,
+        }
+    }
+}
+
+/// A road network and building footprints extracted from OpenStreetMap, already projected.
+/// It is parsed once and sent as is in a `WorldCommand` so that every peer builds the same map.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OsmData {
+    pub intersections: Vec<Vec2>,
+    pub roads: Vec<(usize, usize, LanePattern)>,
+    pub buildings: Vec<OBB>,
+}
+
+struct Way {
+    nodes: Vec<i64>,
+    tags: FastMap<String, String>,
+}
+
+impl Way {
+    fn tag(&self, k: &str) -> Option<&str> {
+        self.tags.get(k).map(String::as_str)
+    }
+}
+
+pub fn load_osm_file(path: &Path, opts: &OsmOptions) -> Result<OsmData, OsmError> {
+    if path.to_string_lossy().ends_with(".pbf") {
+        return Err(OsmError::PbfUnsupported);
+    }
+    let f = std::fs::File::open(path).map_err(OsmError::Io)?;
+    parse_osm(BufReader::new(f), opts)
+}
+
+pub fn parse_osm(r: impl Read, opts: &OsmOptions) -> Result<OsmData, OsmError> {
+    let time = std::time::Instant::now();
+
+    let mut nodes: FastMap<i64, (f64, f64)> = FastMap::default();
+    let mut ways = vec![];
+    let mut bounds = None;
+    let mut cur_way: Option<Way> = None;
+
+    for e in EventReader::new(r) {
+        match e.map_err(OsmError::Xml)? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let attr = |k: &str| {
+                    attributes
+                        .iter()
+                        .find(|a| a.name.local_name == k)
+                        .map(|a| a.value.as_str())
+                };
+                match name.local_name.as_str() {
+                    "bounds" => {
+                        let v = |k| attr(k)?.parse::<f64>().ok();
+                        if let (Some(a), Some(b), Some(c), Some(d)) =
+                            (v("minlat"), v("minlon"), v("maxlat"), v("maxlon"))
+                        {
+                            bounds = Some(((a + c) * 0.5, (b + d) * 0.5));
+                        }
+                    }
+                    "node" => {
+                        let id = attr("id").and_then(|x| x.parse().ok());
+                        let lat = attr("lat").and_then(|x| x.parse().ok());
+                        let lon = attr("lon").and_then(|x| x.parse().ok());
+                        if let (Some(id), Some(lat), Some(lon)) = (id, lat, lon) {
+                            nodes.insert(id, (lat, lon));
+                        }
+                    }
+                    "way" => {
+                        cur_way = Some(Way {
+                            nodes: vec![],
+                            tags: Default::default(),
+                        })
+                    }
+                    "nd" => {
+                        if let (Some(w), Some(id)) =
+                            (&mut cur_way, attr("ref").and_then(|x| x.parse().ok()))
+                        {
+                            w.nodes.push(id);
+                        }
+                    }
+                    "tag" => {
+                        if let (Some(w), Some(k), Some(v)) = (&mut cur_way, attr("k"), attr("v")) {
+                            w.tags.insert(k.to_string(), v.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::EndElement { name } if name.local_name == "way" => {
+                if let Some(w) = cur_way.take() {
+                    if w.tags.contains_key("building") || way_pattern(&w).is_some() {
+                        ways.push(w);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let origin = opts.origin.or(bounds).unwrap_or_else(|| {
+        let n = nodes.len().max(1) as f64;
+        let (lat, lon) = nodes
+            .values()
+            .fold((0.0, 0.0), |(a, b), (lat, lon)| (a + lat, b + lon));
+        (lat / n, lon / n)
+    });
+
+    let data = build_data(&nodes, &ways, origin, opts);
+
+    info!(
+        "parsing osm extract took {}ms: {} nodes, {} ways",
+        time.elapsed().as_secs_f32() * 1000.0,
+        nodes.len(),
+        ways.len()
+    );
+
+    Ok(data)
+}
+
+/// Equirectangular projection around `origin`, good enough at the scale of a city
+fn project((lat0, lon0): (f64, f64), (lat, lon): (f64, f64)) -> Vec2 {
+    let x = (lon - lon0).to_radians() * lat0.to_radians().cos() * EARTH_RADIUS;
+    let y = (lat - lat0).to_radians() * EARTH_RADIUS;
+    vec2(x as f32, y as f32)
+}
+
+/// Returns the pattern of a way and whether it goes against the order of its nodes
+fn way_pattern(w: &Way) -> Option<(LanePatternBuilder, bool)> {
+    if w.tag("railway") == Some("rail") {
+        return Some((LanePatternBuilder::new().rail(true), false));
+    }
+
+    let highway = w.tag("highway")?;
+    let highway = highway.strip_suffix("_link").unwrap_or(highway);
+
+    let (pat, implied_oneway) = match highway {
+        "motorway" => (
+            LanePatternBuilder::new()
+                .n_lanes(2)
+                .speed_limit(30.0)
+                .sidewalks(false)
+                .parking(false),
+            true,
+        ),
+        "trunk" => (
+            LanePatternBuilder::new()
+                .n_lanes(2)
+                .speed_limit(25.0)
+                .sidewalks(false)
+                .parking(false),
+            false,
+        ),
+        "primary" => (
+            LanePatternBuilder::new()
+                .n_lanes(2)
+                .speed_limit(15.0)
+                .parking(false),
+            false,
+        ),
+        "secondary" => (
+            LanePatternBuilder::new().n_lanes(2).speed_limit(13.0),
+            false,
+        ),
+        "tertiary" | "unclassified" => (LanePatternBuilder::new().speed_limit(13.0), false),
+        "residential" | "living_street" => (LanePatternBuilder::new(), false),
+        "service" => (
+            LanePatternBuilder::new().speed_limit(6.0).parking(false),
+            false,
+        ),
+        _ => return None,
+    };
+
+    let (one_way, reversed) = match w.tag("oneway") {
+        Some("yes" | "true" | "1") => (true, false),
+        Some("-1" | "reverse") => (true, true),
+        Some("no" | "false" | "0") => (false, false),
+        _ => (
+            implied_oneway || w.tag("junction") == Some("roundabout"),
+            false,
+        ),
+    };
+
+    let mut pat = pat.one_way(one_way);
+
+    // lanes=* counts the lanes of both directions
+    if let Some(lanes) = w.tag("lanes").and_then(|x| x.parse::<u32>().ok()) {
+        let lanes = if one_way { lanes } else { (lanes + 1) / 2 };
+        pat = pat.n_lanes(lanes.max(1));
+    }
+
+    Some((pat, reversed))
+}
+
+fn build_data(
+    nodes: &FastMap<i64, (f64, f64)>,
+    ways: &[Way],
+    origin: (f64, f64),
+    opts: &OsmOptions,
+) -> OsmData {
+    let mut data = OsmData::default();
+    let pos = |id: &i64| Some(project(origin, *nodes.get(id)?) + opts.pos);
+
+    let road_ways: Vec<_> = ways
+        .iter()
+        .filter_map(|w| Some((w, way_pattern(w)?)))
+        .collect();
+
+    let mut uses: FastMap<i64, u32> = FastMap::default();
+    for (w, _) in &road_ways {
+        for id in &w.nodes {
+            *uses.entry(*id).or_default() += 1;
+        }
+    }
+
+    let mut g = Grid::new(50);
+    let mut node_idx: FastMap<i64, usize> = FastMap::default();
+    // Only way ends and junctions are merged, the shape points in the middle of a way
+    // are never merged and nothing is merged into them
+    let mut get_idx = |data: &mut OsmData, id: i64, p: Vec2, mergeable: bool| {
+        if let Some(&idx) = node_idx.get(&id) {
+            return idx;
+        }
+        let merged = mergeable
+            .then(|| g.query_around(p, opts.merge_dist).next())
+            .flatten()
+            .map(|(h, _)| *g.get(h).unwrap().1); // Unwrap ok: handle comes from the query
+        let idx = match merged {
+            Some(idx) => idx,
+            None => {
+                data.intersections.push(p);
+                let idx = data.intersections.len() - 1;
+                if mergeable {
+                    g.insert(p, idx);
+                }
+                idx
+            }
+        };
+        node_idx.insert(id, idx);
+        idx
+    };
+
+    // index in data.roads of the road between two intersections, in any direction
+    let mut edges: FastMap<(usize, usize), usize> = FastMap::default();
+
+    for (w, (pat, reversed)) in road_ways {
+        let pattern = pat.build();
+        let n = w.nodes.len();
+        let mut last: Option<(usize, Vec2)> = None;
+
+        for (i, id) in w.nodes.iter().enumerate() {
+            let p = unwrap_cont!(pos(id));
+            let is_end = i == 0 || i == n - 1;
+            let is_junction = uses.get(id).map_or(false, |&x| x > 1);
+
+            if let Some((_, lastp)) = last {
+                if !is_end && !is_junction && lastp.distance(p) < opts.simplify_dist {
+                    continue;
+                }
+            }
+
+            let idx = get_idx(&mut data, *id, p, is_end || is_junction);
+
+            if let Some((lastidx, _)) = last {
+                if lastidx != idx {
+                    let (src, dst) = if reversed {
+                        (idx, lastidx)
+                    } else {
+                        (lastidx, idx)
+                    };
+                    add_edge(&mut data, &mut edges, src, dst, &pattern);
+                }
+            }
+
+            last = Some((idx, data.intersections[idx]));
+        }
+    }
+
+    if opts.buildings {
+        data.buildings = ways
+            .iter()
+            .filter(|w| w.tags.contains_key("building"))
+            .filter_map(|w| {
+                if w.nodes.len() < 4 || w.nodes.first() != w.nodes.last() {
+                    return None;
+                }
+                let pts = w.nodes.iter().map(pos).collect::<Option<Vec<_>>>()?;
+                footprint_obb(&pts)
+            })
+            .collect();
+    }
+
+    data
+}
+
+/// Adds the road from `src` to `dst` unless the intersections are already connected.
+/// The carriageways of a dual carriageway are usually merged into the same intersections,
+/// so opposite one way roads between them become a single two way road.
+fn add_edge(
+    data: &mut OsmData,
+    edges: &mut FastMap<(usize, usize), usize>,
+    src: usize,
+    dst: usize,
+    pattern: &LanePattern,
+) {
+    let key = (src.min(dst), src.max(dst));
+    let Some(&i) = edges.get(&key) else {
+        edges.insert(key, data.roads.len());
+        data.roads.push((src, dst, pattern.clone()));
+        return;
+    };
+
+    let is_one_way = |p: &LanePattern| !p.lanes_backward.iter().any(|(k, _)| k.vehicles());
+    let (esrc, _, existing) = &mut data.roads[i];
+    if *esrc == dst && is_one_way(existing) && is_one_way(pattern) {
+        existing.lanes_backward = pattern.lanes_forward.clone();
+    }
+}
+
+/// Bounding box aligned with the longest side of the footprint
+fn footprint_obb(pts: &[Vec2]) -> Option<OBB> {
+    let dir = pts
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .max_by_key(|d| ordered_float::OrderedFloat(d.mag2()))?
+        .try_normalize()?;
+    let perp = dir.perpendicular();
+
+    let (mut minx, mut maxx, mut miny, mut maxy) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+    for p in pts {
+        let x = p.dot(dir);
+        let y = p.dot(perp);
+        minx = minx.min(x);
+        maxx = maxx.max(x);
+        miny = miny.min(y);
+        maxy = maxy.max(y);
+    }
+
+    let w = maxx - minx;
+    let h = maxy - miny;
+    if !(4.0..=MAX_HOUSE_SIZE).contains(&w) || !(4.0..=MAX_HOUSE_SIZE).contains(&h) {
+        return None;
+    }
+
+    let center = dir * (minx + maxx) * 0.5 + perp * (miny + maxy) * 0.5;
+    Some(OBB::new(center, dir, w, h))
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct OsmStats {
+    pub roads: usize,
+    pub buildings: Vec<BuildingID>,
+}
+
+impl OsmData {
+    pub fn build(&self, map: &mut Map) -> OsmStats {
+        let time = std::time::Instant::now();
+        let mut stats = OsmStats::default();
+
+        let mut ids: Vec<Option<IntersectionID>> = vec![None; self.intersections.len()];
+        let mut mk_proj = |map: &mut Map, idx: usize| {
+            let p = *self.intersections.get(idx)?;
+            let pos = p.z(map.terrain.height(p).unwrap_or(0.0));
+            let id = *ids
+                .get_mut(idx)?
+                .get_or_insert_with(|| map.add_intersection(pos));
+            Some(MapProject {
+                pos,
+                kind: ProjectKind::Inter(id),
+            })
+        };
+
+        for (src, dst, pat) in &self.roads {
+            let from = unwrap_cont!(mk_proj(map, *src));
+            let to = unwrap_cont!(mk_proj(map, *dst));
+            if map.make_connection(from, to, None, pat).is_some() {
+                stats.roads += 1;
+            }
+        }
+
+        for id in ids.into_iter().flatten() {
+            if map
+                .intersections
+                .get(id)
+                .map_or(false, |i| i.roads.is_empty())
+            {
+                map.remove_intersection(id);
+            }
+        }
+
+        for obb in &self.buildings {
+            let id = map.build_special_building(obb, BuildingKind::House, BuildingGen::House, None);
+            stats.buildings.extend(id);
+        }
+
+        info!(
+            "building osm map took {}ms: {} roads, {} buildings",
+            time.elapsed().as_secs_f32() * 1000.0,
+            stats.roads,
+            stats.buildings.len()
+        );
+
+        map.check_invariants();
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXTRACT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <bounds minlat="48.8500" minlon="2.3000" maxlat="48.8600" maxlon="2.3100"/>
+  <node id="1" lat="48.8550" lon="2.3000"/>
+  <node id="2" lat="48.8550" lon="2.3050"/>
+  <node id="3" lat="48.8550" lon="2.3100"/>
+  <node id="4" lat="48.8500" lon="2.3050"/>
+  <node id="5" lat="48.8600" lon="2.3050"/>
+  <node id="10" lat="48.8560" lon="2.3020"/>
+  <node id="11" lat="48.8560" lon="2.3022"/>
+  <node id="12" lat="48.8561" lon="2.3022"/>
+  <node id="13" lat="48.8561" lon="2.3020"/>
+  <way id="100">
+    <nd ref="1"/><nd ref="2"/><nd ref="3"/>
+    <tag k="highway" v="primary"/>
+    <tag k="lanes" v="4"/>
+  </way>
+  <way id="101">
+    <nd ref="5"/><nd ref="2"/><nd ref="4"/>
+    <tag k="highway" v="residential"/>
+    <tag k="oneway" v="-1"/>
+  </way>
+  <way id="102">
+    <nd ref="1"/><nd ref="4"/>
+    <tag k="highway" v="footway"/>
+  </way>
+  <way id="103">
+    <nd ref="10"/><nd ref="11"/><nd ref="12"/><nd ref="13"/><nd ref="10"/>
+    <tag k="building" v="yes"/>
+  </way>
+</osm>"#;
+
+    #[test]
+    fn parse_extract() {
+        let data = parse_osm(EXTRACT.as_bytes(), &OsmOptions::default()).unwrap();
+
+        assert_eq!(data.intersections.len(), 5);
+        assert_eq!(data.roads.len(), 4);
+        assert_eq!(data.buildings.len(), 1);
+
+        let (_, _, primary) = &data.roads[0];
+        assert_eq!(primary.lanes_forward.len(), 2 + 1);
+        assert_eq!(primary.lanes_backward.len(), 2 + 1);
+
+        // oneway=-1 goes from 4 to 5, so the road 2 -> 4 is reversed
+        let (src, dst, residential) = &data.roads[3];
+        assert!(residential
+            .lanes_backward
+            .iter()
+            .all(|(k, _)| !k.vehicles()));
+        assert!(data.intersections[*src].y < data.intersections[*dst].y);
+
+        let mut m = Map::empty();
+        let stats = data.build(&mut m);
+        assert_eq!(stats.roads, 4);
+        assert_eq!(m.roads().len(), 4);
+        assert_eq!(m.intersections().len(), 5);
+        m.check_invariants();
+    }
+
+    static DUAL_CARRIAGEWAY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="48.8550" lon="2.3000"/>
+  <node id="2" lat="48.8550" lon="2.3050"/>
+  <node id="3" lat="48.85505" lon="2.3000"/>
+  <node id="4" lat="48.85505" lon="2.3050"/>
+  <node id="10" lat="48.8560" lon="2.3020"/>
+  <node id="11" lat="48.8560" lon="2.3030"/>
+  <node id="12" lat="48.8570" lon="2.3030"/>
+  <node id="13" lat="48.8570" lon="2.3020"/>
+  <way id="100">
+    <nd ref="1"/><nd ref="2"/>
+    <tag k="highway" v="primary"/>
+    <tag k="oneway" v="yes"/>
+  </way>
+  <way id="101">
+    <nd ref="4"/><nd ref="3"/>
+    <tag k="highway" v="primary"/>
+    <tag k="oneway" v="yes"/>
+  </way>
+  <way id="102">
+    <nd ref="10"/><nd ref="11"/><nd ref="12"/><nd ref="13"/><nd ref="10"/>
+    <tag k="building" v="apartments"/>
+  </way>
+</osm>"#;
+
+    #[test]
+    fn dual_carriageway_keeps_both_directions() {
+        let data = parse_osm(DUAL_CARRIAGEWAY.as_bytes(), &OsmOptions::default()).unwrap();
+
+        assert_eq!(data.intersections.len(), 2);
+        assert_eq!(data.roads.len(), 1);
+        let (_, _, pattern) = &data.roads[0];
+        assert!(pattern.lanes_forward.iter().any(|(k, _)| k.vehicles()));
+        assert!(pattern.lanes_backward.iter().any(|(k, _)| k.vehicles()));
+
+        // the 70m wide building is not a house
+        assert!(data.buildings.is_empty());
+    }
+
+    static SHAPE_POINT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="48.8550" lon="2.3000"/>
+  <node id="2" lat="48.8550" lon="2.3010"/>
+  <node id="3" lat="48.8550" lon="2.3020"/>
+  <node id="4" lat="48.85508" lon="2.3010"/>
+  <node id="5" lat="48.8560" lon="2.3010"/>
+  <way id="100">
+    <nd ref="1"/><nd ref="2"/><nd ref="3"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="101">
+    <nd ref="4"/><nd ref="5"/>
+    <tag k="highway" v="residential"/>
+  </way>
+</osm>"#;
+
+    #[test]
+    fn shape_points_are_not_merged() {
+        let data = parse_osm(SHAPE_POINT.as_bytes(), &OsmOptions::default()).unwrap();
+
+        // node 4 ends its way 9m away from the middle of the other one, they are not connected
+        assert_eq!(data.intersections.len(), 5);
+        assert_eq!(data.roads.len(), 3);
+    }
+
+    #[test]
+    fn pbf_unsupported() {
+        assert!(matches!(
+            load_osm_file(Path::new("extract.osm.pbf"), &OsmOptions::default()),
+            Err(OsmError::PbfUnsupported)
+        ));
+    }
+}
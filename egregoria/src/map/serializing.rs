@@ -1,8 +1,9 @@
 use crate::map::{
-    BuildingID, Buildings, Intersections, Lanes, Lots, Map, ParkingSpots, Roads, SpatialMap,
-    Terrain,
+    BuildingID, Buildings, Intersections, Landmarks, Lanes, Lots, Map, ParkingSpots, Roads,
+    SpatialMap, Terrain, TravelTimes, Water,
 };
 use crate::BuildingKind;
+use common::saveload::{Bincode, Encoder};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::num::Wrapping;
@@ -18,6 +19,8 @@ pub(crate) struct SerializedMap {
     pub terrain: Terrain,
     pub dirt_id: u32,
     pub bkinds: BTreeMap<BuildingKind, Vec<BuildingID>>,
+    pub travel_times: TravelTimes,
+    pub water: Water,
 }
 
 impl From<&Map> for SerializedMap {
@@ -32,6 +35,8 @@ impl From<&Map> for SerializedMap {
             terrain: m.terrain.clone(),
             bkinds: m.bkinds.clone(),
             dirt_id: m.dirt_id.0,
+            travel_times: m.travel_times.clone(),
+            water: m.terrain.water.clone(),
         }
     }
 }
@@ -39,7 +44,7 @@ impl From<&Map> for SerializedMap {
 impl From<SerializedMap> for Map {
     fn from(sel: SerializedMap) -> Self {
         let spatial_map = mk_spatial_map(&sel);
-        Map {
+        let mut map = Map {
             roads: sel.roads,
             lanes: sel.lanes,
             intersections: sel.intersections,
@@ -47,13 +52,34 @@ impl From<SerializedMap> for Map {
             spatial_map,
             lots: sel.lots,
             parking: sel.parking,
-            terrain: sel.terrain,
+            terrain: Terrain {
+                water: sel.water,
+                ..sel.terrain
+            },
             dirt_id: Wrapping(sel.dirt_id),
+            generation: Wrapping(0),
             bkinds: sel.bkinds,
-        }
+            travel_times: sel.travel_times,
+            landmarks: Landmarks::default(),
+        };
+        // Built right away so that a loaded map routes exactly like the one that was saved
+        map.update_landmarks();
+        map
     }
 }
 
+/// The travel times are the last field of the map, so they can be appended to older saves
+pub(crate) fn migrate_map_travel_times(mut data: Vec<u8>) -> Option<Vec<u8>> {
+    data.extend(Bincode::encode(&TravelTimes::default()).ok()?);
+    Some(data)
+}
+
+/// The water is the last field of the map, older saves get the default sea and no rivers
+pub(crate) fn migrate_map_water(mut data: Vec<u8>) -> Option<Vec<u8>> {
+    data.extend(Bincode::encode(&Water::default()).ok()?);
+    Some(data)
+}
+
 fn mk_spatial_map(m: &SerializedMap) -> SpatialMap {
     let mut sm = SpatialMap::default();
     for h in m.buildings.values() {
@@ -1,15 +1,43 @@
-use crate::map::procgen::heightmap;
-use crate::map::procgen::heightmap::tree_density;
-use geom::{vec2, Radians, Vec2, AABB};
+use crate::map::procgen::heightmap::Heightmap;
+use crate::utils::rand_provider::RandProvider;
+use geom::{vec2, PolyLine, PolyLine3, Radians, Vec2, AABB};
+use ordered_float::OrderedFloat;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::num::Wrapping;
 
 pub const CHUNK_SIZE: u32 = 1024;
 pub const CHUNK_RESOLUTION: usize = 32;
 pub const CELL_SIZE: f32 = CHUNK_SIZE as f32 / CHUNK_RESOLUTION as f32;
 
+/// Height of the surface of the sea, where the water is drawn
+pub const DEFAULT_SEA_LEVEL: f32 = -10.0;
+/// Depth of the bed of the rivers below the sea level, so that they are drawn as water
+const RIVER_DEPTH: f32 = 10.0;
+/// One river is generated for this many chunks of the map
+const CHUNKS_PER_RIVER: u32 = 250;
+/// Distance between the points of the generated rivers
+const RIVER_STEP: f32 = 200.0;
+/// Generated rivers that do not reach the sea within this many steps are dropped
+const RIVER_MAX_STEPS: usize = 200;
+/// Generated rivers with fewer points are dropped, they would only be puddles on the coast
+const RIVER_MIN_POINTS: usize = 10;
+/// Number of sources tried for each generated river
+const RIVER_TRIES: usize = 100;
+/// Height above the water that roads crossing it must keep
+pub const BRIDGE_CLEARANCE: f32 = 5.0;
+/// Depth below the ground that roads going under the water must keep
+pub const TUNNEL_MIN_DEPTH: f32 = 5.0;
+/// Roads further than this from the ground are bridges or tunnels
+const STRUCTURE_MIN_HEIGHT: f32 = 2.0;
+/// Distance between the points checked along roads crossing water
+const WATER_CHECK_STEP: f32 = 5.0;
+/// Largest terraforming brush, in meters
+pub const MAX_TERRAFORM_RADIUS: f32 = 500.0;
+/// Largest height change of one terraforming stroke, in meters
+pub const MAX_TERRAFORM_AMOUNT: f32 = 50.0;
+
 #[derive(Clone)]
 pub struct Chunk {
     pub trees: Vec<Tree>,
@@ -27,7 +55,7 @@ impl Default for Chunk {
     }
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Tree {
     pub pos: Vec2,
     pub size: f32,
@@ -37,34 +65,123 @@ pub struct Tree {
 
 pub type ChunkID = (u32, u32);
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum TerraformKind {
+    Raise,
+    Lower,
+    /// Towards the given height
+    Flatten {
+        height: f32,
+    },
+    /// Towards the average height of the neighboring points
+    Smooth,
+}
+
+/// A new height for one point of the grid of a chunk
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct HeightEdit {
+    pub chunk: ChunkID,
+    pub x: u16,
+    pub y: u16,
+    pub height: f32,
+}
+
+/// Parameters of the procedural generation of the terrain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainOptions {
+    /// Layers of noise, each one adds smaller details
+    pub octaves: u32,
+    /// Depth of the sea floor, in meters. The land is flat at 0.
+    pub amplitude: f32,
+    pub sea_level: f32,
+    /// Multiplier of the density of the forests
+    pub tree_density: f32,
+}
+
+impl Default for TerrainOptions {
+    fn default() -> Self {
+        Self {
+            octaves: 4,
+            amplitude: 1000.0,
+            sea_level: DEFAULT_SEA_LEVEL,
+            tree_density: 1.0,
+        }
+    }
+}
+
+/// Water covering the terrain, the sea everywhere below its level and the rivers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Water {
+    pub sea_level: f32,
+    pub rivers: Vec<River>,
+}
+
+impl Default for Water {
+    fn default() -> Self {
+        Self {
+            sea_level: DEFAULT_SEA_LEVEL,
+            rivers: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct River {
+    pub points: PolyLine,
+    pub width: f32,
+    /// Height of the surface of the water
+    pub level: f32,
+}
+
+impl River {
+    pub fn contains(&self, p: Vec2) -> bool {
+        self.points.project_dist(p) <= self.width * 0.5
+    }
+}
+
 #[derive(Clone)]
 pub struct Terrain {
     pub chunks: BTreeMap<ChunkID, Chunk>,
     pub dirt_id: Wrapping<u32>,
     pub width: u32,
     pub height: u32,
+    /// Serialized with the map rather than the terrain so that older saves can be migrated
+    pub water: Water,
 }
 
 defer_serialize!(Terrain, SerializedTerrain);
 
 impl Default for Terrain {
     fn default() -> Self {
-        Self::new(0, 0)
+        Self {
+            chunks: Default::default(),
+            dirt_id: Wrapping(1),
+            width: 0,
+            height: 0,
+            water: Water::default(),
+        }
     }
 }
 
 impl Terrain {
-    pub fn new(w: u32, h: u32) -> Self {
+    /// Generates a terrain of `w` by `h` chunks, the same seed and options always give the
+    /// same terrain
+    pub fn new(w: u32, h: u32, seed: u64, options: &TerrainOptions) -> Self {
         let mut me = Self {
-            chunks: Default::default(),
-            dirt_id: Wrapping(1),
             width: w,
             height: h,
+            water: Water {
+                sea_level: options.sea_level,
+                rivers: vec![],
+            },
+            ..Self::default()
         };
+        let heightmap = Heightmap::new(seed, options.octaves);
         for y in 0..h {
             let chunks: Vec<_> = (0..w)
                 .into_par_iter()
-                .map(|x| me.generate_chunk((x, y)))
+                .map(|x| me.generate_chunk(&heightmap, options, (x, y)))
                 .collect();
             for (x, chunk) in (0..w).zip(chunks) {
                 if let Some(v) = chunk {
@@ -72,9 +189,57 @@ impl Terrain {
                 }
             }
         }
+        me.generate_rivers(&heightmap, seed);
         me
     }
 
+    /// Digs a river for every `CHUNKS_PER_RIVER` chunks, each one starts from a random point of
+    /// the land and follows the slope of the heightmap down to the sea
+    fn generate_rivers(&mut self, heightmap: &Heightmap, seed: u64) {
+        let mut rng = RandProvider::new(seed.wrapping_add(1));
+        let size = vec2(self.width as f32, self.height as f32) * CHUNK_SIZE as f32;
+        let bounds = AABB::new(Vec2::ZERO, size);
+        for _ in 0..self.width * self.height / CHUNKS_PER_RIVER {
+            let course = (0..RIVER_TRIES).find_map(|_| {
+                let source = vec2(rng.next_f32(), rng.next_f32()) * size;
+                self.river_course(heightmap, bounds, source)
+            });
+            let width = 20.0 + 30.0 * rng.next_f32();
+            if let Some(points) = course {
+                self.add_river(points, width);
+            }
+        }
+    }
+
+    /// Points going down the heightmap from `source` until the sea, None if the way down
+    /// ends before it or if it is too short
+    fn river_course(&self, heightmap: &Heightmap, bounds: AABB, source: Vec2) -> Option<Vec<Vec2>> {
+        let is_sea = |p: Vec2| self.height(p).map_or(false, |h| h < self.water.sea_level);
+        if self.height(source).is_none() || self.is_underwater(source) {
+            return None;
+        }
+
+        let mut points = vec![source];
+        let mut cur = (source, heightmap.height(source).0);
+        for _ in 0..RIVER_MAX_STEPS {
+            let next = (0..8)
+                .map(|i| Radians(i as f32 * std::f32::consts::FRAC_PI_4).vec2())
+                .map(|dir| cur.0 + dir * RIVER_STEP)
+                .filter(|&p| bounds.contains(p))
+                .map(|p| (p, heightmap.height(p).0))
+                .min_by_key(|&(_, h)| OrderedFloat(h))?;
+            if next.1 >= cur.1 {
+                return None;
+            }
+            points.push(next.0);
+            if is_sea(next.0) {
+                return (points.len() >= RIVER_MIN_POINTS).then_some(points);
+            }
+            cur = next;
+        }
+        None
+    }
+
     pub fn remove_near_filter(&mut self, bbox: AABB, should_remove: impl Fn(Vec2) -> bool) {
         let mut v = false;
         for cell in self.chunks_iter(bbox) {
@@ -91,6 +256,261 @@ impl Terrain {
         self.dirt_id += Wrapping(v as u32)
     }
 
+    /// New heights of the grid points within `radius` of `center`, the edit fades out towards
+    /// the border of the brush. `amount` is in meters, except for smoothing where it is the
+    /// fraction of the way to the average of the neighbors, between 0 and 1.
+    /// The radius and amount are clamped to `MAX_TERRAFORM_RADIUS` and `MAX_TERRAFORM_AMOUNT`.
+    pub fn terraform(
+        &self,
+        kind: TerraformKind,
+        center: Vec2,
+        radius: f32,
+        amount: f32,
+    ) -> Vec<HeightEdit> {
+        if !(radius.is_finite() && amount.is_finite()) {
+            return vec![];
+        }
+        let radius = radius.clamp(0.0, MAX_TERRAFORM_RADIUS);
+        let amount = amount.clamp(0.0, MAX_TERRAFORM_AMOUNT);
+        let res = CHUNK_RESOLUTION as u32;
+        let lo = |v: f32| ((v - radius) / CELL_SIZE).max(0.0).ceil() as u32;
+        let hi = |v: f32| ((v + radius) / CELL_SIZE).max(0.0).floor() as u32;
+
+        let mut edits = vec![];
+        for gy in lo(center.y)..=hi(center.y) {
+            for gx in lo(center.x)..=hi(center.x) {
+                let Some(h) = self.grid_height(gx, gy) else {
+                    continue;
+                };
+                let d = (vec2(gx as f32, gy as f32) * CELL_SIZE).distance(center);
+                if d >= radius {
+                    continue;
+                }
+                let falloff = 1.0 - (d / radius) * (d / radius);
+
+                let target = match kind {
+                    TerraformKind::Raise => h + amount,
+                    TerraformKind::Lower => h - amount,
+                    TerraformKind::Flatten { height } => h + (height - h).clamp(-amount, amount),
+                    TerraformKind::Smooth => {
+                        let avg = self.neighbors_average(gx, gy).unwrap_or(h);
+                        h + (avg - h) * amount.clamp(0.0, 1.0)
+                    }
+                };
+                let height = h + (target - h) * falloff;
+                if height == h {
+                    continue;
+                }
+                edits.push(HeightEdit {
+                    chunk: (gx / res, gy / res),
+                    x: (gx % res) as u16,
+                    y: (gy % res) as u16,
+                    height,
+                });
+            }
+        }
+        edits
+    }
+
+    /// Volume of ground moved by the edits, in cubic meters
+    pub fn edits_volume(&self, edits: &[HeightEdit]) -> f32 {
+        edits
+            .iter()
+            .filter_map(|e| {
+                let h = self.point_height(e.chunk, e.x as usize, e.y as usize)?;
+                Some((h - e.height).abs())
+            })
+            .sum::<f32>()
+            * CELL_SIZE
+            * CELL_SIZE
+    }
+
+    /// Applies the edits and returns the ones that revert them.
+    /// Trees that end up under water are removed, they are returned to be added back on undo.
+    pub fn apply_edits(&mut self, edits: &[HeightEdit]) -> (Vec<HeightEdit>, Vec<Tree>) {
+        let mut inverse = Vec::with_capacity(edits.len());
+        let mut culled = vec![];
+        let mut touched = BTreeSet::new();
+        for e in edits {
+            let chunk = unwrap_cont!(self.chunks.get_mut(&e.chunk));
+            let h = unwrap_cont!(chunk
+                .heights
+                .get_mut(e.y as usize)
+                .and_then(|row| row.get_mut(e.x as usize)));
+            inverse.push(HeightEdit { height: *h, ..*e });
+            *h = e.height;
+            touched.insert(e.chunk);
+        }
+
+        let sea_level = self.water.sea_level;
+        for id in touched {
+            let chunk = unwrap_cont!(self.chunks.get_mut(&id));
+            chunk.dirt_id += Wrapping(1);
+
+            let origin = vec2(id.0 as f32, id.1 as f32) * CHUNK_SIZE as f32;
+            let Chunk { trees, heights, .. } = chunk;
+            trees.retain(|t| {
+                let v = (t.pos - origin) / CELL_SIZE;
+                let keep = heights
+                    .get(v.y as usize)
+                    .and_then(|row| row.get(v.x as usize))
+                    .map_or(true, |&h| h >= sea_level);
+                if !keep {
+                    culled.push(*t);
+                }
+                keep
+            });
+        }
+        self.dirt_id += Wrapping(1);
+
+        // Apply in the opposite order so that an edit to the same point twice reverts properly
+        inverse.reverse();
+        (inverse, culled)
+    }
+
+    /// Adds back trees removed by `apply_edits`
+    pub fn add_trees(&mut self, trees: &[Tree]) {
+        for t in trees {
+            let id = Self::cell(t.pos);
+            let chunk = unwrap_cont!(self.chunks.get_mut(&id));
+            chunk.trees.push(*t);
+            chunk.dirt_id += Wrapping(1);
+        }
+        self.dirt_id += Wrapping(1);
+    }
+
+    fn point_height(&self, chunk: ChunkID, x: usize, y: usize) -> Option<f32> {
+        self.chunks.get(&chunk)?.heights.get(y)?.get(x).copied()
+    }
+
+    /// Height of the point of the grid spanning all the chunks
+    fn grid_height(&self, gx: u32, gy: u32) -> Option<f32> {
+        let res = CHUNK_RESOLUTION as u32;
+        self.point_height(
+            (gx / res, gy / res),
+            (gx % res) as usize,
+            (gy % res) as usize,
+        )
+    }
+
+    fn neighbors_average(&self, gx: u32, gy: u32) -> Option<f32> {
+        let mut sum = 0.0;
+        let mut n = 0;
+        for y in gy.saturating_sub(1)..=gy + 1 {
+            for x in gx.saturating_sub(1)..=gx + 1 {
+                if let Some(h) = self.grid_height(x, y) {
+                    sum += h;
+                    n += 1;
+                }
+            }
+        }
+        (n > 0).then(|| sum / n as f32)
+    }
+
+    /// Height of the surface of the water covering this point, None if it is dry
+    pub fn water_height(&self, p: Vec2) -> Option<f32> {
+        let river = self.water.rivers.iter().find(|r| r.contains(p));
+        if let Some(river) = river {
+            return Some(river.level);
+        }
+        let h = self.height(p)?;
+        (h < self.water.sea_level).then_some(self.water.sea_level)
+    }
+
+    pub fn is_underwater(&self, p: Vec2) -> bool {
+        self.water_height(p).is_some()
+    }
+
+    /// Whether a road along these points can cross the water it goes over:
+    /// it must be a bridge high enough above it or a tunnel deep enough below the ground
+    pub fn can_cross_water(&self, points: &PolyLine3) -> bool {
+        points
+            .equipoints_dir(WATER_CHECK_STEP, false)
+            .all(|(p, _)| {
+                let Some(water) = self.water_height(p.xy()) else {
+                    return true;
+                };
+                let ground = self.height(p.xy()).unwrap_or(water);
+                p.z >= water + BRIDGE_CLEARANCE || p.z <= ground.min(water) - TUNNEL_MIN_DEPTH
+            })
+    }
+
+    /// Length of the parts of the road along these points that are bridges and tunnels
+    pub fn structures_length(&self, points: &PolyLine3) -> (f32, f32) {
+        let samples: Vec<_> = points.equipoints_dir(WATER_CHECK_STEP, false).collect();
+        let step = points.length() / samples.len().max(1) as f32;
+
+        let mut bridge = 0.0;
+        let mut tunnel = 0.0;
+        for (p, _) in samples {
+            let Some(ground) = self.height(p.xy()) else {
+                continue;
+            };
+            let surface = self.water_height(p.xy()).unwrap_or(ground).max(ground);
+            if p.z - surface > STRUCTURE_MIN_HEIGHT {
+                bridge += step;
+            } else if ground - p.z > STRUCTURE_MIN_HEIGHT {
+                tunnel += step;
+            }
+        }
+        (bridge, tunnel)
+    }
+
+    /// Digs a river along the points. Its surface is at the height of the lowest of its
+    /// points and its bed is lowered below the sea level so that it is drawn as water.
+    pub fn add_river(&mut self, points: Vec<Vec2>, width: f32) {
+        if points.len() < 2 {
+            return;
+        }
+        let points = PolyLine::new(points);
+        let level = points
+            .iter()
+            .filter_map(|&p| self.height(p))
+            .reduce(f32::min)
+            .unwrap_or(self.water.sea_level)
+            .max(self.water.sea_level);
+        let bed = self.water.sea_level - RIVER_DEPTH;
+
+        let res = CHUNK_RESOLUTION as u32;
+        let lo = |v: f32| (v / CELL_SIZE).max(0.0).ceil() as u32;
+        let hi = |v: f32| (v / CELL_SIZE).max(0.0).floor() as u32;
+
+        // Only look around each segment, long rivers have huge bounding boxes
+        let mut dug = BTreeSet::new();
+        let mut edits = vec![];
+        for seg in points.segments() {
+            let area = AABB::new(seg.src.min(seg.dst), seg.src.max(seg.dst))
+                .expand(width * 0.5 + CELL_SIZE);
+            for gy in lo(area.ll.y)..=hi(area.ur.y) {
+                for gx in lo(area.ll.x)..=hi(area.ur.x) {
+                    let Some(h) = self.grid_height(gx, gy) else {
+                        continue;
+                    };
+                    let p = vec2(gx as f32, gy as f32) * CELL_SIZE;
+                    if h <= bed || seg.project(p).distance(p) > (width + CELL_SIZE) * 0.5 {
+                        continue;
+                    }
+                    if !dug.insert((gx, gy)) {
+                        continue;
+                    }
+                    edits.push(HeightEdit {
+                        chunk: (gx / res, gy / res),
+                        x: (gx % res) as u16,
+                        y: (gy % res) as u16,
+                        height: bed,
+                    });
+                }
+            }
+        }
+        self.apply_edits(&edits);
+
+        self.water.rivers.push(River {
+            points,
+            width,
+            level,
+        });
+    }
+
     pub fn cell(p: Vec2) -> (u32, u32) {
         if p.x < 0.0 || p.y < 0.0 {
             return (0, 0);
@@ -133,7 +553,12 @@ impl Terrain {
         })
     }
 
-    pub fn generate_chunk(&self, (x, y): (u32, u32)) -> Option<Chunk> {
+    fn generate_chunk(
+        &self,
+        heightmap: &Heightmap,
+        options: &TerrainOptions,
+        (x, y): (u32, u32),
+    ) -> Option<Chunk> {
         if self.chunks.contains_key(&(x, y)) {
             return None;
         }
@@ -144,13 +569,13 @@ impl Terrain {
         for (y, l) in chunk.heights.iter_mut().enumerate() {
             for (x, h) in l.iter_mut().enumerate() {
                 let offcell = vec2(x as f32, y as f32) * CELL_SIZE;
-                let mut rh = heightmap::height(offchunk + offcell).0 - 0.12;
+                let mut rh = heightmap.height(offchunk + offcell).0 - 0.12;
 
                 if rh > 0.0 {
                     rh = 0.0;
                 }
 
-                *h = 1000.0 * rh;
+                *h = options.amplitude * rh;
             }
         }
 
@@ -171,7 +596,7 @@ impl Terrain {
 
                 let sample = cellpos + vec2(jitterx, jittery) * TCELLW;
 
-                let tdens = tree_density(pchunk + sample);
+                let tdens = heightmap.tree_density(pchunk + sample) * options.tree_density;
 
                 if dens_test < tdens && chunk.height(sample) >= 0.0 {
                     chunk.trees.push(Tree::new(pchunk + sample));
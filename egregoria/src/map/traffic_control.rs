@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TrafficBehavior {
     RED,
     ORANGE,
@@ -35,11 +35,18 @@ impl TrafficLightSchedule {
     }
 }
 
+/// Saved with the lanes, new variants must be appended, see `LightPolicy`
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum TrafficControl {
     Always,
     Light(TrafficLightSchedule),
     StopSign,
+    /// Light switched by the intersection's controller depending on the detected queues,
+    /// lanes sharing a phase are green together
+    Actuated {
+        phase: u8,
+        behavior: TrafficBehavior,
+    },
 }
 
 impl TrafficControl {
@@ -52,7 +59,14 @@ impl TrafficControl {
     }
 
     pub fn is_light(&self) -> bool {
-        matches!(self, TrafficControl::Light(_))
+        matches!(
+            self,
+            TrafficControl::Light(_) | TrafficControl::Actuated { .. }
+        )
+    }
+
+    pub fn is_actuated(&self) -> bool {
+        matches!(self, TrafficControl::Actuated { .. })
     }
 
     pub fn get_behavior(&self, seconds: u32) -> TrafficBehavior {
@@ -69,6 +83,7 @@ impl TrafficControl {
                 }
             }
             TrafficControl::StopSign => TrafficBehavior::STOP,
+            TrafficControl::Actuated { behavior, .. } => *behavior,
         }
     }
 }
@@ -0,0 +1,138 @@
+use crate::map::{Lane, LaneID, Lanes, Map, Traversable, TraverseKind, TurnID};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Weight of a new observation in the exponential average of travel times
+const SMOOTHING: f32 = 0.2;
+/// Fraction of the gap to the free-flow time recovered at each decay,
+/// so lanes that were jammed and got avoided are eventually tried again
+const DECAY: f32 = 0.05;
+
+/// Travel times observed on the lanes and turns by the vehicles, in seconds.
+/// Lanes without observations take their free-flow time, turns take no time.
+///
+/// Pathfinding only sees the times of the last `publish`, so that paths computed
+/// between two publications are the same and can be cached.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TravelTimes {
+    /// When false, car pathfinding only uses the lanes length and speed limit
+    pub enabled: bool,
+    /// Incremented at each `publish`
+    pub version: u32,
+    lanes: BTreeMap<LaneID, f32>,
+    turns: BTreeMap<TurnID, f32>,
+    observed_lanes: BTreeMap<LaneID, f32>,
+    observed_turns: BTreeMap<TurnID, f32>,
+}
+
+impl Default for TravelTimes {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            version: 0,
+            lanes: Default::default(),
+            turns: Default::default(),
+            observed_lanes: Default::default(),
+            observed_turns: Default::default(),
+        }
+    }
+}
+
+pub fn free_flow_time(lane: &Lane) -> f32 {
+    lane.points.length() / lane.speed_limit
+}
+
+impl TravelTimes {
+    pub fn lane_time(&self, lane: &Lane) -> f32 {
+        self.lanes
+            .get(&lane.id)
+            .copied()
+            .unwrap_or_else(|| free_flow_time(lane))
+    }
+
+    pub fn turn_time(&self, turn: TurnID) -> f32 {
+        self.turns.get(&turn).copied().unwrap_or(0.0)
+    }
+
+    /// Estimated time to go through the traversables, None if one of them doesn't exist anymore
+    pub fn path_time<'a>(
+        &self,
+        map: &Map,
+        path: impl IntoIterator<Item = &'a Traversable>,
+    ) -> Option<f32> {
+        let mut total = 0.0;
+        for t in path {
+            total += match t.kind {
+                TraverseKind::Lane(id) => self.lane_time(map.lanes.get(id)?),
+                TraverseKind::Turn(id) => self.turn_time(id),
+            };
+        }
+        Some(total)
+    }
+
+    pub fn observe_lane(&mut self, lane: &Lane, time: f32) {
+        let free = free_flow_time(lane);
+        let v = self.observed_lanes.entry(lane.id).or_insert(free);
+        *v += (time - *v) * SMOOTHING;
+    }
+
+    pub fn observe_turn(&mut self, turn: TurnID, time: f32) {
+        let v = self.observed_turns.entry(turn).or_insert(0.0);
+        *v += (time - *v) * SMOOTHING;
+    }
+
+    /// Brings the observed times back towards the free-flow times
+    /// and forgets the lanes and turns that were removed
+    pub fn decay(&mut self, lanes: &Lanes) {
+        self.observed_lanes.retain(|id, v| {
+            let Some(lane) = lanes.get(*id) else {
+                return false;
+            };
+            let free = free_flow_time(lane);
+            *v += (free - *v) * DECAY;
+            (*v - free).abs() > 0.1
+        });
+        self.observed_turns.retain(|id, v| {
+            *v -= *v * DECAY;
+            *v > 0.1 && lanes.contains_key(id.src) && lanes.contains_key(id.dst)
+        });
+    }
+
+    /// Makes the observed times visible to pathfinding
+    pub fn publish(&mut self) {
+        self.lanes.clone_from(&self.observed_lanes);
+        self.turns.clone_from(&self.observed_turns);
+        self.version = self.version.wrapping_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TravelTimes, SMOOTHING};
+    use crate::map::{IntersectionID, LaneID, TurnID};
+    use slotmap::KeyData;
+
+    #[test]
+    fn turn_times_are_smoothed() {
+        let lane = |i| LaneID::from(KeyData::from_ffi(i));
+        let turn = TurnID::new(
+            IntersectionID::from(KeyData::from_ffi(1)),
+            lane(1),
+            lane(2),
+            false,
+        );
+        let mut t = TravelTimes::default();
+        assert_eq!(t.turn_time(turn), 0.0);
+
+        t.observe_turn(turn, 10.0);
+        assert_eq!(t.turn_time(turn), 0.0);
+        t.publish();
+        assert_eq!(t.turn_time(turn), 10.0 * SMOOTHING);
+
+        for _ in 0..200 {
+            t.observe_turn(turn, 10.0);
+        }
+        t.publish();
+        assert!((t.turn_time(turn) - 10.0).abs() < 0.01);
+    }
+}
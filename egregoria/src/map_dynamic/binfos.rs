@@ -40,6 +40,14 @@ impl BuildingInfos {
         self.owners.insert(soul, building);
     }
 
+    /// The building becomes empty, as if nobody ever lived or worked there
+    pub fn remove_owner(&mut self, building: BuildingID) {
+        let owner = self.get_mut(building).and_then(|x| x.owner.take());
+        if let Some(owner) = owner {
+            self.owners.remove(&owner);
+        }
+    }
+
     pub fn owner(&self, building: BuildingID) -> Option<SoulID> {
         self.assignment.get(building).and_then(|x| x.owner)
     }
@@ -0,0 +1,240 @@
+use crate::economy::{ItemRegistry, Market};
+use crate::map::{
+    BuildingID, BuildingKind, LandValue, LotID, LotKind, Map, ProjectFilter, ProjectKind,
+};
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::goods_company::{CompanyKind, GoodsCompany, GoodsCompanyRegistry};
+use crate::souls::population::{empty_houses, job_balance, Household};
+use crate::utils::time::{GameTime, SECONDS_PER_HOUR};
+use crate::{Egregoria, SoulID};
+use geom::OBB;
+use ordered_float::OrderedFloat;
+use std::cmp::Reverse;
+
+/// Share of the houses kept empty for the households moving in
+const TARGET_VACANCY: f32 = 0.05;
+const MIN_VACANT_HOUSES: i32 = 5;
+/// Jobs kept open for the households moving in
+const MIN_OPEN_JOBS: i32 = 5;
+const HOUSEHOLDS_PER_STORE: i32 = 20;
+const MAX_HOUSES_PER_HOUR: i32 = 10;
+/// Companies on land worth at least this much hire more workers when jobs are needed
+pub const UPGRADE_LAND_VALUE: f32 = 30.0;
+/// A company can hire up to this many times the workers of its description on top of them
+pub const MAX_UPGRADES: i32 = 2;
+const MAX_UPGRADES_PER_HOUR: usize = 3;
+
+/// How many buildings each zone is missing, recomputed every hour by the `growth_system`
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ZoneDemand {
+    /// Houses needed for the open jobs, and to keep some of them vacant
+    pub residential: i32,
+    /// Stores needed to serve the households
+    pub commercial: i32,
+    /// Jobs needed for the unemployed
+    pub industrial: i32,
+}
+
+impl ZoneDemand {
+    pub fn compute(goria: &Egregoria) -> Self {
+        let (open_jobs, unemployed) = job_balance(goria);
+
+        let map = goria.map();
+        let registry = goria.read::<GoodsCompanyRegistry>();
+        let mut houses = 0;
+        let mut stores = 0;
+        for b in map.buildings().values() {
+            match b.kind {
+                BuildingKind::House => houses += 1,
+                BuildingKind::GoodsCompany(id) => {
+                    let kind = registry.descriptions.get(id).map(|d| &d.kind);
+                    if matches!(kind, Some(CompanyKind::Store)) {
+                        stores += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        drop(registry);
+        drop(map);
+
+        let empty = empty_houses(goria).len() as i32;
+        let households = goria.world.query::<&Household>().iter().count() as i32;
+        let vacant = MIN_VACANT_HOUSES.max((houses as f32 * TARGET_VACANCY) as i32);
+
+        Self {
+            residential: open_jobs - unemployed + vacant - empty,
+            commercial: households / HOUSEHOLDS_PER_STORE - stores,
+            industrial: unemployed + MIN_OPEN_JOBS - open_jobs,
+        }
+    }
+}
+
+/// Every hour, builds houses on residential lots and companies on commercial and industrial
+/// lots depending on the demand. The lots with the highest land value are built first.
+/// Jobs are first added to the companies on valuable land, see `upgrade_companies`.
+/// Houses are never densified: a house is owned by a single household.
+#[profiling::function]
+pub fn growth_system(goria: &mut Egregoria) {
+    if !goria.read::<GameTime>().tick(SECONDS_PER_HOUR as u32) {
+        return;
+    }
+
+    let demand = ZoneDemand::compute(goria);
+    *goria.write::<ZoneDemand>() = demand;
+
+    let n_houses = demand.residential.clamp(0, MAX_HOUSES_PER_HOUR) as usize;
+    let lots = zoned_lots(
+        &goria.map(),
+        &goria.read::<LandValue>(),
+        LotKind::Residential,
+    );
+    for lot in lots.into_iter().take(n_houses) {
+        let Some(house) = goria.map_mut().build_house(lot) else {
+            continue;
+        };
+        goria.write::<BuildingInfos>().insert(house);
+    }
+
+    if demand.commercial > 0 {
+        build_company(goria, LotKind::Commercial, |k| {
+            matches!(k, CompanyKind::Store)
+        });
+    }
+    let mut jobs_needed = demand.industrial;
+    if jobs_needed > 0 {
+        jobs_needed -= upgrade_companies(goria, jobs_needed);
+    }
+    if jobs_needed > 0 {
+        build_company(goria, LotKind::Industrial, |k| {
+            !matches!(k, CompanyKind::Store)
+        });
+    }
+}
+
+/// Gives more jobs to the companies on the most valuable land, by steps of the number of
+/// workers of their description. Returns the number of jobs added.
+pub fn upgrade_companies(goria: &mut Egregoria, jobs_needed: i32) -> i32 {
+    let map = goria.map();
+    let land_value = goria.read::<LandValue>();
+    let registry = goria.read::<GoodsCompanyRegistry>();
+
+    let mut candidates: Vec<_> = goria
+        .world
+        .query::<&GoodsCompany>()
+        .iter()
+        .filter_map(|(e, company)| {
+            let b = map.buildings().get(company.building)?;
+            let BuildingKind::GoodsCompany(id) = b.kind else {
+                return None;
+            };
+            let step = registry.descriptions.get(id)?.n_workers;
+            let value = land_value.building(b);
+            (step > 0
+                && value >= UPGRADE_LAND_VALUE
+                && company.max_workers < step * (1 + MAX_UPGRADES))
+                .then_some((Reverse(OrderedFloat(value)), e, step, b.door_pos))
+        })
+        .collect();
+    candidates.sort_unstable_by_key(|&(value, e, _, _)| (value, e));
+    drop(registry);
+    drop(land_value);
+    drop(map);
+
+    let job_opening = goria.read::<ItemRegistry>().id("job-opening");
+    let mut added = 0;
+    for (_, e, step, door_pos) in candidates.into_iter().take(MAX_UPGRADES_PER_HOUR) {
+        if added >= jobs_needed {
+            break;
+        }
+        let Some(mut company) = goria.comp_mut::<GoodsCompany>(e) else {
+            continue;
+        };
+        company.max_workers += step;
+        let max_workers = company.max_workers;
+        drop(company);
+
+        let soul = SoulID(e);
+        let mut market = goria.write::<Market>();
+        market.produce(soul, job_opening, step);
+        market.sell_all(soul, door_pos.xy(), job_opening, 0);
+        added += step;
+        log::info!("company {:?} upgraded to {} workers", soul, max_workers);
+    }
+    added
+}
+
+/// Lots of this zone, the highest land value first
+fn zoned_lots(map: &Map, land_value: &LandValue, kind: LotKind) -> Vec<LotID> {
+    let mut lots: Vec<_> = map
+        .lots()
+        .values()
+        .filter(|lot| lot.kind == kind)
+        .map(|lot| (Reverse(OrderedFloat(land_value.lot(lot))), lot.id))
+        .collect();
+    lots.sort_unstable();
+    lots.into_iter().map(|(_, id)| id).collect()
+}
+
+/// Builds one of the allowed companies, the ones the city has the fewest buildings of first,
+/// on the lot of the zone with the highest land value it fits on
+fn build_company(
+    goria: &mut Egregoria,
+    zone: LotKind,
+    allowed: fn(&CompanyKind) -> bool,
+) -> Option<BuildingID> {
+    let map = goria.map();
+    let land_value = goria.read::<LandValue>();
+    let registry = goria.read::<GoodsCompanyRegistry>();
+
+    let mut candidates: Vec<_> = registry
+        .descriptions
+        .values()
+        .filter(|d| allowed(&d.kind) && d.n_workers > 0 && d.zone.is_none())
+        .map(|d| {
+            let kind = BuildingKind::GoodsCompany(d.id);
+            let n = map.buildings().values().filter(|b| b.kind == kind).count();
+            (n, d.id, d.size, d.bgen)
+        })
+        .collect();
+    candidates.sort_by_key(|&(n, id, _, _)| (n, id));
+
+    let found = zoned_lots(&map, &land_value, zone)
+        .into_iter()
+        .find_map(|lot| {
+            candidates.iter().find_map(|&(_, id, size, gen)| {
+                Some((company_footprint(&map, lot, size)?, id, gen))
+            })
+        });
+    drop(registry);
+    drop(land_value);
+    drop(map);
+
+    let (obb, id, gen) = found?;
+    let b =
+        goria
+            .map_mut()
+            .build_special_building(&obb, BuildingKind::GoodsCompany(id), gen, None)?;
+    goria.write::<BuildingInfos>().insert(b);
+    Some(b)
+}
+
+/// Footprint of a building of this size facing the road of the lot.
+/// None if it overlaps anything other than this road and lots of the same zone.
+fn company_footprint(map: &Map, lot: LotID, size: f32) -> Option<OBB> {
+    let lot = map.lots().get(lot)?;
+    let depth = lot.shape.axis()[1];
+    let dir = depth.try_normalize()?;
+    let center = lot.shape.center() + dir * (size - depth.mag()) * 0.5;
+    let obb = OBB::new(center, dir, size, size);
+
+    let filter = ProjectFilter::ROAD | ProjectFilter::INTER | ProjectFilter::BUILDING;
+    map.spatial_map()
+        .query(obb, filter | ProjectFilter::LOT)
+        .all(|p| match p {
+            ProjectKind::Road(r) => r == lot.parent,
+            ProjectKind::Lot(id) => map.lots().get(id).map_or(false, |l| l.kind == lot.kind),
+            _ => false,
+        })
+        .then_some(obb)
+}
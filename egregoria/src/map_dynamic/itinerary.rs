@@ -1,4 +1,4 @@
-use crate::map::{Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
+use crate::map::{LaneID, Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
 use crate::utils::time::GameTime;
 use crate::Speed;
 use egui_inspect::egui::Ui;
@@ -56,6 +56,11 @@ pub struct Route {
 
 pub const OBJECTIVE_OK_DIST: f32 = 3.0;
 
+/// A car takes a new route when the remaining one is this many times slower
+const REROUTE_RATIO: f32 = 1.3;
+/// and when it saves at least this much time, in seconds
+const REROUTE_MIN_GAIN: f32 = 10.0;
+
 impl Itinerary {
     pub const NONE: Self = Self {
         kind: ItineraryKind::None,
@@ -100,30 +105,54 @@ impl Itinerary {
         let start_lane = pathkind.nearest_lane(map, start)?;
         let end_lane = pathkind.nearest_lane(map, end)?;
 
-        let mut cur = Traversable::new(TraverseKind::Lane(start_lane), TraverseDirection::Forward);
+        if let Some(it) = Self::local_route(start, end, start_lane, end_lane, map, pathkind) {
+            return Some(it);
+        }
 
-        if start_lane == end_lane {
-            if let Some(mut p) = pathkind.local_route(map, start_lane, start, end) {
-                p.reverse();
-                return Some(Itinerary {
-                    kind: ItineraryKind::Route(
-                        Route {
-                            reversed_route: vec![],
-                            end_pos: end,
-                            cur,
-                        },
-                        pathkind,
-                    ),
-                    reversed_local_path: p.into_vec(),
-                });
-            }
+        let cur = Traversable::new(TraverseKind::Lane(start_lane), TraverseDirection::Forward);
+        let path = pathkind.path(map, cur, end_lane)?;
+        Self::from_path(start, end, start_lane, path, map, pathkind)
+    }
+
+    /// An itinerary staying on the lane, when `start` and `end` are on it in the right order
+    pub(crate) fn local_route(
+        start: Vec3,
+        end: Vec3,
+        start_lane: LaneID,
+        end_lane: LaneID,
+        map: &Map,
+        pathkind: PathKind,
+    ) -> Option<Itinerary> {
+        if start_lane != end_lane {
+            return None;
         }
+        let mut p = pathkind.local_route(map, start_lane, start, end)?;
+        p.reverse();
+        let cur = Traversable::new(TraverseKind::Lane(start_lane), TraverseDirection::Forward);
+        Some(Itinerary {
+            kind: ItineraryKind::Route(
+                Route {
+                    reversed_route: vec![],
+                    end_pos: end,
+                    cur,
+                },
+                pathkind,
+            ),
+            reversed_local_path: p.into_vec(),
+        })
+    }
 
-        let mut reversed_route: Vec<Traversable> = pathkind
-            .path(map, cur, end_lane)?
-            .into_iter()
-            .rev()
-            .collect();
+    /// An itinerary following `path`, as given by `Pathfinder::path` from `start_lane`
+    pub(crate) fn from_path(
+        start: Vec3,
+        end: Vec3,
+        start_lane: LaneID,
+        path: Vec<Traversable>,
+        map: &Map,
+        pathkind: PathKind,
+    ) -> Option<Itinerary> {
+        let mut cur = Traversable::new(TraverseKind::Lane(start_lane), TraverseDirection::Forward);
+        let mut reversed_route: Vec<Traversable> = path.into_iter().rev().collect();
 
         reversed_route.pop(); // Remove start
 
@@ -166,6 +195,39 @@ impl Itinerary {
         Some(it)
     }
 
+    /// Replaces the rest of a car route when the observed travel times make it much slower
+    /// than a fresh one. Returns whether the route changed.
+    pub fn reroute_if_faster(&mut self, map: &Map) -> bool {
+        let times = map.travel_times();
+        if !times.enabled {
+            return false;
+        }
+        let ItineraryKind::Route(ref mut r, PathKind::Vehicle) = self.kind else {
+            return false;
+        };
+        // Only reroute from lanes, the turn the car is taking leads to a single lane anyway
+        if !matches!(r.cur.kind, TraverseKind::Lane(_)) {
+            return false;
+        }
+        let Some(&Traversable {
+            kind: TraverseKind::Lane(end),
+            ..
+        }) = r.reversed_route.first()
+        else {
+            return false;
+        };
+
+        let remaining = unwrap_ret!(times.path_time(map, &r.reversed_route), false);
+        let path = unwrap_ret!(PathKind::Vehicle.path(map, r.cur, end), false);
+        let fresh = unwrap_ret!(times.path_time(map, &path[1..]), false);
+
+        if remaining < fresh * REROUTE_RATIO || remaining - fresh < REROUTE_MIN_GAIN {
+            return false;
+        }
+        r.reversed_route = path.into_iter().skip(1).rev().collect();
+        true
+    }
+
     fn advance(&mut self, map: &Map) -> Option<Vec3> {
         let v = self.reversed_local_path.pop();
 
@@ -234,24 +296,38 @@ impl Itinerary {
             return position + (p - position).normalize_to(dist_to_move);
         }
 
+        // The route itself is computed by the `RoutingService`
         if let ItineraryKind::WaitForReroute {
-            kind,
-            dest,
-            ref mut wait_ticks,
+            ref mut wait_ticks, ..
         } = self.kind
         {
-            if *wait_ticks > 0 {
-                *wait_ticks -= 1;
-                return position;
-            }
-            *self = unwrap_or!(Self::route(position, dest, map, kind), {
-                *wait_ticks = 200;
-                return position;
-            });
+            *wait_ticks = wait_ticks.saturating_sub(1);
         }
         position
     }
 
+    /// Kind and destination of the route to compute, when waiting for a route
+    pub(crate) fn wants_route(&self) -> Option<(PathKind, Vec3)> {
+        match self.kind {
+            ItineraryKind::WaitForReroute {
+                kind,
+                dest,
+                wait_ticks: 0,
+            } => Some((kind, dest)),
+            _ => None,
+        }
+    }
+
+    /// Waits for some ticks before asking for a route again
+    pub(crate) fn retry_later(&mut self, ticks: u16) {
+        if let ItineraryKind::WaitForReroute {
+            ref mut wait_ticks, ..
+        } = self.kind
+        {
+            *wait_ticks = ticks;
+        }
+    }
+
     pub fn end_pos(&self) -> Option<Vec3> {
         match self.kind {
             ItineraryKind::None => None,
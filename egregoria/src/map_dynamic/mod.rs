@@ -1,11 +1,19 @@
 mod binfos;
 mod dispatch;
+mod growth;
 mod itinerary;
 mod parking;
+mod rerouting;
 mod router;
+mod routing_service;
+mod signals;
 
 pub use binfos::*;
 pub use dispatch::*;
+pub use growth::*;
 pub use itinerary::*;
 pub use parking::*;
+pub use rerouting::*;
 pub use router::*;
+pub use routing_service::*;
+pub use signals::*;
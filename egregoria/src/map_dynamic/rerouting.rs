@@ -0,0 +1,95 @@
+use crate::map::{Map, TraverseKind};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::Vehicle;
+use crate::utils::time::{GameTime, Tick};
+use hecs::{Entity, World};
+use rayon::prelude::{ParallelBridge, ParallelIterator};
+use resources::Resources;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Every this many ticks, observed travel times slowly go back to the free-flow times
+/// and are published to pathfinding
+const PUBLISH_TICKS: u32 = 200;
+/// Each car checks if a faster route exists every this many ticks
+const REROUTE_TICKS: u32 = 200;
+
+/// The lane or turn each vehicle is on and when it got there.
+/// The time is None when the vehicle was first seen in the middle of it,
+/// so that only complete traversals are measured.
+#[derive(Default, Serialize, Deserialize)]
+pub struct TravelTimeObserver {
+    entered: BTreeMap<Entity, (TraverseKind, Option<f64>)>,
+}
+
+/// Measures how long vehicles take to go through lanes and turns to update the map travel times
+#[profiling::function]
+pub fn travel_times_system(world: &mut World, resources: &mut Resources) {
+    let mut map = resources.get_mut::<Map>().unwrap();
+    let mut observer = resources.get_mut::<TravelTimeObserver>().unwrap();
+    let time = resources.get::<GameTime>().unwrap().timestamp;
+    let tick = resources.get::<Tick>().unwrap().0;
+
+    if !map.travel_times.enabled {
+        observer.entered.clear();
+        return;
+    }
+
+    let map = &mut *map;
+    let mut entered = BTreeMap::new();
+    for (e, (itin, _)) in world.query::<(&Itinerary, &Vehicle)>().iter() {
+        let Some(kind) = itin.get_travers().map(|t| t.kind) else {
+            continue;
+        };
+
+        let since = match observer.entered.get(&e) {
+            Some(&(last, since)) if last == kind => since,
+            Some(&(last, Some(since))) => {
+                let elapsed = (time - since) as f32;
+                match last {
+                    TraverseKind::Lane(id) => {
+                        if let Some(lane) = map.lanes.get(id) {
+                            map.travel_times.observe_lane(lane, elapsed);
+                        }
+                    }
+                    TraverseKind::Turn(id) => map.travel_times.observe_turn(id, elapsed),
+                }
+                Some(time)
+            }
+            Some(_) => Some(time),
+            None => None,
+        };
+        entered.insert(e, (kind, since));
+    }
+    observer.entered = entered;
+
+    if tick % PUBLISH_TICKS == 0 {
+        map.travel_times.decay(&map.lanes);
+        map.travel_times.publish();
+    }
+}
+
+/// Gives cars a new route when theirs became much slower than the alternatives.
+/// Cars are spread over `REROUTE_TICKS` so only a few of them look for a route each tick.
+#[profiling::function]
+pub fn reroute_system(world: &mut World, resources: &mut Resources) {
+    let map = &*resources.get::<Map>().unwrap();
+    let tick = resources.get::<Tick>().unwrap().0;
+
+    if !map.travel_times().enabled {
+        return;
+    }
+
+    world
+        .query::<(&mut Itinerary, &Vehicle)>()
+        .iter_batched(32)
+        .par_bridge()
+        .for_each(|chunk| {
+            chunk.for_each(|(e, (itin, _))| {
+                if tick.wrapping_add(e.id()) % REROUTE_TICKS != 0 {
+                    return;
+                }
+                itin.reroute_if_faster(map);
+            })
+        });
+}
@@ -1,6 +1,10 @@
 use crate::map::{BuildingID, Map, PathKind};
 use crate::map_dynamic::{Itinerary, ParkingManagement, ParkingReserveError, SpotReservation};
 use crate::physics::{Collider, CollisionWorld, Speed};
+use crate::transportation::bus::{BusLineID, BusLines};
+use crate::transportation::train::TrainID;
+use crate::transportation::train_line::{TrainLineID, TrainLines};
+use crate::transportation::transit::WALKING_SPEED_ESTIMATE;
 use crate::transportation::{
     put_pedestrian_in_coworld, unpark, Location, Vehicle, VehicleID, VehicleState,
 };
@@ -9,6 +13,7 @@ use crate::{Egregoria, ParCommandBuffer};
 use egui_inspect::Inspect;
 use geom::{Spline3, Transform, Vec3};
 use hecs::{Component, Entity, Ref, World};
+use ordered_float::OrderedFloat;
 use rayon::prelude::{ParallelBridge, ParallelIterator};
 use resources::Resources;
 use serde::{Deserialize, Serialize};
@@ -51,27 +56,52 @@ pub enum RoutingStep {
     GetOutVehicle(VehicleID),
     GetInBuilding(BuildingID),
     GetOutBuilding(BuildingID),
+    /// Waits for a bus of the line at stop `from` and rides it until stop `to`
+    RideBus {
+        line: BusLineID,
+        from: usize,
+        to: usize,
+        bus: Option<VehicleID>,
+    },
+    /// Waits for a train of the line at station `from` and rides it until station `to`
+    RideTrain {
+        line: TrainLineID,
+        from: usize,
+        to: usize,
+        train: Option<TrainID>,
+    },
+    GetOutTrain(TrainID),
 }
 
 debug_inspect_impl!(RoutingStep);
 
+/// Used to compare walking, driving and public transport trips
+const DRIVING_SPEED_ESTIMATE: f32 = 7.0;
+/// Time lost getting the car out and parking it, in seconds
+const DRIVING_OVERHEAD: f32 = 30.0;
+
 #[profiling::function]
 pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
     let ra = &*resources.get().unwrap();
     let rb = &mut *resources.get_mut().unwrap();
+    let rc = &*resources.get().unwrap();
+    let rd = &*resources.get().unwrap();
     world
-        .query::<(&mut Router, &Location)>()
+        .query::<(&mut Router, &Location, &Transform)>()
         .iter()
-        .for_each(|(_, (a, b))| {
-            routing_changed(ra, rb, a, b, world);
+        .for_each(|(_, (a, b, c))| {
+            routing_changed(ra, rb, rc, rd, a, b, c, world);
         });
 }
 
 pub fn routing_changed(
     map: &Map,
     parking: &mut ParkingManagement,
+    lines: &BusLines,
+    trains: &TrainLines,
     router: &mut Router,
     loc: &Location,
+    trans: &Transform,
     world: &World,
 ) {
     if router.cur_dest != router.target_dest {
@@ -80,13 +110,14 @@ pub fn routing_changed(
         router.clear_steps(parking);
         match dest {
             Destination::Outside(pos) => {
-                router.steps = match router.steps_to(pos, parking, map, loc, world) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        router.last_error = Some(e);
-                        return;
-                    }
-                };
+                router.steps =
+                    match router.steps_to(pos, parking, lines, trains, map, loc, trans, world) {
+                        Ok(x) => x,
+                        Err(e) => {
+                            router.last_error = Some(e);
+                            return;
+                        }
+                    };
             }
             Destination::Building(build) => {
                 if let Location::Building(cur_build) = loc {
@@ -104,7 +135,9 @@ pub fn routing_changed(
                     }
                 };
                 let door_pos = bobj.door_pos;
-                router.steps = match router.steps_to(door_pos, parking, map, loc, world) {
+                router.steps = match router
+                    .steps_to(door_pos, parking, lines, trains, map, loc, trans, world)
+                {
                     Ok(x) => x,
                     Err(e) => {
                         router.last_error = Some(e);
@@ -125,6 +158,8 @@ pub fn routing_changed(
 pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
     let ra = &*resources.get().unwrap();
     let rb = &*resources.get().unwrap();
+    let rc = &*resources.get().unwrap();
+    let rd = &*resources.get().unwrap();
     world
         .query::<(
             &Transform,
@@ -136,13 +171,17 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
         .iter_batched(32)
         .par_bridge()
         .for_each(|batch| {
-            batch.for_each(|(e, (a, b, c, d, f))| routing_update(ra, rb, e, a, b, c, d, f, world))
+            batch.for_each(|(e, (a, b, c, d, f))| {
+                routing_update(ra, rb, rc, rd, e, a, b, c, d, f, world)
+            })
         });
 }
 
 pub fn routing_update(
     map: &Map,
     cbuf: &ParCommandBuffer,
+    lines: &BusLines,
+    trains: &TrainLines,
     body: Entity,
     trans: &Transform,
     itin: &Itinerary,
@@ -160,6 +199,9 @@ pub fn routing_update(
         Location::Vehicle(id) => comp::<Transform>(world, id.0)
             .map(|x| x.position)
             .unwrap_or_else(|| trans.position),
+        Location::Train(id) => comp::<Transform>(world, id.0)
+            .map(|x| x.position)
+            .unwrap_or_else(|| trans.position),
         Location::Building(id) => map
             .buildings()
             .get(id)
@@ -183,6 +225,11 @@ pub fn routing_update(
             RoutingStep::GetOutVehicle(_) => true,
             RoutingStep::GetInBuilding(_) => true,
             RoutingStep::GetOutBuilding(_) => true,
+            RoutingStep::RideBus { line, to, bus, .. } => lines.ride_over(world, line, to, bus),
+            RoutingStep::RideTrain {
+                line, to, train, ..
+            } => trains.ride_over(world, line, to, train),
+            RoutingStep::GetOutTrain(_) => true,
         };
     }
     let mut next_step_ready = true;
@@ -203,6 +250,13 @@ pub fn routing_update(
                 .map(|b| b.door_pos.is_close(pos, 3.0))
                 .unwrap_or(true),
             RoutingStep::GetOutBuilding(_) => true,
+            RoutingStep::RideBus { line, from, .. } => {
+                lines.get(line).is_none() || lines.boarding(line, from).is_some()
+            }
+            RoutingStep::RideTrain { line, from, .. } => {
+                trains.get(line).is_none() || trains.boarding(line, from).is_some()
+            }
+            RoutingStep::GetOutTrain(_) => true,
         };
     }
 
@@ -243,10 +297,10 @@ pub fn routing_update(
                 walk_inside(body, cbuf, kin);
             }
             RoutingStep::GetOutVehicle(vehicle) => {
-                let pos = comp::<Transform>(world, vehicle.0)
-                    .map(|vtrans| vtrans.position + vtrans.dir.cross(Vec3::Z) * 2.0)
-                    .unwrap_or(pos);
-                walk_outside(body, pos, cbuf, loc);
+                walk_outside(body, beside(world, vehicle.0, pos), cbuf, loc);
+            }
+            RoutingStep::GetOutTrain(train) => {
+                walk_outside(body, beside(world, train.0, pos), cbuf, loc);
             }
             RoutingStep::GetInBuilding(build) => {
                 if !map.buildings().contains_key(build) {
@@ -264,6 +318,36 @@ pub fn routing_update(
                     .unwrap_or(pos);
                 walk_outside(body, wpos, cbuf, loc);
             }
+            RoutingStep::RideBus {
+                line,
+                from,
+                ref mut bus,
+                ..
+            } => {
+                let Some(boarding) = lines.boarding(line, from) else {
+                    router.reset_dest();
+                    return;
+                };
+                *bus = Some(boarding);
+                *loc = Location::Vehicle(boarding);
+                walk_inside(body, cbuf, kin);
+                router.steps.push(RoutingStep::GetOutVehicle(boarding));
+            }
+            RoutingStep::RideTrain {
+                line,
+                from,
+                ref mut train,
+                ..
+            } => {
+                let Some(boarding) = trains.boarding(line, from) else {
+                    router.reset_dest();
+                    return;
+                };
+                *train = Some(boarding);
+                *loc = Location::Train(boarding);
+                walk_inside(body, cbuf, kin);
+                router.steps.push(RoutingStep::GetOutTrain(boarding));
+            }
         }
     }
 }
@@ -278,6 +362,13 @@ fn comp<T: Component>(sw: &World, e: Entity) -> Option<Ref<T>> {
     sw.get::<&T>(e).ok()
 }
 
+/// Where to get out of a vehicle or a train, `default` if it doesn't exist anymore
+fn beside(world: &World, vehicle: Entity, default: Vec3) -> Vec3 {
+    comp::<Transform>(world, vehicle)
+        .map(|vtrans| vtrans.position + vtrans.dir.cross(Vec3::Z) * 2.0)
+        .unwrap_or(default)
+}
+
 fn walk_inside(body: Entity, cbuf: &ParCommandBuffer, kin: &mut Speed) {
     cbuf.remove_component_drop::<Collider>(body);
     kin.speed = 0.0;
@@ -364,13 +455,65 @@ impl Router {
         &mut self,
         obj: Vec3,
         parking: &mut ParkingManagement,
+        lines: &BusLines,
+        trains: &TrainLines,
         map: &Map,
         loc: &Location,
+        trans: &Transform,
         world: &World,
     ) -> Result<Vec<RoutingStep>, RouterError> {
         let mut steps = vec![];
+        let mut start = trans.position;
         if let Location::Building(cur_build) = loc {
             steps.push(RoutingStep::GetOutBuilding(*cur_build));
+            if let Some(b) = map.buildings().get(*cur_build) {
+                start = b.door_pos;
+            }
+        }
+        if let Location::Train(train) = loc {
+            steps.push(RoutingStep::GetOutTrain(*train));
+            start = beside(world, train.0, start);
+        }
+
+        // Drivers on a mission (e.g. with a truck) don't take public transport
+        if !matches!(loc, Location::Vehicle(_)) && self.vehicle == self.personal_car {
+            let bus = lines.best_trip(start, obj).map(|trip| {
+                let ride = RoutingStep::RideBus {
+                    line: trip.line,
+                    from: trip.from,
+                    to: trip.to,
+                    bus: None,
+                };
+                (trip.duration, trip.from_pos, ride)
+            });
+            let train = trains.best_trip(start, obj).map(|trip| {
+                let ride = RoutingStep::RideTrain {
+                    line: trip.line,
+                    from: trip.from,
+                    to: trip.to,
+                    train: None,
+                };
+                (trip.duration, trip.from_pos, ride)
+            });
+
+            if let Some((duration, from_pos, ride)) = bus
+                .into_iter()
+                .chain(train)
+                .min_by_key(|(duration, _, _)| OrderedFloat(*duration))
+            {
+                let dist = start.distance(obj);
+                let walk = dist / WALKING_SPEED_ESTIMATE;
+                let drive = self
+                    .vehicle
+                    .map(|_| dist / DRIVING_SPEED_ESTIMATE + DRIVING_OVERHEAD);
+
+                if duration < walk && drive.map_or(true, |drive| duration < drive) {
+                    steps.push(RoutingStep::WalkTo(from_pos));
+                    steps.push(ride);
+                    steps.push(RoutingStep::WalkTo(obj));
+                    return Ok(steps);
+                }
+            }
         }
 
         if let Some(car) = self.vehicle {
@@ -0,0 +1,155 @@
+use crate::map::{LaneID, Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
+use crate::map_dynamic::Itinerary;
+use geom::{Transform, Vec3};
+use hecs::{Entity, World};
+use rayon::prelude::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+use resources::Resources;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The cache is emptied when it holds more paths than this
+const CACHE_CAPACITY: usize = 20000;
+/// Ticks to wait before asking again when no route was found
+const RETRY_TICKS: u16 = 200;
+
+/// (kind, start lane, end lane)
+type PathKey = (PathKind, LaneID, LaneID);
+
+/// Computes the routes of all the itineraries waiting for one at once.
+/// Identical paths are only computed once, in parallel, and are cached until the map
+/// or the published travel times change.
+/// Paths only depend on the map so the cache isn't saved, a fresh one gives the same routes.
+#[derive(Default)]
+pub struct RoutingService {
+    dirt_id: u32,
+    times: (bool, u32),
+    cache: BTreeMap<PathKey, Option<Vec<Traversable>>>,
+    /// Number of paths computed since the start
+    pub computed: u64,
+    /// Number of requests answered by the cache or by an identical request of the same tick
+    pub shared: u64,
+}
+
+enum Plan {
+    Local(Itinerary),
+    Path(LaneID, PathKey),
+    NotFound,
+}
+
+impl RoutingService {
+    fn invalidate(&mut self, map: &Map) {
+        if self.dirt_id != map.dirt_id.0 {
+            self.dirt_id = map.dirt_id.0;
+            self.cache.clear();
+        }
+
+        let tt = map.travel_times();
+        if self.times != (tt.enabled, tt.version) {
+            self.times = (tt.enabled, tt.version);
+            // Rail paths are computed by the car pathfinder
+            self.cache
+                .retain(|(kind, _, _), _| !matches!(kind, PathKind::Vehicle | PathKind::Rail));
+        }
+
+        if self.cache.len() > CACHE_CAPACITY {
+            self.cache.clear();
+        }
+    }
+
+    /// Computes the paths that aren't cached yet in parallel, returns how many were computed
+    fn fill(&mut self, map: &Map, keys: BTreeSet<PathKey>) -> usize {
+        let missing: Vec<PathKey> = keys
+            .into_iter()
+            .filter(|k| !self.cache.contains_key(k))
+            .collect();
+
+        let paths: Vec<Option<Vec<Traversable>>> = missing
+            .par_iter()
+            .map(|&(kind, start, end)| {
+                let start = Traversable::new(TraverseKind::Lane(start), TraverseDirection::Forward);
+                kind.path(map, start, end)
+            })
+            .collect();
+
+        let n = missing.len();
+        self.computed += n as u64;
+        self.cache.extend(missing.into_iter().zip(paths));
+        n
+    }
+}
+
+fn plan(map: &Map, kind: PathKind, start: Vec3, end: Vec3) -> Plan {
+    let lanes = kind
+        .nearest_lane(map, start)
+        .zip(kind.nearest_lane(map, end));
+    let Some((start_lane, end_lane)) = lanes else {
+        return Plan::NotFound;
+    };
+    if let Some(it) = Itinerary::local_route(start, end, start_lane, end_lane, map, kind) {
+        return Plan::Local(it);
+    }
+    Plan::Path(start_lane, (kind, start_lane, end_lane))
+}
+
+/// Gives a route to the itineraries waiting for one.
+/// Results are applied in the query order so the simulation stays deterministic.
+#[profiling::function]
+pub fn routing_service_system(world: &mut World, resources: &mut Resources) {
+    let map = &*resources.get::<Map>().unwrap();
+    let mut service = resources.get_mut::<RoutingService>().unwrap();
+
+    let requests: Vec<(Entity, PathKind, Vec3, Vec3)> = world
+        .query::<(&Transform, &Itinerary)>()
+        .iter()
+        .filter_map(|(e, (trans, itin))| {
+            let (kind, dest) = itin.wants_route()?;
+            Some((e, kind, trans.position, dest))
+        })
+        .collect();
+    if requests.is_empty() {
+        return;
+    }
+
+    service.invalidate(map);
+
+    let plans: Vec<Plan> = requests
+        .par_iter()
+        .map(|&(_, kind, start, end)| plan(map, kind, start, end))
+        .collect();
+
+    let keys: Vec<PathKey> = plans
+        .iter()
+        .filter_map(|p| match p {
+            Plan::Path(_, key) => Some(*key),
+            _ => None,
+        })
+        .collect();
+    let n_requests = keys.len();
+    let computed = service.fill(map, keys.into_iter().collect());
+    service.shared += (n_requests - computed) as u64;
+
+    let cache = &service.cache;
+    let itins: Vec<Option<Itinerary>> = plans
+        .into_par_iter()
+        .zip(&requests)
+        .map(|(plan, &(_, kind, start, end))| match plan {
+            Plan::Local(it) => Some(it),
+            Plan::Path(start_lane, key) => {
+                let path = cache.get(&key)?.clone()?;
+                Itinerary::from_path(start, end, start_lane, path, map, kind)
+            }
+            Plan::NotFound => None,
+        })
+        .collect();
+
+    for ((e, ..), it) in requests.into_iter().zip(itins) {
+        let Ok(itin) = world.query_one_mut::<&mut Itinerary>(e) else {
+            continue;
+        };
+        match it {
+            Some(it) => *itin = it,
+            None => itin.retry_later(RETRY_TICKS),
+        }
+    }
+}
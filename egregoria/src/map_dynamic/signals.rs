@@ -0,0 +1,224 @@
+use crate::map::{
+    IntersectionID, LaneID, LightPolicy, Map, TrafficBehavior, TrafficControl, TraverseKind,
+};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::Vehicle;
+use crate::utils::time::GameTime;
+use geom::Transform;
+use hecs::World;
+use resources::Resources;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A phase stays green at least this long, in seconds
+const MIN_GREEN: f32 = 6.0;
+/// A phase can't keep the green longer than this while others are waiting, in seconds
+const MAX_GREEN: f32 = 40.0;
+const ORANGE_TIME: f32 = 4.0;
+/// Every light stays red after the orange so the intersection clears, in seconds
+const ALL_RED_TIME: f32 = 2.0;
+/// Vehicles closer than this to the end of a lane are queuing at the light, in meters
+const DETECTOR_LENGTH: f32 = 30.0;
+
+/// State of the lights of the intersections using `LightPolicy::Actuated`
+#[derive(Default, Serialize, Deserialize)]
+pub struct SignalControllers {
+    controllers: BTreeMap<IntersectionID, ActuatedController>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ActuatedController {
+    pub phase: u8,
+    /// Time since the current phase turned green or orange, in seconds
+    pub elapsed: f32,
+    /// The phase is orange for `ORANGE_TIME` then all lights are red for `ALL_RED_TIME`
+    pub orange: bool,
+}
+
+impl SignalControllers {
+    pub fn get(&self, id: IntersectionID) -> Option<&ActuatedController> {
+        self.controllers.get(&id)
+    }
+}
+
+impl ActuatedController {
+    /// `demand` is the number of vehicles queuing for each phase.
+    /// The green is kept while vehicles are detected for the current phase or nobody else waits,
+    /// and is given to the next phase with a queue otherwise.
+    pub fn update(&mut self, demand: &[u32], delta: f32) {
+        let n_phases = demand.len();
+        if n_phases == 0 {
+            return;
+        }
+        if self.phase as usize >= n_phases {
+            *self = Self::default();
+        }
+
+        self.elapsed += delta;
+        let cur = self.phase as usize;
+
+        if self.orange {
+            if self.elapsed >= ORANGE_TIME + ALL_RED_TIME {
+                let next = (1..n_phases)
+                    .map(|i| (cur + i) % n_phases)
+                    .find(|&i| demand[i] > 0)
+                    .unwrap_or((cur + 1) % n_phases);
+                self.phase = next as u8;
+                self.elapsed = 0.0;
+                self.orange = false;
+            }
+            return;
+        }
+
+        if self.elapsed < MIN_GREEN {
+            return;
+        }
+
+        let others_waiting = demand.iter().enumerate().any(|(i, &d)| i != cur && d > 0);
+
+        if others_waiting && (demand[cur] == 0 || self.elapsed >= MAX_GREEN) {
+            self.orange = true;
+            self.elapsed = 0.0;
+        }
+    }
+
+    pub fn is_all_red(&self) -> bool {
+        self.orange && self.elapsed >= ORANGE_TIME
+    }
+
+    pub fn behavior(&self, phase: u8) -> TrafficBehavior {
+        if phase != self.phase || self.is_all_red() {
+            TrafficBehavior::RED
+        } else if self.orange {
+            TrafficBehavior::ORANGE
+        } else {
+            TrafficBehavior::GREEN
+        }
+    }
+}
+
+/// Detects the vehicles queuing at actuated lights and switches the lights accordingly
+#[profiling::function]
+pub fn actuated_signals_system(world: &mut World, resources: &mut Resources) {
+    let mut map = resources.get_mut::<Map>().unwrap();
+    let mut signals = resources.get_mut::<SignalControllers>().unwrap();
+    let time = resources.get::<GameTime>().unwrap();
+
+    let mut queues: BTreeMap<LaneID, u32> = BTreeMap::new();
+    for (_, (trans, itin, _)) in world.query::<(&Transform, &Itinerary, &Vehicle)>().iter() {
+        let Some(TraverseKind::Lane(id)) = itin.get_travers().map(|t| t.kind) else {
+            continue;
+        };
+        let lane = unwrap_cont!(map.lanes.get(id));
+        if lane.control.is_actuated()
+            && lane.points.last().is_close(trans.position, DETECTOR_LENGTH)
+        {
+            *queues.entry(id).or_default() += 1;
+        }
+    }
+
+    let map = &mut *map;
+    signals.controllers.retain(|id, _| {
+        map.intersections
+            .get(*id)
+            .map_or(false, |i| i.light_policy == LightPolicy::Actuated)
+    });
+
+    for (id, inter) in map.intersections.iter() {
+        if inter.light_policy != LightPolicy::Actuated {
+            continue;
+        }
+
+        let incoming: Vec<LaneID> = inter
+            .roads
+            .iter()
+            .filter_map(|&r| map.roads.get(r))
+            .flat_map(|r| r.incoming_lanes_to(id).iter().map(|&(lane, _)| lane))
+            .collect();
+
+        let mut demand = vec![];
+        for &lane in &incoming {
+            let phase = match unwrap_cont!(map.lanes.get(lane)).control {
+                TrafficControl::Actuated { phase, .. } => phase as usize,
+                _ => continue,
+            };
+            if demand.len() <= phase {
+                demand.resize(phase + 1, 0);
+            }
+            demand[phase] += queues.get(&lane).copied().unwrap_or(0);
+        }
+
+        let controller = signals.controllers.entry(id).or_default();
+        controller.update(&demand, time.delta);
+
+        for lane in incoming {
+            if let TrafficControl::Actuated {
+                phase,
+                ref mut behavior,
+            } = unwrap_cont!(map.lanes.get_mut(lane)).control
+            {
+                *behavior = controller.behavior(phase);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActuatedController, ALL_RED_TIME, MAX_GREEN, MIN_GREEN, ORANGE_TIME};
+    use crate::map::TrafficBehavior;
+
+    fn run(c: &mut ActuatedController, demand: &[u32], seconds: f32) {
+        for _ in 0..(seconds * 10.0) as u32 {
+            c.update(demand, 0.1);
+        }
+    }
+
+    #[test]
+    fn green_rests_without_demand() {
+        let mut c = ActuatedController::default();
+        run(&mut c, &[0, 0], MAX_GREEN * 2.0);
+        assert_eq!(c.behavior(0), TrafficBehavior::GREEN);
+        assert_eq!(c.behavior(1), TrafficBehavior::RED);
+    }
+
+    #[test]
+    fn green_extends_while_queue_detected() {
+        let mut c = ActuatedController::default();
+        run(&mut c, &[3, 1], MIN_GREEN + 5.0);
+        assert_eq!(c.behavior(0), TrafficBehavior::GREEN);
+
+        // Max green is reached even though vehicles keep coming
+        run(&mut c, &[3, 1], MAX_GREEN);
+        assert_eq!(c.behavior(0), TrafficBehavior::RED);
+        assert_eq!(c.behavior(1), TrafficBehavior::GREEN);
+    }
+
+    #[test]
+    fn gap_out_gives_green_to_waiting_phase() {
+        let mut c = ActuatedController::default();
+        run(&mut c, &[0, 0, 2], MIN_GREEN + 0.5);
+        assert_eq!(c.behavior(0), TrafficBehavior::ORANGE);
+
+        run(&mut c, &[0, 0, 2], ORANGE_TIME + ALL_RED_TIME + 0.5);
+        assert_eq!(c.behavior(0), TrafficBehavior::RED);
+        assert_eq!(c.behavior(1), TrafficBehavior::RED);
+        assert_eq!(c.behavior(2), TrafficBehavior::GREEN);
+    }
+
+    #[test]
+    fn all_red_after_orange() {
+        let mut c = ActuatedController::default();
+        run(&mut c, &[0, 2], MIN_GREEN + 0.5);
+        assert_eq!(c.behavior(0), TrafficBehavior::ORANGE);
+
+        run(&mut c, &[0, 2], ORANGE_TIME);
+        assert!(c.is_all_red());
+        assert_eq!(c.behavior(0), TrafficBehavior::RED);
+        assert_eq!(c.behavior(1), TrafficBehavior::RED);
+
+        run(&mut c, &[0, 2], ALL_RED_TIME);
+        assert_eq!(c.behavior(0), TrafficBehavior::RED);
+        assert_eq!(c.behavior(1), TrafficBehavior::GREEN);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::economy::{find_trade_place, Account, Bought, Market};
+use crate::map::{BuildingID, BuildingKind, ProjectFilter, ProjectKind};
+use crate::map_dynamic::{BuildingInfos, Destination};
+use crate::souls::desire::{DesireDefinition, DesireID, DesireRegistry, DesireTarget};
+use crate::souls::goods_company::GoodsCompanyID;
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::Location;
+use crate::utils::time::{GameInstant, GameTime};
+use crate::{Map, ParCommandBuffer, SoulID};
+use geom::{Transform, Vec2};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How far a human looks for a company building to satisfy a desire
+const SEARCH_RADIUS: f32 = 2000.0;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum DesireState {
+    Empty,
+    WaitingForTrade,
+    GoingTo(BuildingID),
+    Staying {
+        building: BuildingID,
+        since: GameInstant,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Desire {
+    pub last_satisfied: GameInstant,
+    pub state: DesireState,
+}
+
+/// State of the desires defined in the `DesireRegistry` for one human
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Desires(pub BTreeMap<DesireID, Desire>);
+
+debug_inspect_impl!(Desires);
+
+impl Desires {
+    /// Starts tracking the desires that are not tracked yet, and forgets the unknown ones
+    pub fn sync(&mut self, registry: &DesireRegistry, time: &GameTime) {
+        if self.0.len() == registry.definitions.len() {
+            return;
+        }
+        self.0
+            .retain(|&id, _| registry.definitions.contains_key(id));
+        for id in registry.definitions.keys() {
+            self.0.entry(id).or_insert_with(|| Desire {
+                last_satisfied: time.instant(),
+                state: DesireState::Empty,
+            });
+        }
+    }
+}
+
+impl Desire {
+    pub fn score(
+        &self,
+        def: &DesireDefinition,
+        time: &GameTime,
+        loc: &Location,
+        bought: &Bought,
+        account: Option<&Account>,
+        market: &Market,
+    ) -> f32 {
+        let days = self.last_satisfied.elapsed(time) as f32 / GameTime::DAY as f32;
+        let curve = || def.curve.eval(days);
+        match self.state {
+            DesireState::Empty => {
+                if let DesireTarget::Item(item) = def.target {
+                    // Households that cannot pay give up on it for now
+                    if account.map_or(false, |a| a.money < market.import_price(item)) {
+                        return 0.0;
+                    }
+                }
+                curve()
+            }
+            DesireState::WaitingForTrade => {
+                if let DesireTarget::Item(item) = def.target {
+                    if bought.0.get(&item).map(Vec::is_empty).unwrap_or(false) {
+                        return 0.0;
+                    }
+                }
+                curve()
+            }
+            DesireState::GoingTo(b) => {
+                if loc == &Location::Building(b) {
+                    return 1.0;
+                }
+                curve()
+            }
+            DesireState::Staying { .. } => 1.0,
+        }
+    }
+
+    pub fn apply(
+        &mut self,
+        def: &DesireDefinition,
+        cbuf: &ParCommandBuffer,
+        binfos: &BuildingInfos,
+        map: &Map,
+        time: &GameTime,
+        soul: SoulID,
+        trans: &Transform,
+        loc: &Location,
+        bought: &mut Bought,
+    ) -> HumanDecisionKind {
+        use HumanDecisionKind::*;
+        let pos = trans.position.xy();
+        match self.state {
+            DesireState::Empty => match def.target {
+                DesireTarget::Item(item) => {
+                    cbuf.exec_on(soul.0, move |market: &mut Market| {
+                        market.buy(soul, pos, item, 1)
+                    });
+                    self.state = DesireState::WaitingForTrade;
+                    Yield
+                }
+                DesireTarget::Company(company) => {
+                    if let Some(b) = nearest_company(map, pos, company) {
+                        self.state = DesireState::GoingTo(b);
+                        GoTo(Destination::Building(b))
+                    } else {
+                        // Nothing around, try again later
+                        self.last_satisfied = time.instant();
+                        Yield
+                    }
+                }
+            },
+            DesireState::WaitingForTrade => {
+                if let DesireTarget::Item(item) = def.target {
+                    for trade in bought.0.entry(item).or_default().drain(..) {
+                        if let Some(b) = find_trade_place(trade.seller, pos, binfos, map) {
+                            self.state = DesireState::GoingTo(b);
+                        }
+                    }
+                } else {
+                    self.state = DesireState::Empty;
+                }
+                Yield
+            }
+            DesireState::GoingTo(b) => {
+                if loc == &Location::Building(b) {
+                    self.state = DesireState::Staying {
+                        building: b,
+                        since: time.instant(),
+                    };
+                    Yield
+                } else {
+                    GoTo(Destination::Building(b))
+                }
+            }
+            DesireState::Staying { building, since } => {
+                if since.elapsed(time) >= def.duration as f64 {
+                    self.state = DesireState::Empty;
+                    self.last_satisfied = time.instant();
+                    log::debug!("{:?} satisfied {} at {:?}", soul, def.name, building);
+                }
+                Yield
+            }
+        }
+    }
+}
+
+fn nearest_company(map: &Map, pos: Vec2, company: GoodsCompanyID) -> Option<BuildingID> {
+    map.spatial_map()
+        .query_around(pos, SEARCH_RADIUS, ProjectFilter::BUILDING)
+        .filter_map(|p| match p {
+            ProjectKind::Building(id) => map.buildings().get(id),
+            _ => None,
+        })
+        .filter(|b| b.kind == BuildingKind::GoodsCompany(company))
+        .min_by_key(|b| OrderedFloat(b.door_pos.xy().distance2(pos)))
+        .map(|b| b.id)
+}
@@ -1,7 +1,11 @@
 mod buyfood;
+mod generic;
 mod home;
+mod registry;
 mod work;
 
 pub use buyfood::*;
+pub use generic::*;
 pub use home::*;
+pub use registry::*;
 pub use work::*;
@@ -0,0 +1,166 @@
+use crate::economy::{ItemID, ItemRegistry};
+use crate::souls::goods_company::{GoodsCompanyID, GoodsCompanyRegistry};
+use common::saveload::Encoder;
+use hecs::World;
+use resources::Resources;
+use serde::{Deserialize, Serialize};
+use slotmap::{new_key_type, SlotMap};
+
+new_key_type! {
+    pub struct DesireID;
+}
+
+debug_inspect_impl!(DesireID);
+
+/// Score of a desire depending on the number of days since it was last satisfied.
+/// Points are linearly interpolated, the score is constant before the first and after the last.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreCurve(pub Vec<(f32, f32)>);
+
+impl ScoreCurve {
+    pub fn eval(&self, days: f32) -> f32 {
+        let mut prev = match self.0.first() {
+            Some(&(x, y)) if days > x => (x, y),
+            Some(&(_, y)) => return y,
+            None => return 0.0,
+        };
+        for &(x, y) in &self.0 {
+            if days <= x {
+                let t = (days - prev.0) / (x - prev.0);
+                return prev.1 + (y - prev.1) * t;
+            }
+            prev = (x, y);
+        }
+        prev.1
+    }
+}
+
+/// How a desire is satisfied
+#[derive(Debug, Clone, Copy)]
+pub enum DesireTarget {
+    /// Buy the item on the market and go to the building of the seller
+    Item(ItemID),
+    /// Go for free to the nearest building of this company
+    Company(GoodsCompanyID),
+}
+
+#[derive(Debug)]
+pub struct DesireDefinition {
+    pub id: DesireID,
+    pub name: String,
+    pub label: String,
+    pub target: DesireTarget,
+    pub curve: ScoreCurve,
+    /// Time spent at the target building, in seconds
+    pub duration: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DesireDefinitionJSON {
+    pub name: String,
+    pub label: String,
+    #[serde(default)]
+    pub item: Option<String>,
+    #[serde(default)]
+    pub company: Option<String>,
+    pub curve: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub duration: f32,
+}
+
+/// Desires of the humans other than working and going home, defined in `desires.json`.
+/// Only one desire should consume a given item, as they share the `Bought` list.
+#[derive(Default)]
+pub struct DesireRegistry {
+    pub definitions: SlotMap<DesireID, DesireDefinition>,
+}
+
+impl DesireRegistry {
+    pub fn load(&mut self, source: &str, items: &ItemRegistry, companies: &GoodsCompanyRegistry) {
+        let definitions: Vec<DesireDefinitionJSON> =
+            match common::saveload::JSON::decode(source.as_ref()) {
+                Ok(x) => x,
+                Err(e) => {
+                    log::error!("couldn't load desire definitions: {}", e);
+                    return;
+                }
+            };
+
+        for descr in definitions {
+            let target = match (&descr.item, &descr.company) {
+                (Some(item), None) => match items.try_id(item) {
+                    Some(id) => DesireTarget::Item(id),
+                    None => {
+                        log::error!("unknown item {} for desire {}", item, descr.name);
+                        continue;
+                    }
+                },
+                (None, Some(company)) => {
+                    match companies.descriptions.values().find(|c| &c.name == company) {
+                        Some(c) => DesireTarget::Company(c.id),
+                        None => {
+                            log::error!("unknown company {} for desire {}", company, descr.name);
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    log::error!("desire {} needs exactly one item or company", descr.name);
+                    continue;
+                }
+            };
+
+            #[allow(unused_variables)]
+            let id = self
+                .definitions
+                .insert_with_key(move |id| DesireDefinition {
+                    id,
+                    name: descr.name,
+                    label: descr.label,
+                    target,
+                    curve: ScoreCurve(descr.curve),
+                    duration: descr.duration,
+                });
+
+            #[cfg(not(test))]
+            log::debug!("loaded {:?}", &self.definitions[id]);
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Option<DesireID> {
+        self.definitions
+            .values()
+            .find(|d| d.name == name)
+            .map(|d| d.id)
+    }
+}
+
+#[cfg(not(test))]
+const DESIRES_PATH: &str = "assets/desires.json";
+
+#[cfg(test)]
+const DESIRES_PATH: &str = "../assets/desires.json";
+
+pub fn init_desires(_: &mut World, res: &mut Resources) {
+    res.get_mut::<DesireRegistry>().unwrap().load(
+        &std::fs::read_to_string(DESIRES_PATH).unwrap(),
+        &res.get::<ItemRegistry>().unwrap(),
+        &res.get::<GoodsCompanyRegistry>().unwrap(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScoreCurve;
+
+    #[test]
+    fn score_curve() {
+        let curve = ScoreCurve(vec![(1.0, -1.0), (3.0, 0.0), (5.0, 2.0)]);
+        assert_eq!(curve.eval(0.0), -1.0);
+        assert_eq!(curve.eval(2.0), -0.5);
+        assert_eq!(curve.eval(3.0), 0.0);
+        assert_eq!(curve.eval(4.0), 1.0);
+        assert_eq!(curve.eval(10.0), 2.0);
+        assert_eq!(ScoreCurve(vec![]).eval(1.0), 0.0);
+    }
+}
@@ -38,6 +38,10 @@ impl Work {
         }
     }
 
+    pub fn workplace(&self) -> BuildingID {
+        self.workplace
+    }
+
     pub fn apply(&mut self, loc: &Location, router: &Router) -> HumanDecisionKind {
         use HumanDecisionKind::*;
         match self.kind {
@@ -1,5 +1,7 @@
 use super::desire::Work;
-use crate::economy::{find_trade_place, ItemID, ItemRegistry, Market, Sold, Workers};
+use crate::economy::{
+    find_trade_place, Account, CompanyProfit, ItemID, ItemRegistry, Market, Sold, Workers,
+};
 use crate::engine_interaction::Selectable;
 use crate::map::{BuildingGen, BuildingID, Map};
 use crate::map_dynamic::BuildingInfos;
@@ -271,6 +273,8 @@ pub fn company_soul(goria: &mut Egregoria, company: GoodsCompany) -> Option<Soul
                 company,
                 Workers::default(),
                 Sold::default(),
+                Account::default(),
+                CompanyProfit::default(),
                 Transform::new(obb.center().z(height)),
                 Selectable::new(obb.axis()[0].mag() * 0.5),
             ),
@@ -280,6 +284,36 @@ pub fn company_soul(goria: &mut Egregoria, company: GoodsCompany) -> Option<Soul
     Some(soul)
 }
 
+/// Removes a bankrupt company: its workers lose their job and look for a new one,
+/// and its building is vacated so that a new company can move in
+pub fn close_company(goria: &mut Egregoria, soul: SoulID) {
+    let Some(building) = goria.comp::<GoodsCompany>(soul.0).map(|c| c.building) else {
+        return;
+    };
+    log::info!("company {:?} went bankrupt", soul);
+
+    let workers = goria
+        .comp::<Workers>(soul.0)
+        .map(|w| w.0.clone())
+        .unwrap_or_default();
+    let job_opening = goria.read::<ItemRegistry>().id("job-opening");
+    for worker in workers {
+        let _ = goria.world.remove_one::<Work>(worker.0);
+
+        let house = goria.read::<BuildingInfos>().building_owned_by(worker);
+        let map = goria.map();
+        let Some(house) = house.and_then(|h| map.buildings().get(h)) else {
+            continue;
+        };
+        let pos = house.door_pos.xy();
+        drop(map);
+        goria.write::<Market>().buy(worker, pos, job_opening, 1);
+    }
+
+    goria.write::<BuildingInfos>().remove_owner(building);
+    goria.read::<ParCommandBuffer>().kill(soul.0);
+}
+
 #[profiling::function]
 pub fn company_system(world: &mut World, res: &mut Resources) {
     let ra = res.get().unwrap();
@@ -314,7 +348,7 @@ pub fn company(
         return;
     });
 
-    if company.recipe.should_produce(soul, market) {
+    if company.max_workers > 0 && company.recipe.should_produce(soul, market) {
         company.progress += n_workers as f32
             / (company.recipe.complexity as f32 * company.max_workers as f32)
             * time.delta;
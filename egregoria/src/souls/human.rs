@@ -1,10 +1,13 @@
-use crate::economy::{Bought, ItemRegistry, Market};
+use crate::economy::{Account, Bought, ItemRegistry, Market, HOUSEHOLD_START_MONEY};
 use crate::map::BuildingID;
 use crate::map_dynamic::{BuildingInfos, Destination, Router};
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::desire::{Desire, DesireDefinition, DesireRegistry, Desires, Home, Work};
+use crate::souls::population::Household;
 use crate::transportation::{
     spawn_parked_vehicle, spawn_pedestrian, Location, VehicleID, VehicleKind,
 };
+use crate::utils::migration::add_missing;
+use crate::utils::rand_provider::RandProvider;
 use crate::utils::time::GameTime;
 use crate::{BuildingKind, Egregoria, FreightStation, Map, ParCommandBuffer, SoulID};
 use egui_inspect::Inspect;
@@ -85,7 +88,7 @@ enum NextDesire<'a> {
     None,
     Home(&'a mut Home),
     Work(&'a mut Work),
-    Food(&'a mut BuyFood),
+    Other(&'a mut Desire, &'a DesireDefinition),
 }
 
 #[profiling::function]
@@ -94,6 +97,8 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
     let rb = &*resources.get().unwrap();
     let rc = &*resources.get().unwrap();
     let rd = &*resources.get().unwrap();
+    let re = &*resources.get().unwrap();
+    let rf = &*resources.get().unwrap();
     world
         .query::<(
             &Transform,
@@ -101,15 +106,16 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
             &mut Router,
             &mut Bought,
             &mut HumanDecision,
-            Option<&mut BuyFood>,
             Option<&mut Home>,
             Option<&mut Work>,
+            Option<&Account>,
+            Option<&mut Desires>,
         )>()
         .iter_batched(32)
         .par_bridge()
         .for_each(|batch| {
-            batch.for_each(|(ent, (a, b, c, d, e, f, g, h))| {
-                update_decision(ra, rb, rc, rd, ent, a, b, c, d, e, f, g, h);
+            batch.for_each(|(ent, (a, b, c, d, e, f, g, h, i))| {
+                update_decision(ra, rb, rc, rd, re, rf, ent, a, b, c, d, e, f, g, h, i);
             })
         })
 }
@@ -120,15 +126,18 @@ pub fn update_decision(
     time: &GameTime,
     binfos: &BuildingInfos,
     map: &Map,
+    market: &Market,
+    registry: &DesireRegistry,
     me: Entity,
     trans: &Transform,
     loc: &Location,
     router: &mut Router,
     bought: &mut Bought,
     decision: &mut HumanDecision,
-    food: Option<&mut BuyFood>,
     home: Option<&mut Home>,
     work: Option<&mut Work>,
+    account: Option<&Account>,
+    desires: Option<&mut Desires>,
 ) {
     if decision.wait != 0 {
         decision.wait -= 1;
@@ -162,21 +171,27 @@ pub fn update_decision(
         }
     }
 
-    if let Some(food) = food {
-        let score = food.score(time, loc, bought);
-
-        #[allow(unused_assignments)]
-        if score > max_score {
-            max_score = score;
-            decision_id = NextDesire::Food(food);
+    if let Some(desires) = desires {
+        desires.sync(registry, time);
+        for (id, desire) in desires.0.iter_mut() {
+            let Some(def) = registry.definitions.get(*id) else {
+                continue;
+            };
+            let score = desire.score(def, time, loc, bought, account, market);
+
+            #[allow(unused_assignments)]
+            if score > max_score {
+                max_score = score;
+                decision_id = NextDesire::Other(desire, def);
+            }
         }
     }
 
     match decision_id {
         NextDesire::Home(home) => decision.kind = home.apply(),
         NextDesire::Work(work) => decision.kind = work.apply(loc, router),
-        NextDesire::Food(food) => {
-            decision.kind = food.apply(cbuf, binfos, map, time, soul, trans, loc, bought)
+        NextDesire::Other(desire, def) => {
+            decision.kind = desire.apply(def, cbuf, binfos, map, time, soul, trans, loc, bought)
         }
         NextDesire::None => {}
     }
@@ -196,13 +211,11 @@ pub fn spawn_human(goria: &mut Egregoria, house: BuildingID) -> Option<SoulID> {
     let registry = goria.read::<ItemRegistry>();
     m.buy(human, housepos.xy(), registry.id("job-opening"), 1);
     drop(m);
+    drop(registry);
 
     goria.write::<BuildingInfos>().set_owner(house, human);
 
-    let time = goria.read::<GameTime>().instant();
-
-    let food = BuyFood::new(time, &registry);
-    drop(registry);
+    let household = Household::immigrant(&mut goria.write::<RandProvider>());
 
     goria
         .world
@@ -211,12 +224,24 @@ pub fn spawn_human(goria: &mut Egregoria, house: BuildingID) -> Option<SoulID> {
             (
                 HumanDecision::default(),
                 Home::new(house),
-                food,
                 Bought::default(),
                 Router::new(car),
                 BasicWorker,
+                Account::new(HOUSEHOLD_START_MONEY),
+                Desires::default(),
+                household,
             ),
         )
         .unwrap();
     Some(human)
 }
+
+/// Humans from older saves start tracking their desires on their next decision
+pub(crate) fn migrate_household_desires(world: &mut World) {
+    add_missing::<HumanDecision, _>(world, Desires::default);
+}
+
+/// Humans from older saves live alone
+pub(crate) fn migrate_households(world: &mut World) {
+    add_missing::<HumanDecision, _>(world, || Household::new(&[30]));
+}
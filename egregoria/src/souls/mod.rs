@@ -3,6 +3,8 @@ use crate::map_dynamic::BuildingInfos;
 use crate::souls::fret_station::freight_station_soul;
 use crate::souls::goods_company::{company_soul, CompanyKind, GoodsCompany, GoodsCompanyRegistry};
 use crate::souls::human::spawn_human;
+use crate::souls::population::{empty_houses, immigrants, PopulationStats};
+use crate::souls::train_station::train_station_soul;
 use crate::transportation::{spawn_parked_vehicle, VehicleKind};
 use crate::Egregoria;
 use geom::Vec3;
@@ -14,6 +16,8 @@ pub mod desire;
 pub mod fret_station;
 pub mod goods_company;
 pub mod human;
+pub mod population;
+pub mod train_station;
 
 /// Adds souls to empty buildings
 #[profiling::function]
@@ -37,22 +41,35 @@ pub(crate) fn add_souls_to_empty_buildings(goria: &mut Egregoria) {
 
     let mut n_souls_added = 0;
 
-    for &(build_id, _) in empty_buildings
+    let n_empty = empty_buildings
         .get(&BuildingKind::House)
+        .map_or(0, Vec::len);
+    let n_immigrants = immigrants(goria, n_empty);
+    if n_immigrants > 0 {
+        // Households moving in pick the houses with the highest land value
+        for house in empty_houses(goria).into_iter().take(n_immigrants) {
+            if spawn_human(goria, house).is_some() {
+                goria.write::<PopulationStats>().today.immigrants += 1;
+                n_souls_added += 1;
+            }
+        }
+    }
+
+    for &(build_id, _) in empty_buildings
+        .get(&BuildingKind::RailFretStation)
         .unwrap_or(&vec![])
         .iter()
-        .take(50)
     {
-        spawn_human(goria, build_id);
+        freight_station_soul(goria, build_id);
         n_souls_added += 1;
     }
 
     for &(build_id, _) in empty_buildings
-        .get(&BuildingKind::RailFretStation)
+        .get(&BuildingKind::TrainStation)
         .unwrap_or(&vec![])
         .iter()
     {
-        freight_station_soul(goria, build_id);
+        train_station_soul(goria, build_id);
         n_souls_added += 1;
     }
 
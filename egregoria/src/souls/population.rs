@@ -0,0 +1,298 @@
+use crate::economy::{Account, ItemRegistry, Market, Money, Workers};
+use crate::map::{BuildingID, BuildingKind, LandValue};
+use crate::map_dynamic::{BuildingInfos, Router};
+use crate::souls::desire::Work;
+use crate::souls::goods_company::GoodsCompany;
+use crate::souls::human::spawn_human;
+use crate::transportation::Location;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::{GameTime, SECONDS_PER_DAY, SECONDS_PER_HOUR};
+use crate::{Egregoria, ParCommandBuffer, SoulID};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::ops::Range;
+
+// Humans age one year every in-game day, otherwise nobody would ever grow old.
+
+/// Age at which a child becomes an adult, and may leave to found its own household
+pub const ADULT_AGE: u32 = 18;
+pub const MAX_HOUSEHOLD_SIZE: usize = 5;
+/// Days in a row without a job after which a household leaves the city
+pub const UNEMPLOYED_DAYS_BEFORE_LEAVING: u32 = 3;
+
+/// Yearly chance for a household with adults in age of having children to get one
+const BIRTH_CHANCE: f32 = 0.1;
+const FERTILE_AGES: Range<u32> = 20..45;
+/// Households with less savings than this don't have children
+const BIRTH_MIN_MONEY: Money = Money::new_base(200);
+
+/// Households coming every hour on top of the open jobs not taken by the unemployed
+const BASE_IMMIGRATION: i32 = 2;
+const MAX_IMMIGRATION: i32 = 50;
+
+/// Yearly chance for a resident of this age to die
+fn death_chance(age: u32) -> f32 {
+    if age < 60 {
+        0.001
+    } else {
+        0.001 + (age - 60) as f32 * 0.01
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Resident {
+    pub age: u32,
+}
+
+/// The people living in a house, the soul carrying it commutes to work and runs the errands
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Household {
+    pub members: Vec<Resident>,
+    /// Days in a row spent without a job
+    pub unemployed_days: u32,
+}
+
+debug_inspect_impl!(Household);
+
+impl Household {
+    pub fn new(ages: &[u32]) -> Self {
+        Self {
+            members: ages.iter().map(|&age| Resident { age }).collect(),
+            unemployed_days: 0,
+        }
+    }
+
+    /// A household moving into the city: one or two adults with a few children
+    pub fn immigrant(rng: &mut RandProvider) -> Self {
+        let adult = 20 + (rng.next_f32() * 30.0) as u32;
+        let mut ages = vec![adult];
+        if rng.next_f32() < 0.6 {
+            ages.push(adult + (rng.next_f32() * 5.0) as u32);
+        }
+        let n_children = (rng.next_f32() * 3.0) as usize;
+        for _ in 0..n_children {
+            ages.push((rng.next_f32() * (adult - ADULT_AGE) as f32) as u32);
+        }
+        Self::new(&ages)
+    }
+
+    pub fn adults(&self) -> usize {
+        self.members.iter().filter(|m| m.age >= ADULT_AGE).count()
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct PopulationChange {
+    pub births: u32,
+    pub deaths: u32,
+    pub immigrants: u32,
+    pub emigrants: u32,
+}
+
+/// Households arriving and leaving the city, and residents born and dying
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PopulationStats {
+    pub today: PopulationChange,
+    pub yesterday: PopulationChange,
+}
+
+/// Jobs offered by the companies that nobody took yet, and households without a job
+pub(crate) fn job_balance(goria: &Egregoria) -> (i32, i32) {
+    let job_opening = goria.read::<ItemRegistry>().id("job-opening");
+    let market = goria.read::<Market>();
+    let open_jobs: i32 = goria
+        .world
+        .query::<&GoodsCompany>()
+        .iter()
+        .map(|(e, _)| market.capital(SoulID(e), job_opening).max(0))
+        .sum();
+    let unemployed = goria
+        .world
+        .query::<(&Household, Option<&Work>)>()
+        .iter()
+        .filter(|(_, (_, work))| work.is_none())
+        .count() as i32;
+    (open_jobs, unemployed)
+}
+
+/// How many households move into the empty houses this tick.
+/// A few come every hour, more when there are jobs nobody in the city is taking.
+pub(crate) fn immigrants(goria: &Egregoria, empty_houses: usize) -> usize {
+    if empty_houses == 0 || !goria.read::<GameTime>().tick(SECONDS_PER_HOUR as u32) {
+        return 0;
+    }
+
+    let (open_jobs, unemployed) = job_balance(goria);
+    (open_jobs - unemployed + BASE_IMMIGRATION).clamp(0, MAX_IMMIGRATION) as usize
+}
+
+/// Every day, residents age, are born and die.
+/// Grown up children found their own household if there is a free house, or leave the city.
+/// Households that are unemployed for too long or have no one left leave the city.
+#[profiling::function]
+pub fn population_system(goria: &mut Egregoria) {
+    if !goria.read::<GameTime>().tick(SECONDS_PER_DAY as u32) {
+        return;
+    }
+
+    let mut change = PopulationChange::default();
+    let mut founders = vec![];
+    let mut leaving = vec![];
+    {
+        let mut rng = goria.write::<RandProvider>();
+        for (e, (household, work, account)) in goria
+            .world
+            .query::<(&mut Household, Option<&Work>, Option<&Account>)>()
+            .iter()
+        {
+            let n_before = household.members.len();
+            household.members.retain_mut(|m| {
+                m.age += 1;
+                rng.next_f32() >= death_chance(m.age)
+            });
+            change.deaths += (n_before - household.members.len()) as u32;
+
+            let money = account.map_or(Money::ZERO, |a| a.money);
+            if household.members.len() < MAX_HOUSEHOLD_SIZE
+                && money >= BIRTH_MIN_MONEY
+                && household
+                    .members
+                    .iter()
+                    .any(|m| FERTILE_AGES.contains(&m.age))
+                && rng.next_f32() < BIRTH_CHANCE
+            {
+                household.members.push(Resident { age: 0 });
+                change.births += 1;
+            }
+
+            if household.adults() > 1 {
+                if let Some(i) = household.members.iter().position(|m| m.age == ADULT_AGE) {
+                    founders.push(household.members.swap_remove(i));
+                }
+            }
+
+            if work.is_some() {
+                household.unemployed_days = 0;
+            } else {
+                household.unemployed_days += 1;
+            }
+
+            if household.members.is_empty()
+                || household.unemployed_days >= UNEMPLOYED_DAYS_BEFORE_LEAVING
+            {
+                leaving.push(SoulID(e));
+            }
+        }
+    }
+
+    let mut houses = empty_houses(goria).into_iter();
+    for founder in founders {
+        let Some(house) = houses.next() else {
+            change.emigrants += 1;
+            continue;
+        };
+        let Some(soul) = spawn_human(goria, house) else {
+            continue;
+        };
+        let _ = goria.world.insert_one(
+            soul.0,
+            Household {
+                members: vec![founder],
+                unemployed_days: 0,
+            },
+        );
+    }
+
+    for soul in leaving {
+        let emigrating = goria
+            .comp::<Household>(soul.0)
+            .map_or(false, |h| !h.members.is_empty());
+        if remove_household(goria, soul) && emigrating {
+            change.emigrants += 1;
+        }
+    }
+
+    let mut stats = goria.write::<PopulationStats>();
+    stats.today.births += change.births;
+    stats.today.deaths += change.deaths;
+    stats.today.emigrants += change.emigrants;
+    stats.yesterday = std::mem::take(&mut stats.today);
+}
+
+/// Houses nobody lives in, the ones with the highest land value first
+pub(crate) fn empty_houses(goria: &Egregoria) -> Vec<BuildingID> {
+    let map = goria.map();
+    let binfos = goria.read::<BuildingInfos>();
+    let land_value = goria.read::<LandValue>();
+    let mut houses: Vec<_> = map
+        .buildings()
+        .iter()
+        .filter(|(_, b)| b.kind == BuildingKind::House)
+        .filter(|&(id, _)| binfos.get(id).map_or(false, |i| i.owner.is_none()))
+        .map(|(id, b)| (Reverse(OrderedFloat(land_value.building(b))), id))
+        .collect();
+    houses.sort_unstable();
+    houses.into_iter().map(|(_, id)| id).collect()
+}
+
+/// The household leaves the city: its job opens again and its house is vacated.
+/// Only happens when it is inside a building, returns false otherwise.
+pub fn remove_household(goria: &mut Egregoria, soul: SoulID) -> bool {
+    let inside = goria.comp::<Location>(soul.0).and_then(|l| match *l {
+        Location::Building(b) => Some(b),
+        _ => None,
+    });
+    let Some(inside) = inside else {
+        return false;
+    };
+
+    let workplace = goria.comp::<Work>(soul.0).map(|w| w.workplace());
+    if let Some(workplace) = workplace {
+        leave_job(goria, soul, workplace);
+    }
+
+    let car = goria.comp::<Router>(soul.0).and_then(|r| r.personal_car);
+
+    let mut binfos = goria.write::<BuildingInfos>();
+    binfos.get_out(inside, soul);
+    if let Some(house) = binfos.building_owned_by(soul) {
+        binfos.remove_owner(house);
+    }
+    drop(binfos);
+
+    let cbuf = goria.read::<ParCommandBuffer>();
+    if let Some(car) = car {
+        cbuf.kill(car.0);
+    }
+    cbuf.kill(soul.0);
+    true
+}
+
+/// The worker is removed from its employer and the job is put back on the market
+fn leave_job(goria: &mut Egregoria, worker: SoulID, workplace: BuildingID) {
+    let Some(company) = goria.read::<BuildingInfos>().owner(workplace) else {
+        return;
+    };
+    if let Some(mut workers) = goria.comp_mut::<Workers>(company.0) {
+        workers.0.retain(|&w| w != worker);
+    }
+    if let Some(mut c) = goria.comp_mut::<GoodsCompany>(company.0) {
+        if c.driver == Some(worker) {
+            c.driver = None;
+        }
+    }
+
+    let Some(pos) = goria
+        .map()
+        .buildings()
+        .get(workplace)
+        .map(|b| b.door_pos.xy())
+    else {
+        return;
+    };
+    let job_opening = goria.read::<ItemRegistry>().id("job-opening");
+    let mut market = goria.write::<Market>();
+    market.produce(company, job_opening, 1);
+    market.sell_all(company, pos, job_opening, 0);
+}
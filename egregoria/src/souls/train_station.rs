@@ -0,0 +1,63 @@
+use crate::map::{BuildingID, LaneKind, Map};
+use crate::map_dynamic::BuildingInfos;
+use crate::{Egregoria, ParCommandBuffer, Selectable, SoulID};
+use geom::{Transform, Vec3};
+use hecs::World;
+use resources::Resources;
+use serde::{Deserialize, Serialize};
+
+/// How far from the station center a rail can be to serve as a platform, in meters
+const PLATFORM_SEARCH_RADIUS: f32 = 150.0;
+
+/// A passenger train station
+/// The platform is the closest point on the rails next to the building where passenger trains stop.
+#[derive(Serialize, Deserialize, Inspect)]
+pub struct TrainStation {
+    pub building: BuildingID,
+    pub platform: Option<Vec3>,
+}
+
+pub fn train_station_soul(goria: &mut Egregoria, building: BuildingID) -> Option<SoulID> {
+    let map = goria.map();
+    let b = map.buildings.get(building)?;
+
+    let obb = b.obb;
+    let pos = obb.center().z(b.height);
+    let [w2, h2] = obb.axis().map(|x| x.mag2());
+    let platform = find_platform(&map, pos);
+
+    drop(map);
+
+    let soul = SoulID(goria.world.spawn((
+        TrainStation { building, platform },
+        Transform::new(pos),
+        Selectable {
+            radius: w2.max(h2).sqrt() * 0.5,
+        },
+    )));
+
+    goria.write::<BuildingInfos>().set_owner(building, soul);
+
+    Some(soul)
+}
+
+fn find_platform(map: &Map, pos: Vec3) -> Option<Vec3> {
+    let lane = map.nearest_lane(pos, LaneKind::Rail, Some(PLATFORM_SEARCH_RADIUS))?;
+    Some(map.lanes().get(lane)?.points.project(pos))
+}
+
+/// Removes the stations whose building was destroyed
+/// and keeps the platforms up to date as rails are built or removed
+pub fn train_station_system(world: &mut World, resources: &mut Resources) {
+    let cbuf = resources.get::<ParCommandBuffer>().unwrap();
+    let map = resources.get::<Map>().unwrap();
+
+    for (me, (trans, station)) in world.query::<(&Transform, &mut TrainStation)>().into_iter() {
+        if !map.buildings.contains_key(station.building) {
+            cbuf.kill(me);
+            continue;
+        }
+
+        station.platform = find_platform(&map, trans.position);
+    }
+}
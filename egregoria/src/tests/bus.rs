@@ -0,0 +1,52 @@
+use crate::engine_interaction::WorldCommand;
+use crate::tests::TestCtx;
+use crate::transportation::bus::{Bus, BusLineDescription, BusLineID, BusLines};
+use geom::vec3;
+
+fn add_line(test: &mut TestCtx) -> BusLineID {
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+    test.apply(&[WorldCommand::AddBusLine(BusLineDescription {
+        stops: vec![vec3(20.0, 0.0, 0.0), vec3(280.0, 0.0, 0.0)],
+        n_buses: 1,
+        interval: 30.0,
+    })]);
+    let lines = test.g.read::<BusLines>();
+    let (id, _) = lines.iter().next().unwrap();
+    id
+}
+
+fn n_buses(test: &TestCtx) -> usize {
+    test.g.world().query::<&Bus>().iter().count()
+}
+
+#[test]
+fn bus_serves_stops() {
+    let mut test = TestCtx::new();
+    let line = add_line(&mut test);
+
+    for _ in 0..5000 {
+        test.tick();
+        if test.g.read::<BusLines>().boarding(line, 1).is_some() {
+            assert_eq!(n_buses(&test), 1);
+            return;
+        }
+    }
+
+    panic!("bus did not reach the second stop after 5000 ticks");
+}
+
+#[test]
+fn removing_line_removes_buses() {
+    let mut test = TestCtx::new();
+    let line = add_line(&mut test);
+
+    test.tick();
+    test.tick();
+    assert_eq!(n_buses(&test), 1);
+
+    test.apply(&[WorldCommand::RemoveBusLine(line)]);
+    test.tick();
+    test.tick();
+    assert_eq!(n_buses(&test), 0);
+    assert!(test.g.read::<BusLines>().get(line).is_none());
+}
@@ -0,0 +1,144 @@
+use crate::economy::{Government, Money};
+use crate::engine_interaction::{CommandInverses, UndoID, WorldCommand};
+use crate::map::procgen::MAX_CITY_SIZE;
+use crate::map::TerraformKind;
+use crate::tests::TestCtx;
+use geom::{vec2, vec3, Vec2};
+
+fn generate(test: &mut TestCtx, pos: Vec2, seed: u64) {
+    test.apply(&[WorldCommand::MapGenerateCity {
+        pos,
+        size: 900.0,
+        seed,
+    }]);
+}
+
+fn last_undo(test: &TestCtx) -> UndoID {
+    test.g
+        .read::<CommandInverses>()
+        .0
+        .last()
+        .cloned()
+        .flatten()
+        .unwrap()
+}
+
+fn inter_positions(test: &TestCtx) -> Vec<(i32, i32)> {
+    let mut v: Vec<_> = test
+        .g
+        .map()
+        .intersections()
+        .values()
+        .map(|i| (i.pos.x as i32, i.pos.y as i32))
+        .collect();
+    v.sort_unstable();
+    v
+}
+
+#[test]
+fn city_has_arterials_streets_and_lots() {
+    let mut test = TestCtx::new();
+    test.flat_terrain();
+    test.apply(&[WorldCommand::Terraform {
+        kind: TerraformKind::Raise,
+        center: vec2(800.0, 512.0),
+        radius: 150.0,
+        amount: 40.0,
+    }]);
+    generate(&mut test, vec2(512.0, 512.0), 1);
+
+    let map = test.g.map();
+    map.check_invariants();
+    assert!(map.roads().len() > 10);
+    assert!(!map.lots().is_empty());
+
+    let widths = map.roads().values().map(|r| r.width);
+    let widest = widths.clone().fold(0.0, f32::max);
+    let narrowest = widths.fold(f32::MAX, f32::min);
+    assert!(widest > narrowest);
+
+    for road in map.roads().values() {
+        let dz = (road.points().first().z - road.points().last().z).abs();
+        assert!(dz <= road.length() * 0.12 + 0.01);
+    }
+}
+
+#[test]
+fn city_is_deterministic_from_the_seed() {
+    let mut a = TestCtx::new();
+    let mut b = TestCtx::new();
+    let mut c = TestCtx::new();
+    for test in [&a, &b, &c] {
+        test.flat_terrain();
+    }
+    generate(&mut a, vec2(512.0, 512.0), 7);
+    generate(&mut b, vec2(512.0, 512.0), 7);
+    generate(&mut c, vec2(512.0, 512.0), 8);
+
+    assert_eq!(inter_positions(&a), inter_positions(&b));
+    assert_ne!(inter_positions(&a), inter_positions(&c));
+}
+
+#[test]
+fn city_stays_out_of_the_water() {
+    let mut test = TestCtx::new();
+    test.flat_terrain();
+    test.g
+        .map_mut()
+        .terrain
+        .add_river(vec![vec2(500.0, 0.0), vec2(500.0, 1000.0)], 40.0);
+    generate(&mut test, vec2(300.0, 500.0), 3);
+
+    let map = test.g.map();
+    assert!(!map.roads().is_empty());
+    for road in map.roads().values() {
+        assert!(road
+            .points()
+            .equipoints_dir(5.0, false)
+            .all(|(p, _)| !map.terrain.is_underwater(p.xy())));
+    }
+}
+
+#[test]
+fn city_costs_money_and_can_be_undone() {
+    let mut test = TestCtx::new();
+    test.flat_terrain();
+    test.build_roads(&[vec3(100.0, 512.0, 0.0), vec3(900.0, 512.0, 0.0)]);
+    let before = inter_positions(&test);
+    let money = test.g.read::<Government>().money;
+
+    // The city starts by splitting the existing road
+    generate(&mut test, vec2(512.0, 500.0), 1);
+    assert!(test.g.map().roads().len() > 10);
+    assert!(test.g.read::<Government>().money < money);
+
+    test.apply(&[WorldCommand::Undo(last_undo(&test))]);
+    test.g.map().check_invariants();
+    assert_eq!(test.g.map().roads().len(), 1);
+    assert_eq!(inter_positions(&test), before);
+    assert_eq!(test.g.read::<Government>().money, money);
+}
+
+#[test]
+fn city_size_is_clamped() {
+    let mut test = TestCtx::new();
+    let cost = |test: &TestCtx, size| {
+        let command = WorldCommand::MapGenerateCity {
+            pos: vec2(512.0, 512.0),
+            size,
+            seed: 1,
+        };
+        Government::action_cost(&command, &test.g)
+    };
+    assert_eq!(cost(&test, 1e9), cost(&test, MAX_CITY_SIZE));
+    assert!(cost(&test, MAX_CITY_SIZE) > cost(&test, 900.0));
+    assert_eq!(cost(&test, f32::NAN), Money::ZERO);
+    assert_eq!(cost(&test, -100.0), Money::ZERO);
+
+    test.apply(&[WorldCommand::MapGenerateCity {
+        pos: vec2(512.0, 512.0),
+        size: f32::INFINITY,
+        seed: 1,
+    }]);
+    assert!(test.g.map().roads().is_empty());
+}
@@ -0,0 +1,57 @@
+use crate::economy::{Account, Bought, ItemRegistry, Market, Money};
+use crate::souls::desire::{DesireRegistry, DesireTarget, Desires};
+use crate::tests::TestCtx;
+use crate::transportation::Location;
+use crate::utils::time::{GameTime, SECONDS_PER_DAY};
+
+#[test]
+fn desires_are_loaded_and_tracked() {
+    let test = TestCtx::new();
+    let registry = test.g.read::<DesireRegistry>();
+    let leisure = registry.id("leisure").expect("leisure should be defined");
+
+    let mut desires = Desires::default();
+    desires.sync(&registry, &test.g.read::<GameTime>());
+    assert_eq!(desires.0.len(), registry.definitions.len());
+    assert!(desires.0.contains_key(&leisure));
+}
+
+#[test]
+fn humans_eat_bread() {
+    let test = TestCtx::new();
+    let registry = test.g.read::<DesireRegistry>();
+    let food = registry.id("food").expect("food should be defined");
+    let bread = test.g.read::<ItemRegistry>().id("bread");
+    assert!(matches!(
+        registry.definitions[food].target,
+        DesireTarget::Item(item) if item == bread
+    ));
+}
+
+#[test]
+fn poor_households_do_not_go_shopping() {
+    let test = TestCtx::new();
+    let registry = test.g.read::<DesireRegistry>();
+    let shopping = registry.id("shopping").expect("shopping should be defined");
+    let def = &registry.definitions[shopping];
+
+    let mut desires = Desires::default();
+    desires.sync(&registry, &GameTime::new(0.0, 0.0));
+    let desire = &desires.0[&shopping];
+
+    let time = GameTime::new(0.0, 30.0 * SECONDS_PER_DAY as f64);
+    let market = test.g.read::<Market>();
+    let bought = Bought::default();
+    let score = |money: Money| {
+        desire.score(
+            def,
+            &time,
+            &Location::Outside,
+            &bought,
+            Some(&Account::new(money)),
+            &market,
+        )
+    };
+    assert!(score(Money::new_base(10_000)) > 0.0);
+    assert_eq!(score(Money::ZERO), 0.0);
+}
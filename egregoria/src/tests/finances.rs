@@ -0,0 +1,182 @@
+use crate::economy::{
+    transfer, Account, Bought, CompanyProfit, Debt, Government, LedgerCategory, Market, Money,
+    TaxRates, BANKRUPTCY_DAYS, COMPANY_START_MONEY, GOVERNMENT_CREDIT_LIMIT, HOUSEHOLD_START_MONEY,
+};
+use crate::engine_interaction::{CommandInverses, WorldCommand};
+use crate::map::{LanePatternBuilder, MapProject};
+use crate::souls::desire::{DesireRegistry, Desires};
+use crate::tests::TestCtx;
+use crate::transportation::Location;
+use crate::utils::time::{GameTime, HOURS_PER_DAY, SECONDS_PER_DAY, SECONDS_PER_HOUR};
+use crate::SoulID;
+use geom::{vec2, vec3};
+
+/// Moves the clock right before the next in-game hour, so that the next tick collects
+fn skip_to_next_hour(test: &mut TestCtx) {
+    let hour = SECONDS_PER_HOUR as f64;
+    let t = test.g.read::<GameTime>().timestamp;
+    let next = ((t / hour).floor() + 1.0) * hour;
+    test.apply(&[WorldCommand::SetGameTime(GameTime::new(0.0, next - 0.01))]);
+}
+
+#[test]
+fn hourly_taxes_and_maintenance_are_recorded() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[
+        vec3(0.0, 0.0, 0.0),
+        vec3(100.0, 0.0, 0.0),
+        vec3(200.0, 50.0, 0.0),
+    ]);
+    test.build_house_near(vec2(50.0, 0.0));
+
+    let maintenance = Government::maintenance_cost(&test.g.map()) / HOURS_PER_DAY as i64;
+    assert!(maintenance > Money::ZERO);
+
+    skip_to_next_hour(&mut test);
+    let money = test.g.read::<Government>().money;
+    test.tick();
+
+    let gvt = test.g.read::<Government>();
+    let month = &gvt.ledger.current;
+    assert_eq!(
+        month.get(LedgerCategory::Maintenance),
+        Money::ZERO - maintenance
+    );
+    assert_eq!(
+        month.get(LedgerCategory::PropertyTax),
+        gvt.taxes.property / HOURS_PER_DAY as i64
+    );
+    assert_eq!(gvt.money, money + month.net());
+}
+
+#[test]
+fn tax_rates_can_be_undone() {
+    let mut test = TestCtx::new();
+    let old = test.g.read::<Government>().taxes;
+
+    test.apply(&[WorldCommand::SetTaxRates(TaxRates {
+        income: 0.2,
+        sales: 0.0,
+        property: Money::new_base(50),
+    })]);
+    assert_eq!(test.g.read::<Government>().taxes.income, 0.2);
+
+    let undo = test
+        .g
+        .read::<CommandInverses>()
+        .0
+        .last()
+        .cloned()
+        .flatten()
+        .expect("tax rates should be invertible");
+    test.apply(&[WorldCommand::Undo(undo)]);
+    assert_eq!(test.g.read::<Government>().taxes.income, old.income);
+}
+
+#[test]
+fn government_loans_are_capped_and_repaid() {
+    let mut test = TestCtx::new();
+    let money = test.g.read::<Government>().money;
+
+    test.apply(&[WorldCommand::GovernmentBorrow(GOVERNMENT_CREDIT_LIMIT * 2)]);
+    {
+        let gvt = test.g.read::<Government>();
+        assert_eq!(gvt.debt.total(), GOVERNMENT_CREDIT_LIMIT);
+        assert_eq!(gvt.money, money + GOVERNMENT_CREDIT_LIMIT);
+        assert!(gvt.debt.daily_interest() > Money::ZERO);
+    }
+
+    test.apply(&[WorldCommand::GovernmentRepay(GOVERNMENT_CREDIT_LIMIT)]);
+    let gvt = test.g.read::<Government>();
+    assert_eq!(gvt.debt.total(), Money::ZERO);
+    assert_eq!(gvt.money, money);
+}
+
+#[test]
+fn negative_repayments_do_not_borrow() {
+    let mut test = TestCtx::new();
+    test.apply(&[WorldCommand::GovernmentBorrow(Money::new_base(1000))]);
+    let money = test.g.read::<Government>().money;
+
+    test.apply(&[WorldCommand::GovernmentRepay(
+        Money::ZERO - GOVERNMENT_CREDIT_LIMIT,
+    )]);
+    let gvt = test.g.read::<Government>();
+    assert_eq!(gvt.debt.total(), Money::new_base(1000));
+    assert_eq!(gvt.money, money);
+
+    let mut debt = Debt::default();
+    debt.borrow(Money::new_base(100), 0.0);
+    assert_eq!(debt.repay(Money::new_base(-50)), Money::ZERO);
+    assert_eq!(debt.total(), Money::new_base(100));
+}
+
+#[test]
+fn bankrupt_government_cannot_build() {
+    let mut test = TestCtx::new();
+    test.g.write::<Government>().debt.insolvent_days = BANKRUPTCY_DAYS;
+    let n_roads = test.g.map().roads().len();
+
+    test.apply(&[WorldCommand::MapMakeConnection {
+        from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+        to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+        inter: None,
+        pat: LanePatternBuilder::new().build(),
+    }]);
+    assert_eq!(test.g.map().roads().len(), n_roads);
+}
+
+#[test]
+fn transfers_update_company_profit() {
+    let mut test = TestCtx::new();
+    let company = SoulID(
+        test.g
+            .world
+            .spawn((Account::default(), CompanyProfit::default())),
+    );
+    let human = SoulID(test.g.world.spawn(()));
+
+    assert!(transfer(&test.g.world, company, Money::new_base(100)));
+    assert!(transfer(&test.g.world, company, Money::new_base(-30)));
+    assert!(!transfer(&test.g.world, human, Money::new_base(10)));
+
+    assert_eq!(
+        test.g.comp::<Account>(company.0).unwrap().money,
+        COMPANY_START_MONEY + Money::new_base(70)
+    );
+    let mut profit = test.g.comp_mut::<CompanyProfit>(company.0).unwrap();
+    assert_eq!(profit.revenue, Money::new_base(100));
+    assert_eq!(profit.expenses, Money::new_base(30));
+    profit.end_day();
+    assert_eq!(profit.last_profit(), Money::new_base(70));
+    assert_eq!(profit.revenue, Money::ZERO);
+}
+
+#[test]
+fn poor_households_do_not_buy_food() {
+    let test = TestCtx::new();
+    let registry = test.g.read::<DesireRegistry>();
+    let food = registry.id("food").expect("food should be defined");
+    let def = &registry.definitions[food];
+
+    let mut desires = Desires::default();
+    desires.sync(&registry, &GameTime::new(0.0, 0.0));
+    let food = &desires.0[&food];
+
+    let time = GameTime::new(0.0, 2.0 * SECONDS_PER_DAY as f64);
+    let market = test.g.read::<Market>();
+    let bought = Bought::default();
+
+    let score = |account: &Account| {
+        food.score(
+            def,
+            &time,
+            &Location::Outside,
+            &bought,
+            Some(account),
+            &market,
+        )
+    };
+    assert!(score(&Account::new(HOUSEHOLD_START_MONEY)) > 0.0);
+    assert_eq!(score(&Account::new(Money::ZERO)), 0.0);
+}
@@ -0,0 +1,140 @@
+use crate::engine_interaction::WorldCommand;
+use crate::map::{BuildingID, BuildingKind, LandValue, LotKind, ProjectFilter, ProjectKind};
+use crate::map_dynamic::{
+    growth_system, upgrade_companies, ZoneDemand, MAX_UPGRADES, UPGRADE_LAND_VALUE,
+};
+use crate::souls::goods_company::{GoodsCompany, GoodsCompanyRegistry};
+use crate::tests::TestCtx;
+use crate::utils::time::{GameTime, SECONDS_PER_HOUR};
+use geom::{vec2, vec3, Vec2, OBB};
+
+/// Moves the clock right after the next in-game hour, so that hourly systems run
+fn start_next_hour(test: &mut TestCtx) {
+    let hour = SECONDS_PER_HOUR as f64;
+    let t = test.g.read::<GameTime>().timestamp;
+    let next = ((t / hour).floor() + 1.0) * hour;
+    test.apply(&[WorldCommand::SetGameTime(GameTime::new(1.0, next + 0.5))]);
+}
+
+fn n_houses(test: &TestCtx) -> usize {
+    test.g
+        .map()
+        .buildings()
+        .values()
+        .filter(|b| b.kind == BuildingKind::House)
+        .count()
+}
+
+#[test]
+fn residential_lots_grow_until_enough_houses_are_vacant() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(400.0, 0.0, 0.0)]);
+
+    let lots: Vec<_> = test.g.map().lots().keys().collect();
+    assert!(lots.len() > 6);
+    let (commercial, residential) = lots.split_first().unwrap();
+    test.apply(&[WorldCommand::MapSetLotKind {
+        lot: *commercial,
+        kind: LotKind::Commercial,
+    }]);
+    for &lot in residential {
+        test.apply(&[WorldCommand::MapSetLotKind {
+            lot,
+            kind: LotKind::Residential,
+        }]);
+    }
+
+    start_next_hour(&mut test);
+    growth_system(&mut test.g);
+
+    let demand = *test.g.read::<ZoneDemand>();
+    assert!(demand.residential > 0);
+    assert_eq!(demand.commercial, 0);
+    assert_eq!(n_houses(&test), demand.residential as usize);
+    // nobody lives in the city yet, no store is needed
+    assert_eq!(
+        test.g.map().lots().get(*commercial).map(|l| l.kind),
+        Some(LotKind::Commercial)
+    );
+
+    // the new houses are still empty, so there is no more demand
+    start_next_hour(&mut test);
+    growth_system(&mut test.g);
+    assert!(test.g.read::<ZoneDemand>().residential <= 0);
+    assert_eq!(n_houses(&test), demand.residential as usize);
+}
+
+fn build_bakery(test: &mut TestCtx, pos: Vec2) -> BuildingID {
+    let (kind, gen) = {
+        let registry = test.g.read::<GoodsCompanyRegistry>();
+        let bakery = registry
+            .descriptions
+            .values()
+            .find(|d| d.name == "Bakery")
+            .unwrap();
+        (BuildingKind::GoodsCompany(bakery.id), bakery.bgen)
+    };
+    test.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(pos, vec2(0.0, 1.0), 10.0, 10.0),
+        kind,
+        gen,
+        zone: None,
+    }]);
+    test.g
+        .map()
+        .spatial_map()
+        .query_around(pos, 1.0, ProjectFilter::BUILDING)
+        .find_map(|p| match p {
+            ProjectKind::Building(b) => Some(b),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn max_workers(test: &TestCtx, b: BuildingID) -> i32 {
+    test.g
+        .world
+        .query::<&GoodsCompany>()
+        .iter()
+        .find(|(_, c)| c.building == b)
+        .unwrap()
+        .1
+        .max_workers
+}
+
+#[test]
+fn companies_on_valuable_land_hire_more() {
+    let mut test = TestCtx::new();
+    let downtown: Vec<_> = (0..3)
+        .map(|i| build_bakery(&mut test, vec2(100.0 + i as f32 * 15.0, 100.0)))
+        .collect();
+    let suburb = build_bakery(&mut test, vec2(900.0, 900.0));
+    test.tick();
+
+    {
+        let map = test.g.map();
+        let land_value = test.g.read::<LandValue>();
+        let value = |b: BuildingID| land_value.building(&map.buildings()[b]);
+        assert!(value(downtown[1]) >= UPGRADE_LAND_VALUE);
+        assert!(value(suburb) < UPGRADE_LAND_VALUE);
+    }
+
+    let base = max_workers(&test, suburb);
+    let added = upgrade_companies(&mut test.g, base);
+    assert_eq!(added, base);
+    let upgraded = downtown
+        .iter()
+        .filter(|&&b| max_workers(&test, b) > base)
+        .count();
+    assert_eq!(upgraded, 1);
+    assert_eq!(max_workers(&test, suburb), base);
+
+    // companies stop growing at some point
+    for _ in 0..10 {
+        upgrade_companies(&mut test.g, 1000);
+    }
+    for b in downtown {
+        assert_eq!(max_workers(&test, b), base * (1 + MAX_UPGRADES));
+    }
+    assert_eq!(max_workers(&test, suburb), base);
+}
@@ -0,0 +1,92 @@
+use crate::engine_interaction::WorldCommand;
+use crate::map::{BuildingKind, LandValue, LandValueLayer, CELL_SIZE};
+use crate::souls::goods_company::GoodsCompanyRegistry;
+use crate::tests::TestCtx;
+use crate::transportation::bus::BusLines;
+use crate::EgregoriaOptions;
+use geom::{vec2, vec3, OBB};
+
+#[test]
+fn land_value_follows_the_buildings() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(100.0, 100.0, 0.0), vec3(400.0, 100.0, 0.0)]);
+    test.tick();
+
+    let p = vec2(250.0, 140.0);
+    let before = test.g.read::<LandValue>().get(p);
+
+    let (kind, gen) = {
+        let registry = test.g.read::<GoodsCompanyRegistry>();
+        let bakery = registry
+            .descriptions
+            .values()
+            .find(|d| d.name == "Bakery")
+            .unwrap();
+        (BuildingKind::GoodsCompany(bakery.id), bakery.bgen)
+    };
+    test.apply(&[WorldCommand::MapBuildSpecialBuilding {
+        pos: OBB::new(vec2(250.0, 120.0), vec2(0.0, 1.0), 10.0, 10.0),
+        kind,
+        gen,
+        zone: None,
+    }]);
+    test.tick();
+
+    {
+        let land_value = test.g.read::<LandValue>();
+        assert!(land_value.get(p) > before);
+        assert!(land_value.layer(p, LandValueLayer::Shops) > 0.0);
+        assert!(land_value.layer(p, LandValueLayer::Jobs) > 0.0);
+
+        // updating step by step gives the same field as building it at once
+        let mut fresh = LandValue::default();
+        fresh.update(
+            &test.g.map(),
+            &test.g.read::<GoodsCompanyRegistry>(),
+            &test.g.read::<BusLines>(),
+        );
+        assert_eq!(fresh.get(p), land_value.get(p));
+    }
+
+    let store = test
+        .g
+        .map()
+        .buildings()
+        .values()
+        .find(|b| b.kind == kind)
+        .unwrap()
+        .id;
+    test.apply(&[WorldCommand::MapRemoveBuilding(store)]);
+    test.tick();
+    assert_eq!(test.g.read::<LandValue>().get(p), before);
+}
+
+#[test]
+fn land_value_is_rebuilt_for_a_new_terrain() {
+    let mut test = TestCtx::new();
+    test.tick();
+
+    let opts = EgregoriaOptions {
+        seed: 1234,
+        ..test.g.read::<EgregoriaOptions>().clone()
+    };
+    test.apply(&[WorldCommand::Init(Box::new(opts))]);
+    test.tick();
+
+    let land_value = test.g.read::<LandValue>();
+    let mut fresh = LandValue::default();
+    fresh.update(
+        &test.g.map(),
+        &test.g.read::<GoodsCompanyRegistry>(),
+        &test.g.read::<BusLines>(),
+    );
+    for y in 0..32 {
+        for x in 0..32 {
+            let p = vec2(x as f32 + 0.5, y as f32 + 0.5) * CELL_SIZE;
+            assert_eq!(
+                fresh.layer(p, LandValueLayer::Nature),
+                land_value.layer(p, LandValueLayer::Nature)
+            );
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::economy::{Government, Money};
+use crate::tests::TestCtx;
+use crate::transportation::bus::{
+    migrate_bus_lines_stops, BusLineDescription, BusLineID, BusLines,
+};
+use crate::transportation::VehicleID;
+use crate::utils::migration::Migration;
+use crate::{Egregoria, EgregoriaDeser, EgregoriaSer, SerWorld, VERSION};
+use common::saveload::{Bincode, Encoder};
+use geom::vec3;
+use serde::Serialize;
+use slotmap::SlotMap;
+
+fn encode_with_version(g: &Egregoria, version: &str) -> Vec<u8> {
+    let mut res = g.serialized_resources();
+    res.remove("world");
+    Bincode::encode(&EgregoriaSer {
+        world: SerWorld(&g.world),
+        version: version.to_string(),
+        res: res.into_iter().collect(),
+    })
+    .unwrap()
+}
+
+#[test]
+fn unknown_version_is_rejected() {
+    let test = TestCtx::new();
+    let data = encode_with_version(&test.g, "test-unknown");
+    assert!(Bincode::decode::<Egregoria>(&data).is_err());
+}
+
+#[test]
+fn undecodable_resource_is_rejected() {
+    let test = TestCtx::new();
+    let mut res = test.g.serialized_resources();
+    res.remove("world");
+    res.insert("government".to_string(), vec![1, 2, 3]);
+    let data = Bincode::encode(&EgregoriaSer {
+        world: SerWorld(&test.g.world),
+        version: VERSION.to_string(),
+        res: res.into_iter().collect(),
+    })
+    .unwrap();
+
+    let deser: EgregoriaDeser = Bincode::decode(&data).unwrap();
+    assert!(Egregoria::from_deser(deser, &[]).is_err());
+}
+
+#[test]
+fn migrations_are_chained() {
+    let test = TestCtx::new();
+
+    let migrations = vec![
+        Migration::new("test-0.1", "test-0.2").resource("government", |data| {
+            let mut gov: Government = Bincode::decode(&data).ok()?;
+            gov.money = Money::new_base(42);
+            Bincode::encode(&gov).ok()
+        }),
+        Migration::new("test-0.2", VERSION).resource("government", |data| {
+            let mut gov: Government = Bincode::decode(&data).ok()?;
+            gov.money += Money::new_base(1);
+            Bincode::encode(&gov).ok()
+        }),
+    ];
+
+    let data = encode_with_version(&test.g, "test-0.1");
+    let deser: EgregoriaDeser = Bincode::decode(&data).unwrap();
+    let migrated = Egregoria::from_deser(deser, &migrations).unwrap();
+
+    assert_eq!(migrated.read::<Government>().money, Money::new_base(43));
+    assert_eq!(migrated.get_tick(), test.g.get_tick());
+}
+
+#[test]
+fn bus_lines_get_their_stops() {
+    #[derive(Serialize)]
+    struct OldBusLine {
+        desc: BusLineDescription,
+        buses: Vec<VehicleID>,
+        boarding: Vec<Option<VehicleID>>,
+        last_departure: f64,
+    }
+
+    let mut old: SlotMap<BusLineID, OldBusLine> = SlotMap::with_key();
+    let removed = old.insert(OldBusLine {
+        desc: BusLineDescription {
+            stops: vec![],
+            n_buses: 0,
+            interval: 0.0,
+        },
+        buses: vec![],
+        boarding: vec![],
+        last_departure: 0.0,
+    });
+    old.remove(removed);
+    let id = old.insert(OldBusLine {
+        desc: BusLineDescription {
+            stops: vec![vec3(1.0, 2.0, 0.0), vec3(3.0, 4.0, 0.0)],
+            n_buses: 2,
+            interval: 30.0,
+        },
+        buses: vec![],
+        boarding: vec![None, None],
+        last_departure: 12.0,
+    });
+
+    let data = migrate_bus_lines_stops(Bincode::encode(&old).unwrap()).unwrap();
+    let lines: BusLines = Bincode::decode(&data).unwrap();
+
+    assert_eq!(lines.iter().count(), 1);
+    let line = lines.get(id).unwrap();
+    assert_eq!(line.desc.n_buses, 2);
+    assert_eq!(line.stops.len(), 2);
+    assert_eq!(line.boarding.len(), 2);
+    assert_eq!(line.last_departure, 12.0);
+}
@@ -2,14 +2,30 @@
 #![cfg(test)]
 
 use crate::engine_interaction::{WorldCommand, WorldCommands};
-use crate::map::{BuildingID, LanePatternBuilder, ProjectFilter};
+use crate::map::{BuildingID, HeightEdit, LanePatternBuilder, ProjectFilter, CHUNK_RESOLUTION};
 use crate::map_dynamic::BuildingInfos;
 use crate::utils::scheduler::SeqSchedule;
 use crate::{Egregoria, EgregoriaOptions};
 use common::logger::MyLog;
 use geom::{Vec2, Vec3};
 
+mod bus;
+mod city;
+mod desires;
+mod finances;
+mod growth;
+mod land_value;
+mod migration;
+mod population;
+mod replay;
+mod rerouting;
+mod routing_service;
+mod terraform;
+mod terrain;
+mod train;
+mod undo;
 mod vehicles;
+mod water;
 
 pub(crate) struct TestCtx {
     pub g: Egregoria,
@@ -24,6 +40,7 @@ impl TestCtx {
         let g = Egregoria::new_with_options(EgregoriaOptions {
             terrain_size: 1,
             save_replay: false,
+            dynamic_rerouting: false,
             ..Default::default()
         });
         let sched = Egregoria::schedule();
@@ -40,6 +57,22 @@ impl TestCtx {
         }
     }
 
+    /// Levels the whole first chunk at 0 so that the roads built on it are on the ground
+    pub(crate) fn flat_terrain(&self) {
+        let mut edits = vec![];
+        for y in 0..CHUNK_RESOLUTION as u16 {
+            for x in 0..CHUNK_RESOLUTION as u16 {
+                edits.push(HeightEdit {
+                    chunk: (0, 0),
+                    x,
+                    y,
+                    height: 0.0,
+                });
+            }
+        }
+        self.g.map_mut().terraform(&edits);
+    }
+
     pub(crate) fn build_house_near(&self, p: Vec2) -> BuildingID {
         let lot = self
             .g
@@ -0,0 +1,41 @@
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::human::spawn_human;
+use crate::souls::population::{remove_household, Household, ADULT_AGE, MAX_HOUSEHOLD_SIZE};
+use crate::tests::TestCtx;
+use crate::utils::rand_provider::RandProvider;
+use crate::ParCommandBuffer;
+use geom::{vec2, vec3};
+
+#[test]
+fn immigrant_households_have_an_adult() {
+    let mut rng = RandProvider::new(42);
+    for _ in 0..100 {
+        let household = Household::immigrant(&mut rng);
+        assert!(household.adults() >= 1);
+        assert!(household.members.len() <= MAX_HOUSEHOLD_SIZE);
+        let oldest = household.members.iter().map(|m| m.age).max().unwrap();
+        assert!(household
+            .members
+            .iter()
+            .all(|m| m.age >= ADULT_AGE || m.age + ADULT_AGE <= oldest));
+    }
+}
+
+#[test]
+fn leaving_households_vacate_their_house() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+    let house = test.build_house_near(vec2(50.0, 0.0));
+
+    let human = spawn_human(&mut test.g, house).unwrap();
+    assert!(test.g.comp::<Household>(human.0).is_some());
+    assert_eq!(test.g.read::<BuildingInfos>().owner(house), Some(human));
+
+    assert!(remove_household(&mut test.g, human));
+    ParCommandBuffer::apply(&mut test.g);
+
+    assert!(!test.g.world().contains(human.0));
+    let binfos = test.g.read::<BuildingInfos>();
+    assert_eq!(binfos.owner(house), None);
+    assert!(binfos.get(house).unwrap().inside.is_empty());
+}
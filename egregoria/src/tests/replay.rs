@@ -0,0 +1,150 @@
+use crate::engine_interaction::WorldCommand;
+use crate::map::{BuildingKind, LandValue, LanePatternBuilder, LotKind, MapProject};
+use crate::map_dynamic::ZoneDemand;
+use crate::souls::human::HumanDecision;
+use crate::tests::TestCtx;
+use crate::utils::replay_player::ReplayPlayer;
+use crate::utils::time::{GameTime, Tick, SECONDS_PER_HOUR};
+use crate::{Egregoria, EgregoriaOptions, Replay};
+use geom::vec3;
+
+fn make_replay() -> Replay {
+    let mut g = Egregoria::new_with_options(EgregoriaOptions {
+        terrain_size: 1,
+        save_replay: true,
+        dynamic_rerouting: false,
+        ..Default::default()
+    });
+    let mut sched = Egregoria::schedule();
+
+    for i in 0..40 {
+        let commands = if i == 10 {
+            vec![WorldCommand::MapMakeConnection {
+                from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+                to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+                inter: None,
+                pat: LanePatternBuilder::new().build(),
+            }]
+        } else {
+            vec![]
+        };
+        g.tick(&mut sched, &commands);
+    }
+
+    let replay = g.read::<Replay>().clone();
+    drop(g);
+    replay
+}
+
+#[test]
+fn replay_seek_is_deterministic() {
+    let _ = TestCtx::new();
+    let replay = make_replay();
+
+    let mut player = ReplayPlayer::new(replay, 10);
+    player.seek(Tick(35));
+    assert_eq!(player.tick(), Tick(35));
+    let hashes = player.goria().hashes();
+    let n_roads = player.goria().map().roads().len();
+    assert!(player.n_checkpoints() >= 3);
+
+    player.step(-30);
+    assert_eq!(player.tick(), Tick(5));
+    assert_eq!(player.goria().map().roads().len() + 1, n_roads);
+
+    player.seek(Tick(35));
+    assert_eq!(player.goria().hashes(), hashes);
+    assert!(player.is_over());
+}
+
+/// A replay in which houses grow on zoned lots and households move in
+fn make_growing_replay() -> Replay {
+    let mut g = Egregoria::new_with_options(EgregoriaOptions {
+        terrain_size: 1,
+        save_replay: true,
+        dynamic_rerouting: false,
+        ..Default::default()
+    });
+    let mut sched = Egregoria::schedule();
+
+    let next_hour = |g: &Egregoria| {
+        let hour = SECONDS_PER_HOUR as f64;
+        let t = g.read::<GameTime>().timestamp;
+        WorldCommand::SetGameTime(GameTime::new(1.0, ((t / hour).floor() + 1.0) * hour + 0.5))
+    };
+
+    for i in 0..300 {
+        let commands = match i {
+            1 => vec![WorldCommand::MapMakeConnection {
+                from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+                to: MapProject::ground(vec3(400.0, 0.0, 0.0)),
+                inter: None,
+                pat: LanePatternBuilder::new().build(),
+            }],
+            2 => g
+                .map()
+                .lots()
+                .keys()
+                .map(|lot| WorldCommand::MapSetLotKind {
+                    lot,
+                    kind: LotKind::Residential,
+                })
+                .collect(),
+            // houses are built on the first hour, households move in on the next one
+            3 | 20 => vec![next_hour(&g)],
+            _ => vec![],
+        };
+        g.tick(&mut sched, &commands);
+    }
+
+    let replay = g.read::<Replay>().clone();
+    drop(g);
+    replay
+}
+
+#[test]
+fn replay_seek_across_checkpoints_with_growth() {
+    let _ = TestCtx::new();
+    let replay = make_growing_replay();
+
+    let mut player = ReplayPlayer::new(replay, 50);
+    player.seek(Tick(290));
+
+    let goria = player.goria();
+    let houses: Vec<_> = goria
+        .map()
+        .buildings()
+        .values()
+        .filter(|b| b.kind == BuildingKind::House)
+        .map(|b| b.obb.center())
+        .collect();
+    assert!(!houses.is_empty());
+    assert!(goria.world().query::<&HumanDecision>().iter().count() > 0);
+
+    let hashes = goria.hashes();
+    let demand = *goria.read::<ZoneDemand>();
+    let values: Vec<f32> = houses
+        .iter()
+        .map(|&p| goria.read::<LandValue>().get(p))
+        .collect();
+
+    assert!(player.n_checkpoints() >= 5);
+
+    // restores the checkpoint of tick 100, then simulates across the next checkpoints
+    // one tick at a time instead of jumping to the closest one
+    player.step(-170);
+    assert_eq!(player.tick(), Tick(120));
+    while player.tick() < Tick(290) {
+        player.step(1);
+    }
+
+    let goria = player.goria();
+    assert_eq!(goria.hashes(), hashes);
+    let restored = *goria.read::<ZoneDemand>();
+    assert_eq!(restored.residential, demand.residential);
+    assert_eq!(restored.commercial, demand.commercial);
+    assert_eq!(restored.industrial, demand.industrial);
+    for (&p, &v) in houses.iter().zip(&values) {
+        assert_eq!(goria.read::<LandValue>().get(p), v);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::map::{LaneID, LaneKind, PathKind, TraverseKind};
+use crate::map_dynamic::{Itinerary, ItineraryKind};
+use crate::tests::TestCtx;
+use geom::{vec3, Vec3};
+
+const START: Vec3 = vec3(-50.0, 0.0, 0.0);
+const END: Vec3 = vec3(250.0, 0.0, 0.0);
+
+/// A direct road from (0, 0) to (200, 0) and a detour three times longer
+fn build(test: &TestCtx) {
+    test.build_roads(&[
+        vec3(-100.0, 0.0, 0.0),
+        vec3(0.0, 0.0, 0.0),
+        vec3(200.0, 0.0, 0.0),
+        vec3(300.0, 0.0, 0.0),
+    ]);
+    test.build_roads(&[
+        vec3(0.0, 0.0, 0.0),
+        vec3(0.0, 150.0, 0.0),
+        vec3(200.0, 150.0, 0.0),
+        vec3(200.0, 0.0, 0.0),
+    ]);
+}
+
+fn direct_lanes(test: &TestCtx) -> Vec<LaneID> {
+    let mid = vec3(100.0, 0.0, 0.0);
+    test.g
+        .map()
+        .lanes()
+        .values()
+        .filter(|l| l.kind == LaneKind::Driving && l.points.project(mid).is_close(mid, 10.0))
+        .map(|l| l.id)
+        .collect()
+}
+
+fn jam(test: &TestCtx, lanes: &[LaneID]) {
+    let mut map = test.g.map_mut();
+    let map = &mut *map;
+    for &id in lanes {
+        for _ in 0..50 {
+            map.travel_times
+                .observe_lane(map.lanes.get(id).unwrap(), 1000.0);
+        }
+    }
+    map.travel_times.publish();
+}
+
+fn uses_any(itin: &Itinerary, lanes: &[LaneID]) -> bool {
+    let ItineraryKind::Route(r, _) = itin.kind() else {
+        panic!("not a route");
+    };
+    r.reversed_route
+        .iter()
+        .chain(std::iter::once(&r.cur))
+        .any(|t| matches!(t.kind, TraverseKind::Lane(id) if lanes.contains(&id)))
+}
+
+#[test]
+fn route_avoids_slow_lanes() {
+    let test = TestCtx::new();
+    build(&test);
+    let direct = direct_lanes(&test);
+    assert!(!direct.is_empty());
+
+    let route = || Itinerary::route(START, END, &test.g.map(), PathKind::Vehicle).unwrap();
+    assert!(uses_any(&route(), &direct));
+
+    jam(&test, &direct);
+    // Disabled, only the geometry matters
+    assert!(uses_any(&route(), &direct));
+
+    test.g.map_mut().travel_times.enabled = true;
+    assert!(!uses_any(&route(), &direct));
+}
+
+#[test]
+fn slow_route_is_replaced() {
+    let test = TestCtx::new();
+    build(&test);
+    let direct = direct_lanes(&test);
+    test.g.map_mut().travel_times.enabled = true;
+
+    let mut itin = Itinerary::route(START, END, &test.g.map(), PathKind::Vehicle).unwrap();
+    assert!(uses_any(&itin, &direct));
+    assert!(!itin.reroute_if_faster(&test.g.map()));
+
+    jam(&test, &direct);
+    assert!(itin.reroute_if_faster(&test.g.map()));
+    assert!(!uses_any(&itin, &direct));
+}
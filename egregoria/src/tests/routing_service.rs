@@ -0,0 +1,50 @@
+use crate::map::PathKind;
+use crate::map_dynamic::{Itinerary, ItineraryKind, RoutingService};
+use crate::tests::TestCtx;
+use geom::{vec3, Transform};
+use hecs::Entity;
+
+fn spawn_walker(test: &mut TestCtx) -> Entity {
+    test.g.world.spawn((
+        Transform::new(vec3(10.0, 0.0, 0.0)),
+        Itinerary::wait_for_reroute(PathKind::Pedestrian, vec3(200.0, 190.0, 0.0)),
+    ))
+}
+
+fn has_route(test: &TestCtx, e: Entity) -> bool {
+    matches!(
+        test.g.comp::<Itinerary>(e).unwrap().kind(),
+        ItineraryKind::Route(..)
+    )
+}
+
+#[test]
+fn identical_requests_are_computed_once() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[
+        vec3(0.0, 0.0, 0.0),
+        vec3(200.0, 0.0, 0.0),
+        vec3(200.0, 200.0, 0.0),
+    ]);
+
+    let a = spawn_walker(&mut test);
+    let b = spawn_walker(&mut test);
+    test.tick();
+
+    assert!(has_route(&test, a));
+    assert!(has_route(&test, b));
+    {
+        let service = test.g.read::<RoutingService>();
+        assert_eq!(service.computed, 1);
+        assert_eq!(service.shared, 1);
+    }
+
+    // The map didn't change, the path comes from the cache
+    let c = spawn_walker(&mut test);
+    test.tick();
+
+    assert!(has_route(&test, c));
+    let service = test.g.read::<RoutingService>();
+    assert_eq!(service.computed, 1);
+    assert_eq!(service.shared, 2);
+}
@@ -0,0 +1,209 @@
+use crate::economy::{Government, Money};
+use crate::engine_interaction::{CommandInverses, UndoID, WorldCommand};
+use crate::map::{IntersectionID, TerraformKind, Tree, MAX_TERRAFORM_AMOUNT};
+use crate::tests::TestCtx;
+use geom::{vec2, vec3, Vec2};
+
+fn inter_at(test: &TestCtx, p: Vec2) -> IntersectionID {
+    test.g
+        .map()
+        .intersections()
+        .values()
+        .find(|i| i.pos.xy().distance(p) < 1.0)
+        .unwrap()
+        .id
+}
+
+fn inter_z(test: &TestCtx, id: IntersectionID) -> f32 {
+    test.g.map().intersections().get(id).unwrap().pos.z
+}
+
+fn ground(test: &TestCtx, p: Vec2) -> f32 {
+    test.g.map().terrain.height(p).unwrap()
+}
+
+fn plant(test: &TestCtx, pos: Vec2) {
+    test.g.map_mut().terrain.add_trees(&[Tree {
+        pos,
+        size: 1.0,
+        col: 0.5,
+        dir: Vec2::X,
+    }]);
+}
+
+#[test]
+fn terraform_moves_roads_and_can_be_undone() {
+    let mut test = TestCtx::new();
+    test.flat_terrain();
+    test.build_roads(&[vec3(300.0, 500.0, 0.0), vec3(700.0, 500.0, 0.0)]);
+
+    let raised = inter_at(&test, vec2(300.0, 500.0));
+    let other = inter_at(&test, vec2(700.0, 500.0));
+    let money = test.g.read::<Government>().money;
+
+    let command = WorldCommand::Terraform {
+        kind: TerraformKind::Raise,
+        center: vec2(300.0, 500.0),
+        radius: 100.0,
+        amount: 10.0,
+    };
+    let cost = Government::action_cost(&command, &test.g);
+    assert!(cost > Money::ZERO);
+    test.apply(&[command]);
+
+    assert_eq!(test.g.read::<Government>().money, money - cost);
+    assert!(ground(&test, vec2(300.0, 500.0)) > 5.0);
+    assert_eq!(inter_z(&test, raised), ground(&test, vec2(300.0, 500.0)));
+    assert_eq!(inter_z(&test, other), 0.0);
+    {
+        let map = test.g.map();
+        let road = map.roads().values().next().unwrap();
+        assert_eq!(road.points().first().z, inter_z(&test, road.src));
+    }
+
+    let undo = test
+        .g
+        .read::<CommandInverses>()
+        .0
+        .last()
+        .cloned()
+        .flatten()
+        .unwrap();
+    test.apply(&[WorldCommand::Undo(undo)]);
+
+    assert_eq!(test.g.read::<Government>().money, money);
+    assert_eq!(ground(&test, vec2(300.0, 500.0)), 0.0);
+    assert_eq!(inter_z(&test, raised), 0.0);
+}
+
+fn last_undo(test: &TestCtx) -> UndoID {
+    test.g
+        .read::<CommandInverses>()
+        .0
+        .last()
+        .cloned()
+        .flatten()
+        .unwrap()
+}
+
+fn n_trees(test: &TestCtx) -> usize {
+    test.g
+        .map()
+        .terrain
+        .chunks
+        .values()
+        .map(|c| c.trees.len())
+        .sum()
+}
+
+#[test]
+fn undo_brings_back_the_trees_put_under_water() {
+    let mut test = TestCtx::new();
+    test.flat_terrain();
+    let p = vec2(200.0, 200.0);
+    plant(&test, p);
+    let trees = n_trees(&test);
+
+    test.apply(&[WorldCommand::Terraform {
+        kind: TerraformKind::Lower,
+        center: p,
+        radius: 100.0,
+        amount: 20.0,
+    }]);
+    assert!(ground(&test, p) < test.g.map().terrain.water.sea_level);
+    assert_eq!(n_trees(&test), trees - 1);
+
+    test.apply(&[WorldCommand::Undo(last_undo(&test))]);
+    assert_eq!(ground(&test, p), 0.0);
+    assert_eq!(n_trees(&test), trees);
+}
+
+#[test]
+fn trees_above_the_sea_are_kept() {
+    let mut test = TestCtx::new();
+    test.flat_terrain();
+    let p = vec2(200.0, 200.0);
+    plant(&test, p);
+    let trees = n_trees(&test);
+
+    test.apply(&[WorldCommand::Terraform {
+        kind: TerraformKind::Lower,
+        center: p,
+        radius: 100.0,
+        amount: 5.0,
+    }]);
+    assert!(ground(&test, p) < 0.0);
+    assert!(ground(&test, p) > test.g.map().terrain.water.sea_level);
+    assert_eq!(n_trees(&test), trees);
+}
+
+#[test]
+fn buildings_follow_the_ground() {
+    let mut test = TestCtx::new();
+    test.flat_terrain();
+    test.build_roads(&[vec3(300.0, 500.0, 0.0), vec3(700.0, 500.0, 0.0)]);
+    let house = test.build_house_near(vec2(500.0, 520.0));
+    let building = |test: &TestCtx| {
+        let map = test.g.map();
+        let b = map.buildings().get(house).unwrap();
+        (b.obb.center(), b.height, b.door_pos.z)
+    };
+    let (center, height, door) = building(&test);
+
+    test.apply(&[WorldCommand::Terraform {
+        kind: TerraformKind::Raise,
+        center,
+        radius: 100.0,
+        amount: 10.0,
+    }]);
+    let (_, raised, raised_door) = building(&test);
+    assert!(raised > height);
+    assert_eq!(raised, ground(&test, center));
+    assert!((raised_door - door - (raised - height)).abs() < 0.01);
+
+    test.apply(&[WorldCommand::Undo(last_undo(&test))]);
+    assert_eq!(building(&test).1, height);
+}
+
+#[test]
+fn terraform_brush_is_clamped() {
+    let test = TestCtx::new();
+    let map = test.g.map();
+    let p = vec2(512.0, 512.0);
+    let before = map.terrain.height(p).unwrap();
+
+    let edits = map.terrain.terraform(TerraformKind::Raise, p, 1e9, 1e9);
+    let center = edits.iter().map(|e| e.height - before).fold(0.0, f32::max);
+    assert!(center <= MAX_TERRAFORM_AMOUNT + 0.01);
+    assert!(map
+        .terrain
+        .terraform(TerraformKind::Raise, p, f32::NAN, 10.0)
+        .is_empty());
+    assert!(map
+        .terrain
+        .terraform(TerraformKind::Raise, p, 100.0, -10.0)
+        .is_empty());
+}
+
+#[test]
+fn flatten_and_smooth_move_towards_the_target() {
+    let mut test = TestCtx::new();
+    let p = vec2(512.0, 512.0);
+
+    let before = ground(&test, p);
+    test.apply(&[WorldCommand::Terraform {
+        kind: TerraformKind::Flatten {
+            height: before + 20.0,
+        },
+        center: p,
+        radius: 200.0,
+        amount: 5.0,
+    }]);
+    let flattened = ground(&test, p);
+    assert!(flattened > before && flattened <= before + 5.0 + 0.01);
+
+    let map = test.g.map();
+    let edits = map.terrain.terraform(TerraformKind::Smooth, p, 200.0, 0.5);
+    assert!(!edits.is_empty());
+    assert!(map.terrain.edits_volume(&edits) > 0.0);
+}
@@ -0,0 +1,83 @@
+use crate::map::{Terrain, TerrainOptions};
+use crate::{Egregoria, EgregoriaOptions};
+use geom::Vec2;
+
+fn heights(terrain: &Terrain) -> Vec<f32> {
+    terrain
+        .chunks
+        .values()
+        .flat_map(|c| c.heights.iter().flatten().copied())
+        .collect()
+}
+
+fn trees(terrain: &Terrain) -> Vec<Vec2> {
+    terrain
+        .chunks
+        .values()
+        .flat_map(|c| c.trees.iter().map(|t| t.pos))
+        .collect()
+}
+
+#[test]
+fn terrain_generation_is_seeded() {
+    let options = TerrainOptions::default();
+    let a = Terrain::new(2, 2, 1, &options);
+    let b = Terrain::new(2, 2, 1, &options);
+    let c = Terrain::new(2, 2, 2, &options);
+
+    assert_eq!(heights(&a), heights(&b));
+    assert_eq!(trees(&a), trees(&b));
+    assert_ne!(trees(&a), trees(&c));
+
+    let flat = Terrain::new(
+        2,
+        2,
+        1,
+        &TerrainOptions {
+            amplitude: 0.0,
+            tree_density: 0.0,
+            sea_level: -30.0,
+            ..options
+        },
+    );
+    assert!(heights(&flat).iter().all(|&h| h == 0.0));
+    assert!(trees(&flat).is_empty());
+    assert_eq!(flat.water.sea_level, -30.0);
+}
+
+#[test]
+fn rivers_flow_to_the_sea() {
+    // A strip of land going down to the sea, big enough for one river
+    let terrain = Terrain::new(5, 50, 1, &TerrainOptions::default());
+    let sea_level = terrain.water.sea_level;
+    assert_eq!(terrain.water.rivers.len(), 1);
+
+    let river = &terrain.water.rivers[0];
+    assert!(river.points.n_points() >= 10);
+    assert!(terrain.height(river.points.last()).unwrap() < sea_level);
+    assert!(river.points.iter().all(|&p| terrain.is_underwater(p)));
+
+    let again = Terrain::new(5, 50, 1, &TerrainOptions::default());
+    assert_eq!(again.water.rivers[0].points.first(), river.points.first());
+
+    let small = Terrain::new(2, 2, 1, &TerrainOptions::default());
+    assert!(small.water.rivers.is_empty());
+}
+
+#[test]
+fn options_reach_the_world() {
+    crate::init::init();
+    let goria = Egregoria::new_with_options(EgregoriaOptions {
+        terrain_size: 1,
+        save_replay: false,
+        seed: 7,
+        terrain: TerrainOptions {
+            sea_level: -3.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    assert_eq!(goria.map().terrain.width, 1);
+    assert_eq!(goria.map().terrain.water.sea_level, -3.0);
+    assert_eq!(goria.read::<EgregoriaOptions>().seed, 7);
+}
@@ -0,0 +1,116 @@
+use crate::engine_interaction::WorldCommand;
+use crate::map::{BuildingGen, BuildingID, BuildingKind, LanePatternBuilder, ProjectFilter};
+use crate::map_dynamic::BuildingInfos;
+use crate::tests::TestCtx;
+use crate::transportation::train::{RailWagon, TrainReservations};
+use crate::transportation::train_line::{
+    PassengerTrain, TrainLineDescription, TrainLineID, TrainLines,
+};
+use geom::{vec2, vec3, Vec2, OBB};
+
+fn build_station(test: &TestCtx, pos: Vec2) -> BuildingID {
+    let b = test
+        .g
+        .map_mut()
+        .build_special_building(
+            &OBB::new(pos, vec2(1.0, 0.0), 20.0, 20.0),
+            BuildingKind::TrainStation,
+            BuildingGen::NoWalkway { door_pos: pos },
+            None,
+        )
+        .unwrap();
+    test.g.write::<BuildingInfos>().insert(b);
+    b
+}
+
+fn add_line(test: &mut TestCtx) -> (TrainLineID, Vec<BuildingID>) {
+    {
+        let mut m = test.g.map_mut();
+        let a = m.project(vec3(0.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+        let b = m.project(vec3(400.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+        let pat = LanePatternBuilder::new().rail(true).one_way(true).build();
+        m.make_connection(a, b, None, &pat);
+    }
+    let stations = vec![
+        build_station(test, vec2(100.0, 25.0)),
+        build_station(test, vec2(300.0, 25.0)),
+    ];
+    // Gives the stations a soul so that they find their platform
+    test.tick();
+
+    test.apply(&[WorldCommand::AddTrainLine(TrainLineDescription {
+        stations: stations.clone(),
+        n_trains: 1,
+        n_wagons: 2,
+        interval: 60.0,
+    })]);
+    let lines = test.g.read::<TrainLines>();
+    let (id, _) = lines.iter().next().unwrap();
+    (id, stations)
+}
+
+fn n_trains(test: &TestCtx) -> usize {
+    test.g.world().query::<&PassengerTrain>().iter().count()
+}
+
+#[test]
+fn train_serves_stations() {
+    let mut test = TestCtx::new();
+    let (line, _) = add_line(&mut test);
+
+    for _ in 0..5000 {
+        test.tick();
+        if test.g.read::<TrainLines>().boarding(line, 1).is_some() {
+            assert_eq!(n_trains(&test), 1);
+            return;
+        }
+    }
+
+    panic!("train did not reach the second station after 5000 ticks");
+}
+
+#[test]
+fn removing_line_removes_trains() {
+    let mut test = TestCtx::new();
+    let (line, _) = add_line(&mut test);
+
+    test.tick();
+    test.tick();
+    assert_eq!(n_trains(&test), 1);
+
+    test.apply(&[WorldCommand::RemoveTrainLine(line)]);
+    test.tick();
+    test.tick();
+    assert_eq!(n_trains(&test), 0);
+    assert_eq!(test.g.world().query::<&RailWagon>().iter().count(), 0);
+    assert!(test.g.read::<TrainLines>().get(line).is_none());
+}
+
+#[test]
+fn train_boards_once_it_holds_the_platform() {
+    let mut test = TestCtx::new();
+    let (line, stations) = add_line(&mut test);
+
+    test.tick();
+    let train = test.g.read::<TrainLines>().get(line).unwrap().vehicles[0];
+    let holder = |test: &TestCtx, b: BuildingID| {
+        test.g
+            .read::<TrainReservations>()
+            .platforms
+            .get(&b)
+            .copied()
+    };
+    assert_eq!(holder(&test, stations[0]), Some(train.0));
+    assert!(test.g.read::<TrainLines>().boarding(line, 0).is_none());
+
+    for _ in 0..5000 {
+        test.tick();
+        if test.g.read::<TrainLines>().boarding(line, 1).is_some() {
+            assert_eq!(holder(&test, stations[1]), Some(train.0));
+            assert_eq!(holder(&test, stations[0]), None);
+            return;
+        }
+    }
+
+    panic!("train did not reach the second station after 5000 ticks");
+}
@@ -0,0 +1,218 @@
+use crate::economy::Government;
+use crate::engine_interaction::{CommandInverses, UndoID, WorldCommand};
+use crate::map::{LaneID, LanePatternBuilder, LotID, LotKind, MapProject, ProjectKind};
+use crate::tests::TestCtx;
+use geom::vec3;
+
+fn last_entry(test: &TestCtx) -> UndoID {
+    test.g
+        .read::<CommandInverses>()
+        .0
+        .last()
+        .cloned()
+        .flatten()
+        .expect("command should be invertible")
+}
+
+#[test]
+fn undo_redo_connection() {
+    let mut test = TestCtx::new();
+
+    let money = test.g.read::<Government>().money;
+    let n_roads = test.g.map().roads().len();
+    let n_inters = test.g.map().intersections().len();
+
+    test.apply(&[WorldCommand::MapMakeConnection {
+        from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+        to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+        inter: Some(geom::vec2(50.0, 30.0)),
+        pat: LanePatternBuilder::new().build(),
+    }]);
+    assert_eq!(test.g.map().roads().len(), n_roads + 1);
+    assert!(test.g.read::<Government>().money < money);
+
+    let id = last_entry(&test);
+    test.apply(&[WorldCommand::Undo(id)]);
+    assert_eq!(test.g.map().roads().len(), n_roads);
+    assert_eq!(test.g.map().intersections().len(), n_inters);
+    assert_eq!(test.g.read::<Government>().money, money);
+
+    // already undone, nothing more is refunded
+    test.apply(&[WorldCommand::Undo(id)]);
+    assert_eq!(test.g.read::<Government>().money, money);
+
+    assert_eq!(last_entry(&test), id);
+    test.apply(&[WorldCommand::Redo(id)]);
+    assert_eq!(test.g.map().roads().len(), n_roads + 1);
+    assert!(test.g.read::<Government>().money < money);
+}
+
+#[test]
+fn undo_ids_cannot_be_forged() {
+    let mut test = TestCtx::new();
+    let money = test.g.read::<Government>().money;
+
+    test.apply(&[
+        WorldCommand::Undo(UndoID(1234)),
+        WorldCommand::Redo(UndoID(1234)),
+    ]);
+    assert_eq!(test.g.read::<Government>().money, money);
+    assert_eq!(test.g.read::<CommandInverses>().0, vec![None, None]);
+}
+
+#[test]
+fn start_commands_and_resets_cannot_be_undone() {
+    let mut test = TestCtx::new();
+    let n_roads = test.g.map().roads().len();
+    assert!(n_roads > 0);
+
+    // the first ids would be the ones of the start commands
+    test.apply(&[WorldCommand::Undo(UndoID(0)), WorldCommand::Undo(UndoID(1))]);
+    assert_eq!(test.g.map().roads().len(), n_roads);
+
+    // a tick starts a new list of inverses
+    test.tick();
+    test.apply(&[
+        WorldCommand::MapMakeConnection {
+            from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+            inter: None,
+            pat: LanePatternBuilder::new().build(),
+        },
+        WorldCommand::ResetSave,
+    ]);
+    let inverses = &test.g.read::<CommandInverses>().0;
+    assert_eq!(inverses.len(), 2);
+    assert!(inverses[0].is_some());
+    assert_eq!(inverses[1], None);
+    assert_eq!(test.g.map().roads().len(), n_roads);
+}
+
+#[test]
+fn failed_commands_are_not_paid_for() {
+    let mut test = TestCtx::new();
+    let money = test.g.read::<Government>().money;
+
+    test.apply(&[WorldCommand::AddTrain {
+        dist: 0.0,
+        n_wagons: 3,
+        lane: LaneID::default(),
+    }]);
+    assert_eq!(test.g.read::<Government>().money, money);
+    assert_eq!(test.g.read::<CommandInverses>().0, vec![None]);
+
+    test.apply(&[WorldCommand::MapMakeConnection {
+        from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+        to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+        inter: None,
+        pat: LanePatternBuilder::new().build(),
+    }]);
+    let id = last_entry(&test);
+    let spent = test.g.read::<Government>().money;
+    assert!(spent < money);
+
+    // the road is gone so the undo cannot be applied, nothing is refunded
+    let road = test
+        .g
+        .map()
+        .roads()
+        .values()
+        .find(|r| r.points.first().xy().mag() < 200.0)
+        .map(|r| r.id)
+        .unwrap();
+    test.g.map_mut().remove_road(road);
+
+    test.apply(&[WorldCommand::Undo(id)]);
+    assert_eq!(test.g.read::<Government>().money, spent);
+    assert_eq!(test.g.read::<CommandInverses>().0.last(), Some(&None));
+
+    test.apply(&[WorldCommand::Redo(id)]);
+    assert_eq!(test.g.read::<Government>().money, spent);
+}
+
+#[test]
+fn undo_connection_merges_split_road() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+    let n_roads = test.g.map().roads().len();
+    let n_inters = test.g.map().intersections().len();
+
+    let split = test.g.map().roads().keys().next().unwrap();
+    test.apply(&[WorldCommand::MapMakeConnection {
+        from: MapProject {
+            pos: vec3(100.0, 0.0, 0.0),
+            kind: ProjectKind::Road(split),
+        },
+        to: MapProject::ground(vec3(100.0, 100.0, 0.0)),
+        inter: None,
+        pat: LanePatternBuilder::new().build(),
+    }]);
+    assert_eq!(test.g.map().roads().len(), n_roads + 2);
+
+    let id = last_entry(&test);
+    test.apply(&[WorldCommand::Undo(id)]);
+    assert_eq!(test.g.map().roads().len(), n_roads);
+    assert_eq!(test.g.map().intersections().len(), n_inters);
+
+    test.apply(&[WorldCommand::Redo(id)]);
+    assert_eq!(test.g.map().roads().len(), n_roads + 2);
+}
+
+#[test]
+fn undo_remove_road() {
+    let mut test = TestCtx::new();
+
+    test.build_roads(&[
+        vec3(0.0, 0.0, 0.0),
+        vec3(100.0, 0.0, 0.0),
+        vec3(200.0, 50.0, 0.0),
+    ]);
+    let n_roads = test.g.map().roads().len();
+
+    let (id, pattern) = {
+        let map = test.g.map();
+        let r = map
+            .roads()
+            .values()
+            .find(|r| r.points.first().x > 150.0 || r.points.last().x > 150.0)
+            .unwrap();
+        (r.id, r.pattern(map.lanes()))
+    };
+
+    test.apply(&[WorldCommand::MapRemoveRoad(id)]);
+    assert_eq!(test.g.map().roads().len(), n_roads - 1);
+
+    test.apply(&[WorldCommand::Undo(last_entry(&test))]);
+    assert_eq!(test.g.map().roads().len(), n_roads);
+
+    let map = test.g.map();
+    let r = map
+        .roads()
+        .values()
+        .find(|r| r.points.first().x > 150.0 || r.points.last().x > 150.0)
+        .unwrap();
+    let new_pattern = r.pattern(map.lanes());
+    assert_eq!(new_pattern.lanes_forward, pattern.lanes_forward);
+    assert_eq!(new_pattern.lanes_backward, pattern.lanes_backward);
+}
+
+fn lot_kind(test: &TestCtx, lot: LotID) -> Option<LotKind> {
+    test.g.map().lots().get(lot).map(|l| l.kind)
+}
+
+#[test]
+fn undo_lot_zoning() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+    let lot = test.g.map().lots().keys().next().unwrap();
+    assert_eq!(lot_kind(&test, lot), Some(LotKind::Unassigned));
+
+    test.apply(&[WorldCommand::MapSetLotKind {
+        lot,
+        kind: LotKind::Industrial,
+    }]);
+    assert_eq!(lot_kind(&test, lot), Some(LotKind::Industrial));
+
+    test.apply(&[WorldCommand::Undo(last_entry(&test))]);
+    assert_eq!(lot_kind(&test, lot), Some(LotKind::Unassigned));
+}
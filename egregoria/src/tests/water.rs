@@ -0,0 +1,62 @@
+use crate::economy::Government;
+use crate::engine_interaction::WorldCommand;
+use crate::map::{LanePatternBuilder, Map, MapProject};
+use crate::tests::TestCtx;
+use common::saveload::{Bincode, Encoder};
+use geom::{vec2, vec3, Vec3};
+
+fn connection(from: Vec3, to: Vec3) -> WorldCommand {
+    WorldCommand::MapMakeConnection {
+        from: MapProject::ground(from),
+        to: MapProject::ground(to),
+        inter: None,
+        pat: LanePatternBuilder::new().build(),
+    }
+}
+
+/// A flat ground with a river going north to south at x = 500
+fn river_test() -> TestCtx {
+    let test = TestCtx::new();
+    test.flat_terrain();
+    test.g
+        .map_mut()
+        .terrain
+        .add_river(vec![vec2(500.0, 0.0), vec2(500.0, 1000.0)], 40.0);
+    test
+}
+
+#[test]
+fn rivers_are_underwater() {
+    let test = river_test();
+    let map = test.g.map();
+
+    assert!(map.terrain.is_underwater(vec2(500.0, 500.0)));
+    assert!(!map.terrain.is_underwater(vec2(300.0, 500.0)));
+    assert_eq!(map.terrain.water_height(vec2(500.0, 500.0)), Some(0.0));
+    assert!(map.terrain.height(vec2(500.0, 512.0)).unwrap() < 0.0);
+
+    let map: Map = Bincode::decode(&Bincode::encode(&*map).unwrap()).unwrap();
+    assert_eq!(map.terrain.water.rivers.len(), 1);
+    assert!(map.terrain.is_underwater(vec2(500.0, 500.0)));
+}
+
+#[test]
+fn roads_cross_water_as_bridges_or_tunnels() {
+    let mut test = river_test();
+
+    test.apply(&[connection(vec3(400.0, 500.0, 0.3), vec3(600.0, 500.0, 0.3))]);
+    assert_eq!(test.g.map().roads().len(), 0);
+
+    let bridge = connection(vec3(400.0, 300.0, 10.0), vec3(600.0, 300.0, 10.0));
+    let ground = connection(vec3(100.0, 700.0, 0.3), vec3(300.0, 700.0, 0.3));
+    let tunnel = connection(vec3(400.0, 800.0, -40.0), vec3(600.0, 800.0, -40.0));
+
+    let bridge_cost = Government::action_cost(&bridge, &test.g);
+    let ground_cost = Government::action_cost(&ground, &test.g);
+    let tunnel_cost = Government::action_cost(&tunnel, &test.g);
+    assert!(bridge_cost > ground_cost);
+    assert!(tunnel_cost > bridge_cost);
+
+    test.apply(&[bridge, ground, tunnel]);
+    assert_eq!(test.g.map().roads().len(), 3);
+}
@@ -0,0 +1,148 @@
+use crate::map::{LaneKind, Map, PathKind};
+use crate::map_dynamic::Itinerary;
+use crate::physics::{Collider, CollisionWorld};
+use crate::transportation::transit::{TransitKind, TransitLine, TransitLines, TransitVehicle};
+use crate::transportation::{make_vehicle_entity, Vehicle, VehicleID, VehicleKind, VehicleState};
+use crate::{Egregoria, ParCommandBuffer};
+use common::saveload::{Bincode, Encoder};
+use geom::{Color, Transform, Vec3};
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
+use slotmap::{new_key_type, SlotMap};
+
+new_key_type! {
+    pub struct BusLineID;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusLineDescription {
+    /// Buses go through the stops in order and loop back to the first one
+    pub stops: Vec<Vec3>,
+    pub n_buses: u32,
+    /// Minimum time between two departures from the first stop, in seconds
+    pub interval: f32,
+}
+
+#[derive(Debug)]
+pub struct BusKind;
+
+pub type BusLine = TransitLine<BusKind>;
+pub type BusLines = TransitLines<BusKind>;
+/// Component of the vehicles serving a bus line
+pub type Bus = TransitVehicle<BusLineID>;
+
+impl BusLineDescription {
+    /// Moves the stops onto the closest driving lane so buses can reach them
+    pub fn snapped(mut self, map: &Map) -> Self {
+        for stop in &mut self.stops {
+            let lane = unwrap_cont!(map.nearest_lane(*stop, LaneKind::Driving, Some(50.0)));
+            if let Some(lane) = map.lanes().get(lane) {
+                *stop = lane.points.project(*stop);
+            }
+        }
+        self
+    }
+}
+
+impl TransitKind for BusKind {
+    type LineID = BusLineID;
+    type Vehicle = VehicleID;
+    type Description = BusLineDescription;
+
+    const DWELL_TIME: f32 = 10.0;
+    const SPEED_ESTIMATE: f32 = 7.0;
+    const STOP_RADIUS: f32 = 10.0;
+    const PATH_KIND: PathKind = PathKind::Bus;
+
+    fn vehicle(e: Entity) -> VehicleID {
+        VehicleID(e)
+    }
+
+    fn entity(v: VehicleID) -> Entity {
+        v.0
+    }
+
+    fn n_stops(desc: &BusLineDescription) -> usize {
+        desc.stops.len()
+    }
+
+    fn n_vehicles(desc: &BusLineDescription) -> usize {
+        desc.n_buses as usize
+    }
+
+    fn interval(desc: &BusLineDescription) -> f32 {
+        desc.interval
+    }
+
+    fn stops(_: &Egregoria, desc: &BusLineDescription) -> Vec<Option<Vec3>> {
+        desc.stops.iter().copied().map(Some).collect()
+    }
+
+    fn spawn(
+        goria: &mut Egregoria,
+        line: BusLineID,
+        _: &BusLineDescription,
+        stop: Vec3,
+    ) -> Option<VehicleID> {
+        let map = goria.map();
+        let lane = map
+            .lanes()
+            .get(map.nearest_lane(stop, LaneKind::Driving, None)?)?;
+        let (pos, _, dir) = lane.points.project_segment_dir(stop);
+        drop(map);
+
+        let e = make_vehicle_entity(
+            goria,
+            Transform::new_dir(pos, dir),
+            Vehicle {
+                ang_velocity: 0.0,
+                wait_time: 0.0,
+                state: VehicleState::Driving,
+                kind: VehicleKind::Bus,
+                tint: Color::from_hex(0xe8_b0_20),
+                flag: 0,
+            },
+            Itinerary::NONE,
+            true,
+        );
+        goria.world.insert_one(e, Bus::new(line)).ok()?;
+        Some(VehicleID(e))
+    }
+
+    fn despawn(goria: &mut Egregoria, bus: VehicleID) {
+        if let Ok(c) = goria.world.remove_one::<Collider>(bus.0) {
+            goria.write::<CollisionWorld>().remove_maintain(c.0);
+        }
+        goria.read::<ParCommandBuffer>().kill(bus.0);
+    }
+}
+
+/// Bus lines didn't keep the position of their stops apart from their description
+pub(crate) fn migrate_bus_lines_stops(data: Vec<u8>) -> Option<Vec<u8>> {
+    #[derive(Deserialize)]
+    struct OldBusLine {
+        desc: BusLineDescription,
+        buses: Vec<VehicleID>,
+        boarding: Vec<Option<VehicleID>>,
+        last_departure: f64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(from = "OldBusLine")]
+    struct Migrated(BusLine);
+
+    impl From<OldBusLine> for Migrated {
+        fn from(old: OldBusLine) -> Self {
+            Migrated(BusLine {
+                stops: vec![None; old.desc.stops.len()],
+                desc: old.desc,
+                vehicles: old.buses,
+                boarding: old.boarding,
+                last_departure: old.last_departure,
+            })
+        }
+    }
+
+    let lines: SlotMap<BusLineID, Migrated> = Bincode::decode(&data).ok()?;
+    Bincode::encode(&lines).ok()
+}
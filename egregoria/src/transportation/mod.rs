@@ -1,9 +1,14 @@
 use crate::map::BuildingID;
+use crate::transportation::train::TrainID;
 use serde::{Deserialize, Serialize};
 
+pub mod bus;
 pub mod pedestrian;
 pub mod road;
+pub mod traffic_stats;
 pub mod train;
+pub mod train_line;
+pub mod transit;
 mod vehicle;
 
 pub use pedestrian::*;
@@ -14,5 +19,7 @@ pub enum Location {
     Outside,
     Vehicle(VehicleID),
     Building(BuildingID),
+    /// Riding a passenger train, identified by its locomotive
+    Train(TrainID),
 }
 debug_inspect_impl!(Location);
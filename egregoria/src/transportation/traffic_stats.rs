@@ -0,0 +1,313 @@
+use crate::economy::{HISTORY_SIZE, LEVEL_FREQS};
+use crate::map::{LaneID, Map, TraverseKind};
+use crate::map_dynamic::Itinerary;
+use crate::physics::Speed;
+use crate::transportation::Vehicle;
+use crate::utils::time::Tick;
+use hecs::{Entity, World};
+use resources::Resources;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+
+/// Vehicles slower than this are counted as queuing, m/s
+const QUEUE_SPEED: f32 = 0.5;
+
+/// Measurements of one lane during one bin
+#[derive(Default, Copy, Clone, Debug)]
+pub struct LaneSample {
+    /// Number of vehicles that left the lane
+    pub throughput: u32,
+    /// Sum over the ticks of the number of vehicles on the lane
+    pub vehicle_ticks: u32,
+    pub speed_sum: f32,
+    pub wait_sum: f32,
+    /// Sum over the ticks of the number of queuing vehicles on the lane
+    pub queue_sum: u32,
+}
+
+/// Averages of a lane over a window of bins
+#[derive(Default, Copy, Clone, Debug)]
+pub struct LaneStats {
+    pub throughput: u32,
+    /// m/s, None when no vehicle used the lane
+    pub avg_speed: Option<f32>,
+    /// Average number of queuing vehicles
+    pub avg_queue: f32,
+    /// Average `Vehicle::wait_time` of the vehicles on the lane, in seconds
+    pub avg_wait: Option<f32>,
+}
+
+/// Only the bins in which the lane saw traffic are kept, most lanes are empty most of the time
+#[derive(Default, Clone)]
+struct LaneHistory {
+    /// For each level, the bin numbers and their samples from oldest to newest
+    levels: [VecDeque<(u32, LaneSample)>; LEVEL_FREQS.len()],
+}
+
+/// Per-lane traffic measurements, binned like `EcoStats` using `LEVEL_FREQS`.
+/// Not saved: measurements start over when a game is loaded.
+#[derive(Clone)]
+pub struct TrafficStats {
+    lanes: BTreeMap<LaneID, LaneHistory>,
+    /// Number of the current bin of each level, counted since the start
+    bins: [u32; LEVEL_FREQS.len()],
+    /// Number of ticks in each bin, the same for every lane
+    bin_ticks: [[u32; HISTORY_SIZE]; LEVEL_FREQS.len()],
+    /// Lane each vehicle was on at the last tick, to count the ones leaving
+    last_lane: BTreeMap<Entity, LaneID>,
+}
+
+impl Default for TrafficStats {
+    fn default() -> Self {
+        Self {
+            lanes: Default::default(),
+            bins: [0; LEVEL_FREQS.len()],
+            bin_ticks: [[0; HISTORY_SIZE]; LEVEL_FREQS.len()],
+            last_lane: Default::default(),
+        }
+    }
+}
+
+impl LaneSample {
+    fn merge(&mut self, other: &LaneSample) {
+        self.throughput += other.throughput;
+        self.vehicle_ticks += other.vehicle_ticks;
+        self.speed_sum += other.speed_sum;
+        self.wait_sum += other.wait_sum;
+        self.queue_sum += other.queue_sum;
+    }
+
+    fn stats(&self, ticks: u32) -> LaneStats {
+        let per_vehicle = |sum: f32| {
+            if self.vehicle_ticks == 0 {
+                return None;
+            }
+            Some(sum / self.vehicle_ticks as f32)
+        };
+        LaneStats {
+            throughput: self.throughput,
+            avg_speed: per_vehicle(self.speed_sum),
+            avg_queue: self.queue_sum as f32 / ticks.max(1) as f32,
+            avg_wait: per_vehicle(self.wait_sum),
+        }
+    }
+}
+
+impl LaneStats {
+    /// 0 when vehicles drive at the speed limit, 1 when they are stopped
+    pub fn congestion(&self, speed_limit: f32) -> f32 {
+        let speed = unwrap_ret!(self.avg_speed, 0.0);
+        (1.0 - speed / speed_limit.max(0.1)).clamp(0.0, 1.0)
+    }
+}
+
+impl TrafficStats {
+    fn cursor(&self, level: usize) -> usize {
+        self.bins[level] as usize % HISTORY_SIZE
+    }
+
+    /// Stats of the lane over the last `n_bins` bins of the level, including the current one
+    pub fn lane_stats(&self, lane: LaneID, level: usize, n_bins: usize) -> Option<LaneStats> {
+        let h = self.lanes.get(&lane)?;
+        let (sample, ticks) = self.window(h, level, n_bins)?;
+        Some(sample.stats(ticks))
+    }
+
+    /// Stats of every lane that saw traffic, see `lane_stats`
+    pub fn iter_lanes(
+        &self,
+        level: usize,
+        n_bins: usize,
+    ) -> impl Iterator<Item = (LaneID, LaneStats)> + '_ {
+        self.lanes.iter().filter_map(move |(id, h)| {
+            let (sample, ticks) = self.window(h, level, n_bins)?;
+            Some((*id, sample.stats(ticks)))
+        })
+    }
+
+    fn window(&self, h: &LaneHistory, level: usize, n_bins: usize) -> Option<(LaneSample, u32)> {
+        let samples = h.levels.get(level)?;
+        let n_bins = n_bins.min(HISTORY_SIZE);
+        let cur = self.bins[level];
+        let cursor = self.cursor(level);
+
+        let mut sample = LaneSample::default();
+        for (_, s) in samples
+            .iter()
+            .rev()
+            .take_while(|(bin, _)| ((cur - bin) as usize) < n_bins)
+        {
+            sample.merge(s);
+        }
+        let ticks = (0..n_bins)
+            .map(|i| self.bin_ticks[level][(cursor + HISTORY_SIZE - i) % HISTORY_SIZE])
+            .sum();
+        Some((sample, ticks))
+    }
+
+    /// Writes one row per lane and bin of the level, oldest bins first
+    pub fn write_csv(&self, level: usize, mut w: impl Write) -> std::io::Result<()> {
+        writeln!(w, "lane,bin,throughput,avg_speed,avg_queue,avg_wait")?;
+        if level >= LEVEL_FREQS.len() {
+            return Ok(());
+        }
+        let cur = self.bins[level];
+        let cursor = self.cursor(level);
+        let opt = |v: Option<f32>| v.map(|v| v.to_string()).unwrap_or_default();
+
+        for (id, h) in &self.lanes {
+            let mut samples = h.levels[level].iter().peekable();
+            for i in 0..HISTORY_SIZE {
+                let age = (HISTORY_SIZE - 1 - i) as u32;
+                let sample = cur
+                    .checked_sub(age)
+                    .and_then(|bin| samples.next_if(|(b, _)| *b == bin))
+                    .map(|(_, s)| *s)
+                    .unwrap_or_default();
+                let stats = sample.stats(self.bin_ticks[level][(cursor + 1 + i) % HISTORY_SIZE]);
+                writeln!(
+                    w,
+                    "{:?},{},{},{},{},{}",
+                    id,
+                    i,
+                    stats.throughput,
+                    opt(stats.avg_speed),
+                    stats.avg_queue,
+                    opt(stats.avg_wait)
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts a new bin of the level, forgetting the samples older than `HISTORY_SIZE` bins
+    fn next_bin(&mut self, level: usize) {
+        self.bins[level] += 1;
+        let cur = self.bins[level];
+        let cursor = self.cursor(level);
+        self.bin_ticks[level][cursor] = 0;
+        for h in self.lanes.values_mut() {
+            let samples = &mut h.levels[level];
+            while samples
+                .front()
+                .map_or(false, |(bin, _)| (cur - bin) as usize >= HISTORY_SIZE)
+            {
+                samples.pop_front();
+            }
+        }
+    }
+
+    fn advance(&mut self, tick: u32, map: &Map) {
+        for (level, freq) in LEVEL_FREQS.iter().enumerate() {
+            if tick % *freq == 0 {
+                self.next_bin(level);
+            }
+            let cursor = self.cursor(level);
+            self.bin_ticks[level][cursor] += 1;
+        }
+
+        if tick % LEVEL_FREQS[0] == 0 {
+            self.lanes.retain(|id, h| {
+                map.lanes().contains_key(*id) && h.levels.iter().any(|l| !l.is_empty())
+            });
+        }
+    }
+
+    fn record(&mut self, lane: LaneID, f: impl Fn(&mut LaneSample)) {
+        let h = self.lanes.entry(lane).or_default();
+        for (samples, &bin) in h.levels.iter_mut().zip(&self.bins) {
+            match samples.back_mut() {
+                Some((b, s)) if *b == bin => f(s),
+                _ => {
+                    let mut s = LaneSample::default();
+                    f(&mut s);
+                    samples.push_back((bin, s));
+                }
+            }
+        }
+    }
+}
+
+#[profiling::function]
+pub fn traffic_stats_system(world: &mut World, resources: &mut Resources) {
+    let mut stats = resources.get_mut::<TrafficStats>().unwrap();
+    let map = resources.get::<Map>().unwrap();
+    let tick = resources.get::<Tick>().unwrap().0;
+
+    stats.advance(tick, &map);
+
+    let mut cur_lane = BTreeMap::new();
+    for (e, (vehicle, itin, speed)) in world.query::<(&Vehicle, &Itinerary, &Speed)>().iter() {
+        let Some(TraverseKind::Lane(lane)) = itin.get_travers().map(|t| t.kind) else {
+            continue;
+        };
+        cur_lane.insert(e, lane);
+
+        let (speed, wait) = (speed.speed, vehicle.wait_time);
+        stats.record(lane, |s| {
+            s.vehicle_ticks += 1;
+            s.speed_sum += speed;
+            s.wait_sum += wait;
+            if speed < QUEUE_SPEED {
+                s.queue_sum += 1;
+            }
+        });
+    }
+
+    let last_lane = std::mem::replace(&mut stats.last_lane, cur_lane);
+    for (e, lane) in last_lane {
+        if stats.last_lane.get(&e) != Some(&lane) {
+            stats.record(lane, |s| s.throughput += 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LaneSample, TrafficStats, HISTORY_SIZE};
+    use crate::map::LaneID;
+
+    #[test]
+    fn averages_per_vehicle_and_tick() {
+        let s = LaneSample {
+            throughput: 3,
+            vehicle_ticks: 4,
+            speed_sum: 20.0,
+            wait_sum: 2.0,
+            queue_sum: 10,
+        };
+        let stats = s.stats(5);
+        assert_eq!(stats.avg_speed, Some(5.0));
+        assert_eq!(stats.avg_wait, Some(0.5));
+        assert_eq!(stats.avg_queue, 2.0);
+        assert_eq!(stats.congestion(10.0), 0.5);
+
+        let empty = LaneSample::default().stats(0);
+        assert_eq!(empty.avg_speed, None);
+        assert_eq!(empty.congestion(10.0), 0.0);
+    }
+
+    #[test]
+    fn only_recent_bins_are_kept() {
+        let mut stats = TrafficStats::default();
+        let lane = LaneID::default();
+        stats.record(lane, |s| s.throughput += 1);
+        stats.next_bin(0);
+        stats.next_bin(0);
+        stats.record(lane, |s| s.throughput += 2);
+
+        assert_eq!(stats.lane_stats(lane, 0, 1).unwrap().throughput, 2);
+        assert_eq!(stats.lane_stats(lane, 0, 2).unwrap().throughput, 2);
+        assert_eq!(stats.lane_stats(lane, 0, 3).unwrap().throughput, 3);
+        assert_eq!(stats.lanes[&lane].levels[0].len(), 2);
+
+        for _ in 0..HISTORY_SIZE - 1 {
+            stats.next_bin(0);
+        }
+        assert_eq!(stats.lanes[&lane].levels[0].len(), 1);
+        assert_eq!(
+            stats.lane_stats(lane, 0, HISTORY_SIZE).unwrap().throughput,
+            2
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use crate::map::{IntersectionID, LaneID, Map, TraverseKind};
+use crate::map::{BuildingID, IntersectionID, LaneID, Map, TraverseKind};
 use crate::map_dynamic::{DispatchKind, ItineraryFollower2, ItineraryKind};
-use crate::{Egregoria, GameTime, Itinerary, ItineraryLeader, Selectable, Speed};
+use crate::{Egregoria, GameTime, Itinerary, ItineraryLeader, ParCommandBuffer, Selectable, Speed};
+use common::saveload::{Bincode, Encoder};
 use egui_inspect::Inspect;
 use geom::{PolyLine3, Polyline3Queue, Transform, Vec3};
 use hecs::{Entity, View, World};
@@ -19,6 +20,14 @@ debug_inspect_impl!(TrainID);
 pub struct TrainReservations {
     pub reservations: BTreeMap<IntersectionID, Entity>,
     pub localisations: BTreeMap<TraverseKind, BTreeMap<Entity, f32>>,
+    /// The passenger train stopped, or about to start, at the platform of each station
+    pub platforms: BTreeMap<BuildingID, Entity>,
+}
+
+/// Platforms were not reserved yet
+pub(crate) fn migrate_train_reservations_platforms(mut data: Vec<u8>) -> Option<Vec<u8>> {
+    data.extend(Bincode::encode(&BTreeMap::<BuildingID, Entity>::new()).ok()?);
+    Some(data)
 }
 
 #[derive(Serialize, Deserialize, Inspect)]
@@ -123,7 +132,6 @@ pub fn spawn_train(
             dec_force: 2.5,
             length: trainlength,
         },
-        DispatchKind::FretTrain,
         LocomotiveReservation {
             cur_travers_dist: dist,
             waited_for: 0.0,
@@ -165,10 +173,39 @@ pub fn spawn_train(
     }
 
     world.insert_one(loco, leader).unwrap();
+    if let RailWagonKind::Fret = kind {
+        world.insert_one(loco, DispatchKind::FretTrain).unwrap();
+    }
 
     Some(loco)
 }
 
+/// Removes the locomotive and its wagons, and frees the rails they reserved
+pub fn despawn_train(goria: &mut Egregoria, train: TrainID) {
+    let wagons: Vec<Entity> = goria
+        .world
+        .query::<&ItineraryFollower2>()
+        .iter()
+        .filter(|(_, f)| f.leader == train.0)
+        .map(|(e, _)| e)
+        .collect();
+
+    let mut reservations = goria.write::<TrainReservations>();
+    reservations.reservations.retain(|_, e| *e != train.0);
+    reservations.localisations.retain(|_, l| {
+        l.remove(&train.0);
+        !l.is_empty()
+    });
+    reservations.platforms.retain(|_, e| *e != train.0);
+    drop(reservations);
+
+    let cbuf = goria.read::<ParCommandBuffer>();
+    for wagon in wagons {
+        cbuf.kill(wagon);
+    }
+    cbuf.kill(train.0);
+}
+
 pub fn traverse_forward<'a>(
     map: &'a Map,
     itin: &'a Itinerary,
@@ -0,0 +1,153 @@
+use crate::map::{BuildingID, LaneKind, PathKind};
+use crate::map_dynamic::Itinerary;
+use crate::souls::train_station::TrainStation;
+use crate::transportation::train::{
+    despawn_train, spawn_train, RailWagonKind, TrainID, TrainReservations,
+};
+use crate::transportation::transit::{TransitKind, TransitLine, TransitLines, TransitVehicle};
+use crate::Egregoria;
+use geom::Vec3;
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
+use slotmap::new_key_type;
+
+new_key_type! {
+    pub struct TrainLineID;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainLineDescription {
+    /// Train stations served in order, trains loop back to the first one
+    pub stations: Vec<BuildingID>,
+    pub n_trains: u32,
+    pub n_wagons: u32,
+    /// Minimum time between two departures from the first station, in seconds
+    pub interval: f32,
+}
+
+#[derive(Debug)]
+pub struct TrainKind;
+
+/// The stops of a train line are the platforms of its stations
+pub type TrainLine = TransitLine<TrainKind>;
+pub type TrainLines = TransitLines<TrainKind>;
+/// Component of the locomotives serving a passenger train line
+pub type PassengerTrain = TransitVehicle<TrainLineID>;
+
+impl TransitKind for TrainKind {
+    type LineID = TrainLineID;
+    type Vehicle = TrainID;
+    type Description = TrainLineDescription;
+
+    const DWELL_TIME: f32 = 20.0;
+    const SPEED_ESTIMATE: f32 = 20.0;
+    const STOP_RADIUS: f32 = 20.0;
+    const PATH_KIND: PathKind = PathKind::Rail;
+
+    fn vehicle(e: Entity) -> TrainID {
+        TrainID(e)
+    }
+
+    fn entity(v: TrainID) -> Entity {
+        v.0
+    }
+
+    fn n_stops(desc: &TrainLineDescription) -> usize {
+        desc.stations.len()
+    }
+
+    fn n_vehicles(desc: &TrainLineDescription) -> usize {
+        desc.n_trains as usize
+    }
+
+    fn interval(desc: &TrainLineDescription) -> f32 {
+        desc.interval
+    }
+
+    /// None while the station has no rails nearby
+    fn stops(goria: &Egregoria, desc: &TrainLineDescription) -> Vec<Option<Vec3>> {
+        let mut stations = goria.world.query::<&TrainStation>();
+        desc.stations
+            .iter()
+            .map(|&b| {
+                stations
+                    .iter()
+                    .find(|(_, station)| station.building == b)
+                    .and_then(|(_, station)| station.platform)
+            })
+            .collect()
+    }
+
+    /// The train reserves the first platform until it starts boarding there
+    fn spawn(
+        goria: &mut Egregoria,
+        line: TrainLineID,
+        desc: &TrainLineDescription,
+        platform: Vec3,
+    ) -> Option<TrainID> {
+        let map = goria.map();
+        let lane = map
+            .lanes()
+            .get(map.nearest_lane(platform, LaneKind::Rail, None)?)?;
+        let dist = lane.points.length_at_proj(lane.points.project(platform));
+        let lane = lane.id;
+        drop(map);
+
+        let loco = spawn_train(goria, dist, desc.n_wagons, lane, RailWagonKind::Passenger)?;
+        goria
+            .world
+            .insert_one(loco, PassengerTrain::new(line))
+            .ok()?;
+        goria
+            .write::<TrainReservations>()
+            .platforms
+            .insert(*desc.stations.first()?, loco);
+        Some(TrainID(loco))
+    }
+
+    fn despawn(goria: &mut Egregoria, train: TrainID) {
+        despawn_train(goria, train);
+    }
+
+    fn can_spawn(goria: &Egregoria, desc: &TrainLineDescription) -> bool {
+        desc.stations.first().map_or(false, |b| {
+            !goria.read::<TrainReservations>().platforms.contains_key(b)
+        })
+    }
+
+    /// Boarding only starts once the train holds the platform,
+    /// a train of another line might still be stopped there
+    fn arrive(
+        goria: &Egregoria,
+        desc: &TrainLineDescription,
+        station: usize,
+        loco: Entity,
+        itin: &mut Itinerary,
+        time: f64,
+    ) -> bool {
+        let Some(&b) = desc.stations.get(station) else {
+            return false;
+        };
+        let mut reservations = goria.write::<TrainReservations>();
+        // The line might have been edited while the train was stopped at another station
+        reservations
+            .platforms
+            .retain(|&station, train| station == b || *train != loco);
+        if *reservations.platforms.entry(b).or_insert(loco) != loco {
+            *itin = Itinerary::NONE;
+            return false;
+        }
+        *itin = Itinerary::wait_until(time + Self::DWELL_TIME as f64);
+        true
+    }
+
+    fn depart(goria: &Egregoria, desc: &TrainLineDescription, station: usize, loco: Entity) {
+        let Some(b) = desc.stations.get(station) else {
+            return;
+        };
+        let mut reservations = goria.write::<TrainReservations>();
+        if reservations.platforms.get(b) == Some(&loco) {
+            reservations.platforms.remove(b);
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use crate::map::PathKind;
+use crate::map_dynamic::Itinerary;
+use crate::utils::time::GameTime;
+use crate::Egregoria;
+use geom::{Transform, Vec3};
+use hecs::{Entity, World};
+use ordered_float::OrderedFloat;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use slotmap::{Key, SlotMap};
+use std::fmt::Debug;
+
+/// Used to compare walking, driving and public transport trips, m/s
+pub const WALKING_SPEED_ESTIMATE: f32 = 1.2;
+
+/// What differs between the vehicles serving transit lines, buses or passenger trains.
+/// The default methods make vehicles wait at the stop for `DWELL_TIME`.
+pub trait TransitKind: 'static + Sized + Debug {
+    type LineID: Key + Send + Sync + Serialize + DeserializeOwned;
+    type Vehicle: Copy + PartialEq + Debug + Send + Sync + Serialize + DeserializeOwned;
+    type Description: Clone + Debug + Send + Sync + Serialize + DeserializeOwned;
+
+    /// Time a vehicle waits at a stop for riders to get in and out, in seconds
+    const DWELL_TIME: f32;
+    /// Used to estimate trip durations, m/s
+    const SPEED_ESTIMATE: f32;
+    /// How close to a stop a vehicle has to be to serve it, in meters
+    const STOP_RADIUS: f32;
+    const PATH_KIND: PathKind;
+
+    fn vehicle(e: Entity) -> Self::Vehicle;
+    fn entity(v: Self::Vehicle) -> Entity;
+
+    fn n_stops(desc: &Self::Description) -> usize;
+    fn n_vehicles(desc: &Self::Description) -> usize;
+    /// Minimum time between two departures from the first stop, in seconds
+    fn interval(desc: &Self::Description) -> f32;
+
+    /// Where the vehicles stop, None while a stop cannot be served
+    fn stops(goria: &Egregoria, desc: &Self::Description) -> Vec<Option<Vec3>>;
+
+    fn spawn(
+        goria: &mut Egregoria,
+        line: Self::LineID,
+        desc: &Self::Description,
+        stop: Vec3,
+    ) -> Option<Self::Vehicle>;
+    fn despawn(goria: &mut Egregoria, v: Self::Vehicle);
+
+    /// Whether a new vehicle can be spawned at the first stop
+    fn can_spawn(_goria: &Egregoria, _desc: &Self::Description) -> bool {
+        true
+    }
+
+    /// Called when the vehicle reaches the stop, returns whether it can start boarding
+    fn arrive(
+        _goria: &Egregoria,
+        _desc: &Self::Description,
+        _stop: usize,
+        _vehicle: Entity,
+        itin: &mut Itinerary,
+        time: f64,
+    ) -> bool {
+        *itin = Itinerary::wait_until(time + Self::DWELL_TIME as f64);
+        true
+    }
+
+    /// Called when the vehicle leaves the stop
+    fn depart(_goria: &Egregoria, _desc: &Self::Description, _stop: usize, _vehicle: Entity) {}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TransitLine<K: TransitKind> {
+    pub desc: K::Description,
+    pub vehicles: Vec<K::Vehicle>,
+    /// Where the vehicles stop, updated every tick
+    pub stops: Vec<Option<Vec3>>,
+    /// The vehicle currently boarding at each stop
+    pub boarding: Vec<Option<K::Vehicle>>,
+    pub last_departure: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TransitLines<K: TransitKind> {
+    lines: SlotMap<K::LineID, TransitLine<K>>,
+}
+
+impl<K: TransitKind> Default for TransitLines<K> {
+    fn default() -> Self {
+        Self {
+            lines: SlotMap::with_key(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum TransitState {
+    Moving,
+    Boarding(usize),
+}
+
+/// Component of the vehicles serving a transit line
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransitVehicle<L> {
+    pub line: L,
+    pub next_stop: usize,
+    pub state: TransitState,
+}
+
+impl<L> TransitVehicle<L> {
+    pub fn new(line: L) -> Self {
+        Self {
+            line,
+            next_stop: 0,
+            state: TransitState::Moving,
+        }
+    }
+
+    pub fn boarding_at(&self) -> Option<usize> {
+        match self.state {
+            TransitState::Boarding(stop) => Some(stop),
+            TransitState::Moving => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct TransitTrip<L> {
+    pub line: L,
+    pub from: usize,
+    pub to: usize,
+    pub from_pos: Vec3,
+    /// Estimated duration including walking to and from the stops, in seconds
+    pub duration: f32,
+}
+
+impl<K: TransitKind> TransitLine<K> {
+    /// A line runs once it has at least two stops and all of them can be served
+    pub fn is_operational(&self) -> bool {
+        self.stops.len() >= 2 && self.stops.iter().all(Option::is_some)
+    }
+}
+
+impl<K: TransitKind> TransitLines<K> {
+    pub fn add(&mut self, desc: K::Description) -> K::LineID {
+        let n_stops = K::n_stops(&desc);
+        self.lines.insert(TransitLine {
+            desc,
+            vehicles: vec![],
+            stops: vec![None; n_stops],
+            boarding: vec![None; n_stops],
+            last_departure: f64::NEG_INFINITY,
+        })
+    }
+
+    /// Returns the previous description of the line
+    pub fn edit(&mut self, id: K::LineID, desc: K::Description) -> Option<K::Description> {
+        let line = self.lines.get_mut(id)?;
+        let n_stops = K::n_stops(&desc);
+        line.stops = vec![None; n_stops];
+        line.boarding = vec![None; n_stops];
+        Some(std::mem::replace(&mut line.desc, desc))
+    }
+
+    pub fn remove(&mut self, id: K::LineID) -> Option<TransitLine<K>> {
+        self.lines.remove(id)
+    }
+
+    pub fn get(&self, id: K::LineID) -> Option<&TransitLine<K>> {
+        self.lines.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (K::LineID, &TransitLine<K>)> {
+        self.lines.iter()
+    }
+
+    pub fn boarding(&self, id: K::LineID, stop: usize) -> Option<K::Vehicle> {
+        *self.lines.get(id)?.boarding.get(stop)?
+    }
+
+    /// Whether a rider of `vehicle` going to stop `to` has arrived, or cannot get there anymore
+    pub fn ride_over(
+        &self,
+        world: &World,
+        id: K::LineID,
+        to: usize,
+        vehicle: Option<K::Vehicle>,
+    ) -> bool {
+        let Some(v) =
+            vehicle.and_then(|v| world.get::<&TransitVehicle<K::LineID>>(K::entity(v)).ok())
+        else {
+            return true;
+        };
+        v.line != id
+            || v.boarding_at() == Some(to)
+            || self.get(id).map_or(true, |l| to >= l.stops.len())
+    }
+
+    /// Finds the fastest trip from `start` to `end` using a single line
+    pub fn best_trip(&self, start: Vec3, end: Vec3) -> Option<TransitTrip<K::LineID>> {
+        let mut best: Option<TransitTrip<K::LineID>> = None;
+        for (id, line) in &self.lines {
+            if !line.is_operational() || line.vehicles.is_empty() {
+                continue;
+            }
+            let stops: Vec<Vec3> = line.stops.iter().flatten().copied().collect();
+            let closest =
+                |p: Vec3| (0..stops.len()).min_by_key(|&i| OrderedFloat(stops[i].distance2(p)));
+            let (from, to) = unwrap_cont!(closest(start).zip(closest(end)));
+            if from == to {
+                continue;
+            }
+
+            let mut ride = 0.0;
+            let mut i = from;
+            while i != to {
+                let next = (i + 1) % stops.len();
+                ride += stops[i].distance(stops[next]) / K::SPEED_ESTIMATE + K::DWELL_TIME;
+                i = next;
+            }
+
+            let duration = (start.distance(stops[from]) + stops[to].distance(end))
+                / WALKING_SPEED_ESTIMATE
+                + K::interval(&line.desc) * 0.5
+                + ride;
+
+            if best.map_or(true, |b| duration < b.duration) {
+                best = Some(TransitTrip {
+                    line: id,
+                    from,
+                    to,
+                    from_pos: stops[from],
+                    duration,
+                });
+            }
+        }
+        best
+    }
+}
+
+/// Keeps the number of vehicles of each line in sync with its description
+/// and moves them from stop to stop
+#[profiling::function]
+pub fn transit_system<K: TransitKind>(goria: &mut Egregoria) {
+    let time = goria.read::<GameTime>().timestamp;
+    let mut to_despawn = vec![];
+    let mut to_spawn = vec![];
+
+    {
+        let g = &*goria;
+        let map = g.map();
+        let mut lines = g.write::<TransitLines<K>>();
+        let world = &g.world;
+
+        for (id, line) in lines.lines.iter_mut() {
+            line.stops = K::stops(g, &line.desc);
+
+            line.vehicles.retain(|v| world.contains(K::entity(*v)));
+            for v in &mut line.boarding {
+                if v.map_or(false, |v| !world.contains(K::entity(v))) {
+                    *v = None;
+                }
+            }
+
+            while line.vehicles.len() > K::n_vehicles(&line.desc) {
+                to_despawn.extend(line.vehicles.pop());
+            }
+
+            // Only one vehicle is spawned at a time, the next one comes when the previous one
+            // left the first stop
+            let first_stop_served = line.vehicles.iter().any(|v| {
+                world
+                    .get::<&TransitVehicle<K::LineID>>(K::entity(*v))
+                    .map_or(false, |v| v.next_stop == 0)
+            });
+            if line.vehicles.len() < K::n_vehicles(&line.desc)
+                && line.is_operational()
+                && !first_stop_served
+                && K::can_spawn(g, &line.desc)
+            {
+                if let Some(stop) = line.stops[0] {
+                    to_spawn.push((id, line.desc.clone(), stop));
+                }
+            }
+        }
+
+        for (e, (v, itin, trans)) in world
+            .query::<(&mut TransitVehicle<K::LineID>, &mut Itinerary, &Transform)>()
+            .iter()
+        {
+            let Some(line) = lines.lines.get_mut(v.line) else {
+                to_despawn.push(K::vehicle(e));
+                continue;
+            };
+            let n_stops = line.stops.len();
+            if n_stops < 2 {
+                continue;
+            }
+
+            match v.state {
+                TransitState::Moving => {
+                    if v.next_stop >= n_stops {
+                        v.next_stop = 0;
+                        *itin = Itinerary::NONE;
+                    }
+                    if !itin.has_ended(0.0) {
+                        continue;
+                    }
+                    let stop = v.next_stop;
+                    let Some(stop_pos) = line.stops[stop] else {
+                        continue;
+                    };
+                    if itin.is_none() && !trans.position.is_close(stop_pos, K::STOP_RADIUS) {
+                        *itin = Itinerary::route(trans.position, stop_pos, &map, K::PATH_KIND)
+                            .unwrap_or_else(|| Itinerary::wait_for_reroute(K::PATH_KIND, stop_pos));
+                        continue;
+                    }
+                    if !K::arrive(g, &line.desc, stop, e, itin, time) {
+                        continue;
+                    }
+                    line.boarding[stop] = Some(K::vehicle(e));
+                    v.state = TransitState::Boarding(stop);
+                }
+                TransitState::Boarding(stop) => {
+                    if !itin.has_ended(time) {
+                        continue;
+                    }
+                    if stop == 0 && time < line.last_departure + K::interval(&line.desc) as f64 {
+                        continue;
+                    }
+                    let next = (stop + 1) % n_stops;
+                    let Some(stop_pos) = line.stops[next] else {
+                        continue;
+                    };
+                    *itin = unwrap_or!(
+                        Itinerary::route(trans.position, stop_pos, &map, K::PATH_KIND),
+                        continue
+                    );
+                    if stop == 0 {
+                        line.last_departure = time;
+                    }
+                    if let Some(b) = line.boarding.get_mut(stop) {
+                        *b = None;
+                    }
+                    K::depart(g, &line.desc, stop, e);
+                    v.next_stop = next;
+                    v.state = TransitState::Moving;
+                }
+            }
+        }
+    }
+
+    for v in to_despawn {
+        K::despawn(goria, v);
+    }
+
+    for (id, desc, stop) in to_spawn {
+        let Some(v) = K::spawn(goria, id, &desc, stop) else {
+            continue;
+        };
+        if let Some(line) = goria.write::<TransitLines<K>>().lines.get_mut(id) {
+            line.vehicles.push(v);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::Egregoria;
+use std::collections::{BTreeMap, VecDeque};
+
+/// How many checked frames are kept, desyncs are usually reported a frame or two after the check
+const MAX_SNAPSHOTS: usize = 4;
+
+/// Serialized resources of the last frames whose hashes were checked, keyed by frame,
+/// so that both sides of a desync can dump the state at the frame where it diverged.
+#[derive(Default)]
+pub struct DesyncSnapshots {
+    frames: VecDeque<(u32, BTreeMap<String, Vec<u8>>)>,
+}
+
+impl DesyncSnapshots {
+    /// Keeps the serialized resources of the current tick and returns their hashes,
+    /// as computed by `Egregoria::hashes`
+    pub fn record(&mut self, goria: &Egregoria) -> BTreeMap<String, u64> {
+        let resources = goria.serialized_resources();
+        let hashes = resources
+            .iter()
+            .map(|(name, data)| (name.clone(), common::hash_u64(&**data)))
+            .collect();
+
+        self.frames.push_back((goria.get_tick(), resources));
+        while self.frames.len() > MAX_SNAPSHOTS {
+            self.frames.pop_front();
+        }
+        hashes
+    }
+
+    /// Writes the given resources as they were at `frame` to
+    /// `world/desync/{frame}_{side}_{name}.bin` so that both sides of a desync can be diffed.
+    pub fn dump(&self, frame: u32, resources: &[String], side: &str) {
+        let snapshot = match self.frames.iter().find(|(f, _)| *f == frame) {
+            Some((_, snapshot)) => snapshot,
+            None => {
+                log::error!(
+                    "resources of tick {} are no longer kept, cannot dump",
+                    frame
+                );
+                return;
+            }
+        };
+
+        if let Err(e) = std::fs::create_dir_all("world/desync") {
+            log::error!("could not create desync dump directory: {}", e);
+            return;
+        }
+
+        let side = sanitize(side);
+        for name in resources {
+            let data = unwrap_cont!(snapshot.get(name));
+            let path = format!("world/desync/{}_{}_{}.bin", frame, side, sanitize(name));
+            match std::fs::write(&path, data) {
+                Ok(_) => log::info!("dumped diverging resource to {}", path),
+                Err(e) => log::error!("could not dump {}: {}", path, e),
+            }
+        }
+    }
+}
+
+/// Client names are chosen by the players, keep them from escaping the dump directory
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
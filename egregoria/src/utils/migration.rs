@@ -0,0 +1,71 @@
+use hecs::{Component, World};
+
+/// Upgrades a save from one version to the next.
+///
+/// Resource migrations transform the bincode blob saved under a `SAVELOAD_FUNCS` name,
+/// returning None drops the resource so it is reinitialized to its default.
+/// World migrations run on the world once hecs deserialized it, so they can only add
+/// components that didn't exist yet to old entities. A component cannot be renamed or have
+/// its fields changed, the old layout would fail to deserialize before any migration runs.
+/// Note that component ids are bincode enum indices: new components must be appended
+/// at the end of the `register!` list for old worlds to deserialize at all.
+pub struct Migration {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub(crate) resources: Vec<(&'static str, fn(Vec<u8>) -> Option<Vec<u8>>)>,
+    pub(crate) world: Vec<fn(&mut World)>,
+}
+
+impl Migration {
+    pub fn new(from: &'static str, to: &'static str) -> Self {
+        Self {
+            from,
+            to,
+            resources: vec![],
+            world: vec![],
+        }
+    }
+
+    pub fn resource(mut self, name: &'static str, f: fn(Vec<u8>) -> Option<Vec<u8>>) -> Self {
+        self.resources.push((name, f));
+        self
+    }
+
+    pub fn world(mut self, f: fn(&mut World)) -> Self {
+        self.world.push(f);
+        self
+    }
+}
+
+/// Finds the chain of migrations going from `version` to `target`
+pub(crate) fn migration_path<'a>(
+    migrations: &'a [Migration],
+    version: &str,
+    target: &str,
+) -> Option<Vec<&'a Migration>> {
+    let mut path = vec![];
+    let mut cur = version;
+    while cur != target {
+        let m = migrations.iter().find(|m| m.from == cur)?;
+        if path.len() > migrations.len() {
+            log::error!("migration cycle detected from {}", version);
+            return None;
+        }
+        path.push(m);
+        cur = m.to;
+    }
+    Some(path)
+}
+
+/// Inserts the component made by `f` into the entities having `M` but not `T` yet
+pub(crate) fn add_missing<M: Component, T: Component>(world: &mut World, f: fn() -> T) {
+    let missing: Vec<_> = world
+        .query_mut::<(&M, Option<&T>)>()
+        .into_iter()
+        .filter(|(_, (_, c))| c.is_none())
+        .map(|(e, _)| e)
+        .collect();
+    for e in missing {
+        let _ = world.insert_one(e, f());
+    }
+}
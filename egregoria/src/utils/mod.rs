@@ -1,4 +1,7 @@
+pub mod desync;
+pub mod migration;
 pub mod par_command_buffer;
 pub mod rand_provider;
+pub mod replay_player;
 pub mod scheduler;
 pub mod time;
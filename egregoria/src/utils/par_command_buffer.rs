@@ -102,7 +102,7 @@ impl ParCommandBuffer {
         deleted.sort_unstable();
 
         for entity in deleted {
-            if goria.world.despawn(entity).is_err() {
+            if !goria.world.contains(entity) {
                 continue;
             }
             goria.write::<Market>().remove(SoulID(entity));
@@ -118,6 +118,7 @@ impl ParCommandBuffer {
             if let Ok(mut v) = goria.world.get::<&mut DispatchKind>(entity) {
                 ComponentDrop::drop(&mut *v, &mut goria.resources, entity);
             }
+            let _ = goria.world.despawn(entity);
         }
 
         let added = std::mem::take(
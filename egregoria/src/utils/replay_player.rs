@@ -0,0 +1,175 @@
+use crate::map::land_value_update_system;
+use crate::map_dynamic::ZoneDemand;
+use crate::transportation::traffic_stats::TrafficStats;
+use crate::utils::scheduler::SeqSchedule;
+use crate::utils::time::Tick;
+use crate::{Egregoria, Replay};
+use common::saveload::{CompressedBincode, Encoder};
+use std::collections::BTreeMap;
+
+/// 5000 ticks is 100 seconds of game time
+pub const DEFAULT_CHECKPOINT_PERIOD: u32 = 5000;
+
+/// A snapshot of the world to seek back to
+struct Checkpoint {
+    /// `CompressedBincode` save of the world
+    save: Vec<u8>,
+    /// Resources that are not part of saves. `LandValue` and `RoutingService` are not kept:
+    /// they only depend on the map and are rebuilt from it when restoring.
+    zone_demand: ZoneDemand,
+    traffic_stats: TrafficStats,
+}
+
+/// Plays a `Replay` back tick by tick.
+/// A `CompressedBincode` snapshot of the world is taken every `checkpoint_period` ticks
+/// so that seeking backward only replays from the closest checkpoint instead of from the start.
+pub struct ReplayPlayer {
+    replay: Replay,
+    goria: Egregoria,
+    schedule: SeqSchedule,
+    /// Index of the next command to apply
+    idx: usize,
+    checkpoints: BTreeMap<Tick, Checkpoint>,
+    checkpoint_period: u32,
+}
+
+impl ReplayPlayer {
+    pub fn new(mut replay: Replay, checkpoint_period: u32) -> Self {
+        replay.commands.sort_by_key(|(t, _)| *t);
+        Self {
+            replay,
+            goria: Egregoria::empty(),
+            schedule: Egregoria::schedule(),
+            idx: 0,
+            checkpoints: Default::default(),
+            checkpoint_period: checkpoint_period.max(1),
+        }
+    }
+
+    pub fn goria(&self) -> &Egregoria {
+        &self.goria
+    }
+
+    pub fn tick(&self) -> Tick {
+        Tick(self.goria.get_tick())
+    }
+
+    /// Tick of the last recorded command
+    pub fn last_command_tick(&self) -> Tick {
+        self.replay
+            .commands
+            .last()
+            .map(|(t, _)| *t)
+            .unwrap_or_default()
+    }
+
+    /// True once every recorded command was applied
+    pub fn is_over(&self) -> bool {
+        self.idx >= self.replay.commands.len()
+    }
+
+    pub fn n_checkpoints(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    /// Simulates one tick, applying the commands recorded for it
+    pub fn advance(&mut self) {
+        let curt = self.tick();
+        if curt.0 > 0
+            && curt.0 % self.checkpoint_period == 0
+            && !self.checkpoints.contains_key(&curt)
+        {
+            match CompressedBincode::encode(&self.goria) {
+                Ok(save) => {
+                    let checkpoint = Checkpoint {
+                        save,
+                        zone_demand: *self.goria.read::<ZoneDemand>(),
+                        traffic_stats: self.goria.read::<TrafficStats>().clone(),
+                    };
+                    self.checkpoints.insert(curt, checkpoint);
+                }
+                Err(e) => log::error!("could not make replay checkpoint at {:?}: {}", curt, e),
+            }
+        }
+
+        let idx_start = self.idx;
+        let commands = &self.replay.commands;
+        while self.idx < commands.len() && commands[self.idx].0 <= curt {
+            self.idx += 1;
+        }
+
+        self.goria.tick(
+            &mut self.schedule,
+            commands[idx_start..self.idx].iter().map(|(_, c)| c),
+        );
+    }
+
+    /// Brings the world to the state it was in at tick `t`.
+    /// Going forward continues from the current state when no checkpoint is closer to `t`.
+    pub fn seek(&mut self, t: Tick) {
+        let cur = self.tick();
+        let closest = self.checkpoints.range(..=t).next_back().map(|(t, _)| *t);
+
+        if t < cur || closest.map_or(false, |c| c > cur) {
+            self.restore(closest);
+        }
+
+        while self.tick() < t {
+            self.advance();
+        }
+    }
+
+    /// Moves `n` ticks forward, or backward when `n` is negative
+    pub fn step(&mut self, n: i64) {
+        let t = (self.tick().0 as i64 + n).clamp(0, u32::MAX as i64);
+        self.seek(Tick(t as u32));
+    }
+
+    /// Applies every remaining recorded command
+    pub fn fast_forward_to_end(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        self.seek(Tick(self.last_command_tick().0 + 1));
+    }
+
+    /// Exchanges the replayed world with `goria` so that it can be displayed from elsewhere.
+    /// It must be swapped back before the player is used again.
+    pub fn swap_world(&mut self, goria: &mut Egregoria) {
+        std::mem::swap(&mut self.goria, goria);
+    }
+
+    /// Stops replaying and returns the world at the current tick so live play can resume from there.
+    /// The commands recorded after the current tick are discarded.
+    pub fn into_live(self) -> Egregoria {
+        self.goria
+    }
+
+    fn restore(&mut self, checkpoint: Option<Tick>) {
+        let t = unwrap_or!(checkpoint, {
+            self.goria = Egregoria::empty();
+            self.idx = 0;
+            return;
+        });
+
+        // Unwrap ok: checkpoint comes from the map keys
+        let checkpoint = self.checkpoints.get(&t).unwrap();
+        match CompressedBincode::decode::<Egregoria>(&checkpoint.save) {
+            Ok(mut goria) => {
+                *goria.write::<ZoneDemand>() = checkpoint.zone_demand;
+                *goria.write::<TrafficStats>() = checkpoint.traffic_stats.clone();
+                let (world, resources) = goria.world_res();
+                land_value_update_system(world, resources);
+
+                self.goria = goria;
+                self.idx = self.replay.commands.partition_point(|(ct, _)| *ct < t);
+            }
+            Err(e) => {
+                log::error!("could not restore replay checkpoint at {:?}: {}", t, e);
+                self.checkpoints.remove(&t);
+                self.goria = Egregoria::empty();
+                self.idx = 0;
+            }
+        }
+    }
+}
@@ -5,6 +5,8 @@ pub const SECONDS_PER_HOUR: i32 = 200;
 pub const HOURS_PER_DAY: i32 = 24;
 pub const SECONDS_PER_DAY: i32 = SECONDS_PER_HOUR * HOURS_PER_DAY;
 pub const TICKS_PER_SECOND: u32 = 50;
+/// Months are short so that the government ledger history is readable at normal speed
+pub const DAYS_PER_MONTH: i32 = 5;
 
 /// The amount of time the game was updated
 /// Used as a resource
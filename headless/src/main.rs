@@ -1,11 +1,16 @@
 use common::logger::MyLog;
 use common::unwrap_or;
 use egregoria::engine_interaction::WorldCommands;
-use egregoria::Egregoria;
-use networking::{Frame, Server, ServerConfiguration, ServerPollResult};
+use egregoria::utils::desync::DesyncSnapshots;
+use egregoria::{Egregoria, EgregoriaOptions};
+use networking::{Frame, Server, ServerConfiguration, ServerPollResult, HASH_CHECK_PERIOD};
+use scenario::Scenario;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
+mod scenario;
+
 const VERSION: &str = include_str!("../../VERSION");
 
 #[derive(StructOpt, Debug)]
@@ -27,17 +32,59 @@ struct Opt {
     /// i.e. 20ms = 50FPS
     #[structopt(long, default_value = "20")]
     timestep: u64,
+
+    /// Run the given scenario file instead of hosting a server.
+    /// Exits with a non-zero code if any of its assertions fail
+    #[structopt(long, parse(from_os_str))]
+    scenario: Option<PathBuf>,
+
+    /// Write the per-lane traffic statistics of the scenario to this CSV file once it ran
+    #[structopt(long, parse(from_os_str))]
+    traffic_csv: Option<PathBuf>,
+
+    /// Level of the traffic statistics to export: 0 is 10 minute bins, up to 3 for 50 hour bins
+    #[structopt(long, default_value = "0")]
+    traffic_level: usize,
+
+    /// Seed of the generated world, overrides the one of the scenario.
+    /// The same seed and options always generate the same world
+    #[structopt(long)]
+    seed: Option<u64>,
 }
 
 fn main() {
     let opt: Opt = Opt::from_args();
     MyLog::init();
 
+    if let Some(path) = opt.scenario {
+        log::info!(
+            "running scenario {} with version: {}",
+            path.display(),
+            VERSION
+        );
+        let mut scenario = unwrap_or!(Scenario::load(&path), std::process::exit(2));
+        if let Some(seed) = opt.seed {
+            scenario.options.seed = seed;
+        }
+        let traffic_csv = opt.traffic_csv.as_deref().map(|p| (p, opt.traffic_level));
+        let failed = scenario.run(traffic_csv);
+        if failed > 0 {
+            log::error!("{} assertion(s) failed", failed);
+            std::process::exit(1);
+        }
+        log::info!("all assertions passed");
+        return;
+    }
+
     log::info!("starting server with version: {}", VERSION);
 
     let mut w = unwrap_or!(Egregoria::load_from_disk("world"), {
         log::info!("savegame not found defaulting to empty");
-        Egregoria::new(true)
+        let mut options = EgregoriaOptions::default();
+        if let Some(seed) = opt.seed {
+            options.seed = seed;
+        }
+        Egregoria::new_with_options(options)
     });
 
     let mut sched = Egregoria::schedule();
@@ -59,6 +106,7 @@ fn main() {
     log::info!("server started!");
 
     let mut last_saved = Instant::now();
+    let mut snapshots = DesyncSnapshots::default();
 
     loop {
         if let ServerPollResult::Input(inputs) = server.poll(&w, Frame(w.get_tick()), None) {
@@ -66,9 +114,17 @@ fn main() {
                 assert_eq!(frame.frame.0, w.get_tick() + 1);
                 let merged: WorldCommands = frame.inputs.into_iter().map(|x| x.inp).collect();
                 w.tick(&mut sched, merged.as_ref());
+
+                if w.get_tick() % HASH_CHECK_PERIOD == 0 {
+                    server.register_hashes(Frame(w.get_tick()), snapshots.record(&w));
+                }
             }
         }
 
+        for d in server.take_desyncs() {
+            snapshots.dump(d.frame.0, &d.resources, "server");
+        }
+
         if last_saved.elapsed().as_secs() > opt.autosave {
             w.save_to_disk("world");
             last_saved = Instant::now();
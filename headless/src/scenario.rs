@@ -0,0 +1,277 @@
+use common::saveload::{Encoder, JSON};
+use egregoria::economy::{EcoStats, Government, ItemHistories, ItemRegistry, Money};
+use egregoria::engine_interaction::WorldCommand;
+use egregoria::souls::goods_company::GoodsCompany;
+use egregoria::souls::human::HumanDecision;
+use egregoria::transportation::traffic_stats::TrafficStats;
+use egregoria::transportation::train::Locomotive;
+use egregoria::transportation::Vehicle;
+use egregoria::utils::time::Tick;
+use egregoria::{Egregoria, EgregoriaOptions};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// A scripted run of the simulation, used to regression-test city layouts without a window.
+/// Commands are applied on the tick they are tagged with, like a `Replay`.
+#[derive(Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub options: EgregoriaOptions,
+    #[serde(default)]
+    pub commands: Vec<(Tick, WorldCommand)>,
+    /// Number of ticks to simulate
+    pub ticks: u32,
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+}
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub enum EntityKind {
+    Human,
+    Vehicle,
+    Train,
+    Company,
+    Road,
+    Intersection,
+    Building,
+    Lot,
+    Tree,
+}
+
+const ENTITY_KINDS: [EntityKind; 9] = [
+    EntityKind::Human,
+    EntityKind::Vehicle,
+    EntityKind::Train,
+    EntityKind::Company,
+    EntityKind::Road,
+    EntityKind::Intersection,
+    EntityKind::Building,
+    EntityKind::Lot,
+    EntityKind::Tree,
+];
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub enum TradeKind {
+    Exports,
+    Imports,
+    Internal,
+}
+
+/// Inclusive bounds, a missing bound is not checked
+#[derive(Deserialize, Debug, Default, Copy, Clone)]
+pub struct Bounds<T> {
+    #[serde(default)]
+    pub min: Option<T>,
+    #[serde(default)]
+    pub max: Option<T>,
+}
+
+impl<T: PartialOrd> Bounds<T> {
+    fn contains(&self, v: &T) -> bool {
+        self.min.as_ref().map_or(true, |min| min <= v)
+            && self.max.as_ref().map_or(true, |max| v <= max)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub enum Assertion {
+    /// Government money, in cents
+    Money(Bounds<Money>),
+    EntityCount {
+        kind: EntityKind,
+        #[serde(flatten)]
+        bounds: Bounds<u64>,
+    },
+    /// Change of the government money since the world was created, in cents
+    MoneyChange(Bounds<Money>),
+    /// Change of the entity count since the world was created, so that the roads built by
+    /// the start commands are not counted
+    EntityCountChange {
+        kind: EntityKind,
+        #[serde(flatten)]
+        bounds: Bounds<i64>,
+    },
+    /// Quantity traded of an item over the whole history of an EcoStats level
+    Traded {
+        kind: TradeKind,
+        item: String,
+        #[serde(default)]
+        level: usize,
+        #[serde(flatten)]
+        bounds: Bounds<u64>,
+    },
+}
+
+/// State of the world before the first tick, that the `*Change` assertions are relative to
+struct Start {
+    money: Money,
+    counts: [u64; ENTITY_KINDS.len()],
+}
+
+impl Start {
+    fn new(goria: &Egregoria) -> Self {
+        Self {
+            money: goria.read::<Government>().money,
+            counts: ENTITY_KINDS.map(|kind| entity_count(goria, kind)),
+        }
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Option<Self> {
+        let data = std::fs::read(path)
+            .map_err(|e| log::error!("could not read scenario {}: {}", path.display(), e))
+            .ok()?;
+        JSON::decode(&data)
+            .map_err(|e| log::error!("could not parse scenario {}: {}", path.display(), e))
+            .ok()
+    }
+
+    /// Runs the scenario as fast as possible and returns the number of failed assertions.
+    /// The traffic statistics of the given level are exported to `traffic_csv` if provided.
+    pub fn run(mut self, traffic_csv: Option<(&Path, usize)>) -> usize {
+        let mut goria = Egregoria::new_with_options(self.options.clone());
+        let mut sched = Egregoria::schedule();
+
+        self.commands.sort_by_key(|(t, _)| *t);
+        let start_state = Start::new(&goria);
+
+        let t = Instant::now();
+        let start = goria.get_tick();
+        let mut idx = 0;
+
+        for i in 0..self.ticks {
+            let curt = Tick(i);
+            let idx_start = idx;
+            while idx < self.commands.len() && self.commands[idx].0 <= curt {
+                idx += 1;
+            }
+            goria.tick(
+                &mut sched,
+                self.commands[idx_start..idx].iter().map(|(_, c)| c),
+            );
+        }
+
+        if idx < self.commands.len() {
+            log::warn!(
+                "{} commands were scheduled after the tick budget and were not applied",
+                self.commands.len() - idx
+            );
+        }
+
+        log::info!(
+            "simulated {} ticks in {}s",
+            goria.get_tick() - start,
+            t.elapsed().as_secs_f32()
+        );
+
+        if let Some((path, level)) = traffic_csv {
+            export_traffic(&goria, path, level);
+        }
+
+        let mut failed = 0;
+        for assertion in &self.assertions {
+            match assertion.check(&goria, &start_state) {
+                Ok(()) => log::info!("[ok] {:?}", assertion),
+                Err(reason) => {
+                    log::error!("[failed] {:?}: {}", assertion, reason);
+                    failed += 1;
+                }
+            }
+        }
+        failed
+    }
+}
+
+impl Assertion {
+    fn check(&self, goria: &Egregoria, start: &Start) -> Result<(), String> {
+        match *self {
+            Assertion::Money(ref bounds) => {
+                let money = goria.read::<Government>().money;
+                if !bounds.contains(&money) {
+                    return Err(format!("money is {}", money));
+                }
+            }
+            Assertion::EntityCount { kind, ref bounds } => {
+                let count = entity_count(goria, kind);
+                if !bounds.contains(&count) {
+                    return Err(format!("count is {}", count));
+                }
+            }
+            Assertion::MoneyChange(ref bounds) => {
+                let change = goria.read::<Government>().money - start.money;
+                if !bounds.contains(&change) {
+                    return Err(format!("money changed by {}", change));
+                }
+            }
+            Assertion::EntityCountChange { kind, ref bounds } => {
+                let change = entity_count(goria, kind) as i64 - start.counts[kind as usize] as i64;
+                if !bounds.contains(&change) {
+                    return Err(format!("count changed by {}", change));
+                }
+            }
+            Assertion::Traded {
+                kind,
+                ref item,
+                level,
+                ref bounds,
+            } => {
+                let id = goria
+                    .read::<ItemRegistry>()
+                    .try_id(item)
+                    .ok_or_else(|| format!("unknown item {}", item))?;
+                let stats = goria.read::<EcoStats>();
+                let histories: &ItemHistories = match kind {
+                    TradeKind::Exports => &stats.exports,
+                    TradeKind::Imports => &stats.imports,
+                    TradeKind::Internal => &stats.internal_trade,
+                };
+                let qty: u64 = histories
+                    .iter_histories(level)
+                    .filter(|(x, _)| *x == id)
+                    .flat_map(|(_, h)| h.past_ring.iter())
+                    .map(|&x| x as u64)
+                    .sum();
+                if !bounds.contains(&qty) {
+                    return Err(format!("traded quantity is {}", qty));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn export_traffic(goria: &Egregoria, path: &Path, level: usize) {
+    let res = File::create(path).and_then(|f| {
+        let mut w = BufWriter::new(f);
+        goria.read::<TrafficStats>().write_csv(level, &mut w)?;
+        w.flush()
+    });
+    match res {
+        Ok(()) => log::info!("traffic statistics written to {}", path.display()),
+        Err(e) => log::error!(
+            "could not write traffic statistics to {}: {}",
+            path.display(),
+            e
+        ),
+    }
+}
+
+fn entity_count(goria: &Egregoria, kind: EntityKind) -> u64 {
+    let w = goria.world();
+    let map = goria.map();
+    (match kind {
+        EntityKind::Human => w.query::<&HumanDecision>().iter().count(),
+        EntityKind::Vehicle => w.query::<&Vehicle>().iter().count(),
+        EntityKind::Train => w.query::<&Locomotive>().iter().count(),
+        EntityKind::Company => w.query::<&GoodsCompany>().iter().count(),
+        EntityKind::Road => map.roads().len(),
+        EntityKind::Intersection => map.intersections().len(),
+        EntityKind::Building => map.buildings().len(),
+        EntityKind::Lot => map.lots().len(),
+        EntityKind::Tree => map.terrain.chunks.values().map(|c| c.trees.len()).sum(),
+    }) as u64
+}
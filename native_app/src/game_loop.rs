@@ -7,7 +7,7 @@ use winit::window::{Fullscreen, Window};
 
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
 use common::History;
-use egregoria::utils::time::GameTime;
+use egregoria::utils::time::{GameTime, Tick};
 use egregoria::Egregoria;
 use geom::{Camera, LinearColor};
 use wgpu_engine::{FrameContext, GfxContext, GuiRenderContext, Tesselator};
@@ -15,6 +15,7 @@ use wgpu_engine::{FrameContext, GfxContext, GuiRenderContext, Tesselator};
 use crate::audio::GameAudio;
 use crate::context::Context;
 use crate::gui::windows::debug::DebugObjs;
+use crate::gui::windows::replay::{ReplayControls, ReplayRequest};
 use crate::gui::windows::settings::Settings;
 use crate::gui::{FollowEntity, Gui, Tool, UiTextures};
 use crate::inputmap::{Bindings, InputAction, InputMap};
@@ -22,7 +23,8 @@ use crate::rendering::egui_wrapper::EguiWrapper;
 use crate::rendering::{CameraHandler3D, InstancedRender, MapRenderOptions, MapRenderer};
 use crate::uiworld::{SaveLoadState, UiWorld};
 use common::saveload::Encoder;
-use egregoria::engine_interaction::{WorldCommand, WorldCommands};
+use egregoria::engine_interaction::{UndoStack, WorldCommand, WorldCommands};
+use egregoria::utils::replay_player::{ReplayPlayer, DEFAULT_CHECKPOINT_PERIOD};
 use egregoria::utils::scheduler::SeqSchedule;
 
 pub(crate) const VERSION: &str = include_str!("../../VERSION");
@@ -34,6 +36,10 @@ pub(crate) struct State {
 
     pub(crate) game_schedule: SeqSchedule,
 
+    /// Replay being watched instead of the live world.
+    /// While watching, the player holds the live world and the replayed one is displayed.
+    replay: Option<ReplayPlayer>,
+
     pub(crate) camera: CameraHandler3D,
 
     egui_render: EguiWrapper,
@@ -87,6 +93,7 @@ impl State {
         Self {
             uiw: uiworld,
             game_schedule,
+            replay: None,
             camera,
             egui_render,
             instanced_renderer: InstancedRender::new(&mut ctx.gfx),
@@ -126,7 +133,11 @@ impl State {
             self.reset();
         }
 
-        crate::network::goria_update(self);
+        if self.replay.is_some() {
+            self.replay_update();
+        } else {
+            crate::network::goria_update(self);
+        }
 
         self.uiw.write::<Timings>().all.add_value(ctx.delta as f32);
         self.uiw.write::<Timings>().per_game_system = self.game_schedule.times();
@@ -156,6 +167,53 @@ impl State {
         self.camera.update(ctx);
     }
 
+    /// Moves the watched replay as asked by the replay window, the world stays still otherwise
+    fn replay_update(&mut self) {
+        let mut goria = unwrap_orr!(self.goria.try_write(), return);
+        let player = unwrap_ret!(self.replay.as_mut());
+
+        // The replayed world cannot be edited
+        *self.uiw.write::<WorldCommands>() = WorldCommands::default();
+
+        let mut controls = self.uiw.write::<ReplayControls>();
+        let request = controls.request.take();
+        let mut stop_watching = false;
+        match request {
+            Some(ReplayRequest::Step(n)) => {
+                player.swap_world(&mut goria);
+                player.step(n);
+                player.swap_world(&mut goria);
+            }
+            Some(ReplayRequest::Seek(t)) => {
+                player.swap_world(&mut goria);
+                player.seek(t);
+                player.swap_world(&mut goria);
+            }
+            Some(ReplayRequest::Live) => {
+                // The undo entries were made in the world that is left behind
+                *self.uiw.write::<UndoStack>() = UndoStack::default();
+                stop_watching = true;
+            }
+            Some(ReplayRequest::Stop) => {
+                player.swap_world(&mut goria);
+                stop_watching = true;
+            }
+            None => {}
+        }
+
+        controls.watching =
+            (!stop_watching).then(|| (Tick(goria.get_tick()), player.last_command_tick()));
+        drop(controls);
+        drop(goria);
+
+        if stop_watching {
+            self.replay = None;
+        }
+        if request.is_some() {
+            self.reset();
+        }
+    }
+
     pub(crate) fn reset(&mut self) {
         self.road_renderer.reset();
         self.road_renderer.terrain_dirt_id = 0;
@@ -261,12 +319,31 @@ impl State {
         }
 
         // We might be saving egregoria, wait for it to finish
+        let mut watch_started = false;
         if let Ok(mut goria) = self.goria.try_write() {
             if let Some(replay) = slstate.please_load.take() {
                 log::info!("loading replay");
                 let newgoria = Egregoria::from_replay(replay);
                 *goria = newgoria;
+                self.replay = None;
             }
+            if let Some(replay) = slstate.please_watch.take() {
+                log::info!("watching replay");
+                // Give the live world back to the player being replaced first
+                if let Some(mut old) = self.replay.take() {
+                    old.swap_world(&mut goria);
+                }
+                let mut player = ReplayPlayer::new(replay, DEFAULT_CHECKPOINT_PERIOD);
+                player.seek(Tick(1));
+                player.swap_world(&mut goria);
+                self.replay = Some(player);
+                watch_started = true;
+            }
+        }
+        drop(slstate);
+
+        if watch_started {
+            self.reset();
         }
     }
 
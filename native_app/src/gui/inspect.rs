@@ -1,15 +1,18 @@
 use crate::gui::follow::FollowEntity;
 use crate::uiworld::UiWorld;
-use egregoria::economy::{ItemRegistry, Market, Workers};
-use egregoria::map_dynamic::{DispatchKind, Itinerary, Router};
+use egregoria::economy::{Account, ItemRegistry, Market, Money, Workers};
+use egregoria::map::LandValue;
+use egregoria::map_dynamic::{BuildingInfos, DispatchKind, Itinerary, Router};
 use egregoria::physics::{Collider, CollisionWorld, PhysicsObject, Speed};
-use egregoria::souls::desire::{BuyFood, Home, Work};
+use egregoria::souls::desire::{Desires, Home, Work};
 use egregoria::souls::goods_company::GoodsCompany;
 use egregoria::souls::human::HumanDecision;
+use egregoria::souls::population::Household;
 use egregoria::transportation::{Location, Pedestrian, Vehicle, VehicleID};
 use egregoria::{Egregoria, SoulID};
 
 use egregoria::souls::fret_station::FreightStation;
+use egregoria::souls::train_station::TrainStation;
 use egregoria::transportation::train::{Locomotive, LocomotiveReservation};
 use egui::{Color32, RichText, Ui};
 use egui_inspect::{Inspect, InspectArgs};
@@ -72,15 +75,34 @@ impl InspectRenderer {
         self.inspect_component::<Router>(goria, ui);
         self.inspect_component::<HumanDecision>(goria, ui);
         self.inspect_component::<FreightStation>(goria, ui);
+        self.inspect_component::<TrainStation>(goria, ui);
         self.inspect_component::<Workers>(goria, ui);
         self.inspect_component::<Work>(goria, ui);
         self.inspect_component::<Home>(goria, ui);
-        self.inspect_component::<BuyFood>(goria, ui);
+        self.inspect_component::<Desires>(goria, ui);
+        self.inspect_component::<Household>(goria, ui);
         self.inspect_component::<GoodsCompany>(goria, ui);
         self.inspect_component::<Locomotive>(goria, ui);
         self.inspect_component::<LocomotiveReservation>(goria, ui);
         self.inspect_component::<DispatchKind>(goria, ui);
 
+        if let Some(account) = goria.comp::<Account>(self.entity) {
+            ui.label(format!("Money: {}", account.money));
+            if account.debt.total() > Money::ZERO {
+                ui.label(format!("Debt: {}", account.debt.total()));
+            }
+        }
+
+        let owned = goria
+            .read::<BuildingInfos>()
+            .building_owned_by(SoulID(self.entity));
+        if let Some(b) = owned {
+            if let Some(building) = goria.map().buildings().get(b) {
+                let value = goria.read::<LandValue>().building(building);
+                ui.label(format!("Land value: {:.1}", value));
+            }
+        }
+
         if goria.comp::<Vehicle>(self.entity).is_some() {
             for (e, loc) in goria.world().query::<&Location>().iter() {
                 let loc: &Location = loc;
@@ -19,6 +19,7 @@ pub(crate) fn inspected_aura(goria: &Egregoria, uiworld: &mut UiWorld) {
             match *loc {
                 Location::Outside => {}
                 Location::Vehicle(v) => pos = goria.pos(v.0),
+                Location::Train(t) => pos = goria.pos(t.0),
                 Location::Building(b) => pos = map.buildings().get(b).map(|b| b.door_pos),
             }
         }
@@ -21,6 +21,7 @@ pub(crate) mod roadeditor;
 pub(crate) mod selectable;
 pub(crate) mod specialbuilding;
 pub(crate) mod topgui;
+pub(crate) mod undo;
 
 pub(crate) mod addtrain;
 pub(crate) mod windows;
@@ -39,6 +40,7 @@ pub(crate) fn run_ui_systems(goria: &Egregoria, uiworld: &mut UiWorld) {
     specialbuilding::specialbuilding(goria, uiworld);
     addtrain::addtrain(goria, uiworld);
     zoneedit::zoneedit(goria, uiworld);
+    undo::undo(uiworld);
 
     // run last so other systems can have the chance to cancel select
     selectable::selectable(goria, uiworld);
@@ -6,6 +6,7 @@ use common::AudioKind;
 use egregoria::engine_interaction::{WorldCommand, WorldCommands};
 use egregoria::map::{
     Intersection, LanePatternBuilder, Map, MapProject, ProjectFilter, ProjectKind, PylonPosition,
+    RoadSegmentKind,
 };
 use egregoria::Egregoria;
 use geom::{BoldLine, BoldSpline, Camera, PolyLine, ShapeEnum, Spline};
@@ -168,9 +169,17 @@ pub(crate) fn roadbuild(goria: &Egregoria, uiworld: &mut UiWorld) {
                 patwidth * 0.5,
             );
 
+            let points = egregoria::map::Road::segment_points(
+                selected_proj.pos,
+                cur_proj.pos,
+                RoadSegmentKind::Straight,
+                is_rail,
+            );
+
             compatible(map, cur_proj, selected_proj)
                 && check_angle(map, selected_proj, cur_proj.pos.xy(), is_rail)
                 && check_angle(map, cur_proj, selected_proj.pos.xy(), is_rail)
+                && map.terrain.can_cross_water(&points)
                 && !check_intersect(
                     map,
                     &ShapeEnum::BoldLine(sp),
@@ -188,10 +197,18 @@ pub(crate) fn roadbuild(goria: &Egregoria, uiworld: &mut UiWorld) {
                 to_derivative: (cur_proj.pos.xy() - interpoint) * std::f32::consts::FRAC_1_SQRT_2,
             };
 
+            let points = egregoria::map::Road::segment_points(
+                selected_proj.pos,
+                cur_proj.pos,
+                RoadSegmentKind::from_elbow(selected_proj.pos.xy(), cur_proj.pos.xy(), interpoint),
+                is_rail,
+            );
+
             compatible(map, cur_proj, selected_proj)
                 && check_angle(map, selected_proj, interpoint, is_rail)
                 && check_angle(map, cur_proj, interpoint, is_rail)
                 && !sp.is_steep(state.pattern_builder.width())
+                && map.terrain.can_cross_water(&points)
                 && !check_intersect(
                     map,
                     &ShapeEnum::BoldSpline(BoldSpline::new(sp, patwidth * 0.5)),
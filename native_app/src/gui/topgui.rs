@@ -260,27 +260,49 @@ impl Gui {
                         *uiworld.write::<Tool>() = Tool::Train;
                     }
 
-                    /*
-                    if ui.button_with_size("Trainstation", [rbw, 30.0]) {
+                    let mut trainstation = RichText::new("Train station");
+                    if *uiworld.read::<Tool>() == Tool::SpecialBuilding {
+                        trainstation = trainstation.strong();
+                    };
+                    if ui.button(trainstation).clicked() {
                         *uiworld.write::<Tool>() = Tool::SpecialBuilding;
 
-                        let h = LanePatternBuilder::new().rail(true).n_lanes(1).width();
+                        let railw = LanePatternBuilder::new().rail(true).n_lanes(1).width();
 
                         uiworld.write::<SpecialBuildingResource>().opt = Some(SpecialBuildKind {
-                            make: Box::new(move |args, commands| {
-                                let d = args.obb.axis()[0].z(0.0) * 0.5;
-                                let off = args.obb.axis()[1].z(0.0).normalize_to(h * 0.5 + 10.0);
-                                commands.map_build_trainstation(
-                                    args.mpos - d - off,
-                                    args.mpos + d - off,
-                                );
+                            make: Box::new(move |args| {
+                                let obb = args.obb;
+                                let c = obb.center().z(args.mpos.z + 0.3);
+
+                                let [axisx, axisy] = obb.axis().map(|x| x.z(0.0));
+                                let d = axisx * 0.5;
+                                let off = axisy.normalize_to(railw * 0.5 + 10.0);
+
+                                let pat = LanePatternBuilder::new().rail(true).n_lanes(1).build();
+
+                                vec![
+                                    WorldCommand::MapMakeConnection {
+                                        from: MapProject::ground(c - d - off),
+                                        to: MapProject::ground(c + d - off),
+                                        inter: None,
+                                        pat,
+                                    },
+                                    WorldCommand::MapBuildSpecialBuilding {
+                                        pos: args.obb,
+                                        kind: BuildingKind::TrainStation,
+                                        gen: BuildingGen::NoWalkway {
+                                            door_pos: Vec2::ZERO,
+                                        },
+                                        zone: None,
+                                    },
+                                ]
                             }),
-                            w: h + 15.0,
+                            w: railw + 15.0,
                             h: 230.0,
                             asset: "trainstation.glb".to_string(),
                             road_snap: false,
                         });
-                    }*/
+                    }
 
                     let mut freightstation = RichText::new("Freight station");
                     if *uiworld.read::<Tool>() == Tool::SpecialBuilding {
@@ -442,7 +464,12 @@ impl Gui {
                 });
         }
 
-        let brushes = [("Residential", LotKind::Residential)];
+        let brushes = [
+            ("Residential", LotKind::Residential),
+            ("Commercial", LotKind::Commercial),
+            ("Industrial", LotKind::Industrial),
+            ("Unzone", LotKind::Unassigned),
+        ];
 
         if matches!(*uiworld.read::<Tab>(), Tab::Lotbrush) {
             let lbw = 120.0;
@@ -0,0 +1,23 @@
+use crate::inputmap::{InputAction, InputMap};
+use crate::uiworld::UiWorld;
+use egregoria::engine_interaction::UndoStack;
+
+/// Sends the last undone or redone command through the usual command pipeline
+/// so that it stays deterministic in multiplayer
+#[profiling::function]
+pub(crate) fn undo(uiworld: &mut UiWorld) {
+    let inp = uiworld.read::<InputMap>();
+    let mut stack = uiworld.write::<UndoStack>();
+
+    let command = if inp.just_act.contains(&InputAction::Redo) {
+        stack.redo()
+    } else if inp.just_act.contains(&InputAction::Undo) {
+        stack.undo()
+    } else {
+        None
+    };
+
+    if let Some(command) = command {
+        uiworld.commands().push(command);
+    }
+}
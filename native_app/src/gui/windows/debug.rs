@@ -10,11 +10,14 @@ use egregoria::utils::time::{GameTime, SECONDS_PER_DAY};
 use egregoria::Egregoria;
 
 use crate::inputmap::InputMap;
+use egregoria::map::procgen::{load_osm_file, OsmOptions};
 use egregoria::map::{IntersectionID, Map, RoadSegmentKind, TraverseKind};
+use egregoria::transportation::traffic_stats::TrafficStats;
 use egregoria::transportation::train::TrainReservations;
 use egregoria::transportation::{Pedestrian, Vehicle};
 use egui::Widget;
 use geom::{Camera, Color, LinearColor, Spline3, Vec2};
+use std::path::Path;
 use wgpu_engine::Tesselator;
 
 #[derive(Default)]
@@ -42,6 +45,7 @@ impl Default for DebugObjs {
             (false, "Debug lots", debug_lots),
             (false, "Debug road points", debug_road_points),
             (false, "Debug parking", debug_parking),
+            (false, "Debug traffic congestion", debug_congestion),
         ])
     }
 }
@@ -61,6 +65,36 @@ impl Default for TestFieldProperties {
     }
 }
 
+#[derive(Clone)]
+struct CityGenProperties {
+    size: f32,
+    seed: u64,
+}
+
+impl Default for CityGenProperties {
+    fn default() -> Self {
+        Self {
+            size: 1500.0,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct OsmImportProperties {
+    path: String,
+    buildings: bool,
+}
+
+impl Default for OsmImportProperties {
+    fn default() -> Self {
+        Self {
+            path: "assets/extract.osm".to_string(),
+            buildings: true,
+        }
+    }
+}
+
 pub(crate) fn debug(
     window: egui::Window<'_>,
     ui: &egui::Context,
@@ -69,6 +103,8 @@ pub(crate) fn debug(
 ) {
     window.show(ui, |ui| {
         uiworld.check_present(TestFieldProperties::default);
+        uiworld.check_present(CityGenProperties::default);
+        uiworld.check_present(OsmImportProperties::default);
 
         let mut objs = uiworld.write::<DebugObjs>();
         for (val, name, _) in &mut objs.0 {
@@ -135,6 +171,26 @@ pub(crate) fn debug(
             uiworld.commands().map_load_paris();
         }
         ui.separator();
+        let mut osm = uiworld.write::<OsmImportProperties>();
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut osm.path);
+            ui.label("osm path");
+        });
+        ui.checkbox(&mut osm.buildings, "import buildings");
+
+        if ui.small_button("load OSM extract").clicked() {
+            let opts = OsmOptions {
+                pos: uiworld.read::<Camera>().pos.xy(),
+                buildings: osm.buildings,
+                ..Default::default()
+            };
+            match load_osm_file(Path::new(&osm.path), &opts) {
+                Ok(data) => uiworld.commands().map_load_osm(data),
+                Err(e) => log::error!("could not load {}: {}", osm.path, e),
+            }
+        }
+        ui.separator();
         let mut state = uiworld.write::<TestFieldProperties>();
 
         ui.horizontal(|ui| {
@@ -158,6 +214,28 @@ pub(crate) fn debug(
                 state.spacing,
             );
         }
+        ui.separator();
+        let mut city = uiworld.write::<CityGenProperties>();
+
+        ui.horizontal(|ui| {
+            egui::DragValue::new(&mut city.size)
+                .clamp_range(300.0..=5000.0f32)
+                .ui(ui);
+            ui.label("city size");
+        });
+
+        ui.horizontal(|ui| {
+            egui::DragValue::new(&mut city.seed).ui(ui);
+            ui.label("seed");
+        });
+
+        if ui.small_button("generate city").clicked() {
+            uiworld.commands().map_generate_city(
+                uiworld.read::<Camera>().pos.xy(),
+                city.size,
+                city.seed,
+            );
+        }
 
         if matches!(
             *uiworld.read::<NetworkState>(),
@@ -410,6 +488,38 @@ pub(crate) fn debug_parking(tess: &mut Tesselator, goria: &Egregoria, _: &UiWorl
     Some(())
 }
 
+/// Level 0 bins averaged by the congestion overlay, about 10 in-game minutes.
+/// The current bin may have just started, the older ones keep the overlay from flickering
+const CONGESTION_BINS: usize = 7;
+
+/// Colors the lanes from green to red depending on the average speed of the last 10 minutes
+pub(crate) fn debug_congestion(
+    tess: &mut Tesselator,
+    goria: &Egregoria,
+    _: &UiWorld,
+) -> Option<()> {
+    let map: &Map = &goria.map();
+    let stats = goria.read::<TrafficStats>();
+
+    for (id, lstats) in stats.iter_lanes(0, CONGESTION_BINS) {
+        let lane = unwrap_cont!(map.lanes().get(id));
+        let c = lstats.congestion(lane.speed_limit);
+        tess.set_color((1.0 - c) * LinearColor::GREEN + c * LinearColor::RED);
+        tess.draw_polyline(
+            &lane
+                .points
+                .as_slice()
+                .iter()
+                .map(|x| x.up(0.02))
+                .collect::<Vec<_>>(),
+            1.0,
+            false,
+        );
+    }
+
+    Some(())
+}
+
 pub(crate) fn debug_trainreservations(
     tess: &mut Tesselator,
     goria: &Egregoria,
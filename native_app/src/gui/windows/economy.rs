@@ -1,20 +1,23 @@
 use crate::uiworld::UiWorld;
 use common::timestep::UP_DT;
 use egregoria::economy::{
-    EcoStats, ItemHistories, ItemRegistry, Market, HISTORY_SIZE, LEVEL_FREQS, LEVEL_NAMES,
+    Account, CompanyProfit, EcoStats, ItemHistories, ItemRegistry, Market, Money, HISTORY_SIZE,
+    LEVEL_FREQS, LEVEL_NAMES,
 };
+use egregoria::souls::goods_company::{GoodsCompany, GoodsCompanyRegistry};
 use egregoria::Egregoria;
 use egui::plot::{Line, PlotPoints};
 use egui::{Align2, Color32, Ui};
 use geom::Color;
 use slotmap::Key;
 use std::cmp::Reverse;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 enum EconomyTab {
     ImportExports,
     InternalTrade,
     MarketPrices,
+    Companies,
 }
 
 struct EconomyState {
@@ -68,6 +71,12 @@ pub(crate) fn economy(
                 {
                     state.tab = EconomyTab::MarketPrices;
                 }
+                if ui
+                    .selectable_label(matches!(state.tab, EconomyTab::Companies), "Companies")
+                    .clicked()
+                {
+                    state.tab = EconomyTab::Companies;
+                }
             });
 
             ui.horizontal(|ui| {
@@ -222,6 +231,11 @@ pub(crate) fn economy(
                         render_market_prices(goria, ui);
                     });
                 }
+                EconomyTab::Companies => {
+                    ui.push_id(4, |ui| {
+                        render_companies(goria, ui);
+                    });
+                }
             }
             ui.allocate_space(ui.available_size());
         });
@@ -238,3 +252,54 @@ fn render_market_prices(goria: &Egregoria, ui: &mut Ui) {
         }
     });
 }
+
+/// Companies grouped by kind, the least profitable first
+fn render_companies(goria: &Egregoria, ui: &mut Ui) {
+    let registry = goria.read::<GoodsCompanyRegistry>();
+    let map = goria.map();
+
+    let mut kinds: BTreeMap<_, (u32, Money, Money)> = BTreeMap::new();
+    for (_, (company, account, profit)) in goria
+        .world()
+        .query::<(&GoodsCompany, &Account, &CompanyProfit)>()
+        .iter()
+    {
+        let kind = unwrap_cont!(map
+            .buildings()
+            .get(company.building)
+            .and_then(|b| b.kind.as_goods_company()));
+        let v = kinds.entry(kind).or_default();
+        v.0 += 1;
+        v.1 += account.money;
+        v.2 += profit.last_profit();
+    }
+    let mut kinds: Vec<_> = kinds.into_iter().collect();
+    kinds.sort_by_key(|(_, (_, _, profit))| *profit);
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("companies").striped(true).show(ui, |ui| {
+            ui.label("Company");
+            ui.label("Count");
+            ui.label("Money");
+            ui.label("Profit yesterday");
+            ui.end_row();
+
+            for (kind, (count, money, profit)) in kinds {
+                let name = registry
+                    .descriptions
+                    .get(kind)
+                    .map_or("?", |d| d.name.as_str());
+                ui.label(name);
+                ui.label(count.to_string());
+                ui.label(money.to_string());
+                let color = if profit < Money::ZERO {
+                    Color32::RED
+                } else {
+                    Color32::GREEN
+                };
+                ui.colored_label(color, profit.to_string());
+                ui.end_row();
+            }
+        });
+    });
+}
@@ -0,0 +1,107 @@
+use crate::uiworld::UiWorld;
+use egregoria::economy::{Government, LedgerCategory, Money, GOVERNMENT_CREDIT_LIMIT};
+use egregoria::utils::time::DAYS_PER_MONTH;
+use egregoria::Egregoria;
+use egui::{Button, Color32, Grid, Slider};
+
+/// Number of months shown in the ledger table
+const SHOWN_MONTHS: usize = 6;
+/// Amount borrowed or repaid per click
+const LOAN_STEP: Money = Money::new_base(10_000);
+
+pub(crate) fn finances(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiw: &mut UiWorld,
+    goria: &Egregoria,
+) {
+    let gvt = goria.read::<Government>();
+
+    window
+        .default_size([500.0, 400.0])
+        .vscroll(true)
+        .show(ui, |ui| {
+            ui.label(format!("Money: {}", gvt.money));
+            if gvt.is_bankrupt() {
+                ui.colored_label(Color32::RED, "Bankrupt: no new construction until solvent");
+            }
+
+            ui.separator();
+            ui.label(format!(
+                "Debt: {} / {} (interest: {} per day)",
+                gvt.debt.total(),
+                GOVERNMENT_CREDIT_LIMIT,
+                gvt.debt.daily_interest()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button(format!("Borrow {}", LOAN_STEP)).clicked() {
+                    uiw.commands().government_borrow(LOAN_STEP);
+                }
+                let can_repay = !gvt.debt.loans.is_empty() && gvt.money > Money::ZERO;
+                if ui
+                    .add_enabled(can_repay, Button::new(format!("Repay {}", LOAN_STEP)))
+                    .clicked()
+                {
+                    uiw.commands().government_repay(LOAN_STEP);
+                }
+            });
+
+            ui.separator();
+            ui.label("Taxes");
+            let mut taxes = gvt.taxes;
+            let mut income = taxes.income * 100.0;
+            let mut sales = taxes.sales * 100.0;
+            let mut property = taxes.property.cents() / 100;
+            let mut changed = false;
+            changed |= ui
+                .add(Slider::new(&mut income, 0.0..=50.0).text("Income tax (%)"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut sales, 0.0..=30.0).text("Sales tax (%)"))
+                .changed();
+            changed |= ui
+                .add(Slider::new(&mut property, 0..=200).text("Property tax ($ per day)"))
+                .changed();
+            if changed {
+                taxes.income = income / 100.0;
+                taxes.sales = sales / 100.0;
+                taxes.property = Money::new_base(property);
+                uiw.commands().set_tax_rates(taxes);
+            }
+
+            ui.separator();
+            ui.label(format!("Ledger ({} days per month)", DAYS_PER_MONTH));
+            let months: Vec<_> = gvt.ledger.months().take(SHOWN_MONTHS).collect();
+            Grid::new("ledger").striped(true).show(ui, |ui| {
+                ui.label("");
+                for m in &months {
+                    ui.label(format!("Month {}", m.month + 1));
+                }
+                ui.end_row();
+
+                for cat in LedgerCategory::ALL {
+                    ui.label(cat.name());
+                    for m in &months {
+                        ui.label(m.get(cat).to_string());
+                    }
+                    ui.end_row();
+                }
+
+                ui.label("Income");
+                for m in &months {
+                    ui.label(m.income().to_string());
+                }
+                ui.end_row();
+                ui.label("Expenses");
+                for m in &months {
+                    ui.label(m.expenses().to_string());
+                }
+                ui.end_row();
+                ui.label("Net");
+                for m in &months {
+                    ui.label(m.net().to_string());
+                }
+                ui.end_row();
+            });
+        });
+}
@@ -48,6 +48,15 @@ pub(crate) fn load(window: egui::Window<'_>, ui: &egui::Context, uiw: &mut UiWor
                     uiw.write::<SaveLoadState>().please_load = replay;
                 }
             }
+            if ui.button("Watch world/world_replay.json").clicked() {
+                let replay = Egregoria::load_replay_from_disk("world");
+
+                if replay.is_none() {
+                    lstate.load_fail = "Failed to load replay".to_string();
+                } else {
+                    uiw.write::<SaveLoadState>().please_watch = replay;
+                }
+            }
         } else {
             ui.label("No replay found in world/world_replay.json");
         }
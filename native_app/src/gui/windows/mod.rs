@@ -8,9 +8,12 @@ use egregoria::Egregoria;
 mod config;
 pub(crate) mod debug;
 mod economy;
+mod finances;
 pub(crate) mod load;
 #[cfg(feature = "multiplayer")]
 pub(crate) mod network;
+mod population;
+pub(crate) mod replay;
 pub(crate) mod settings;
 
 pub(crate) trait GUIWindow: Send + Sync {
@@ -58,12 +61,15 @@ impl Default for GUIWindows {
             opened: vec![],
         };
         s.insert("Economy", economy::economy, false);
+        s.insert("Finances", finances::finances, false);
+        s.insert("Population", population::population, false);
         s.insert("Config", config::config, false);
         s.insert("Debug", debug::debug, false);
         s.insert("Settings", settings::settings, false);
         #[cfg(feature = "multiplayer")]
         s.insert("Network", network::network, false);
         s.insert("Load", load::load, false);
+        s.insert("Replay", replay::replay, false);
         s
     }
 }
@@ -0,0 +1,87 @@
+use crate::uiworld::UiWorld;
+use egregoria::map_dynamic::ZoneDemand;
+use egregoria::souls::desire::Work;
+use egregoria::souls::population::{Household, PopulationStats, ADULT_AGE};
+use egregoria::Egregoria;
+use egui::Grid;
+
+/// Residents of this age and older are counted as seniors
+const SENIOR_AGE: u32 = 65;
+
+pub(crate) fn population(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    _: &mut UiWorld,
+    goria: &Egregoria,
+) {
+    let mut households = 0;
+    let mut unemployed = 0;
+    let mut ages = [0; 3];
+    for (_, (household, work)) in goria.world().query::<(&Household, Option<&Work>)>().iter() {
+        households += 1;
+        if work.is_none() {
+            unemployed += 1;
+        }
+        for m in &household.members {
+            match m.age {
+                a if a < ADULT_AGE => ages[0] += 1,
+                a if a < SENIOR_AGE => ages[1] += 1,
+                _ => ages[2] += 1,
+            }
+        }
+    }
+    let [children, adults, seniors] = ages;
+    let stats = goria.read::<PopulationStats>();
+    let demand = *goria.read::<ZoneDemand>();
+
+    window.default_size([250.0, 300.0]).show(ui, |ui| {
+        ui.label(format!("Residents: {}", children + adults + seniors));
+        ui.label(format!("Households: {}", households));
+        ui.label(format!("Unemployed households: {}", unemployed));
+
+        ui.separator();
+        Grid::new("ages").show(ui, |ui| {
+            ui.label("Children");
+            ui.label(children.to_string());
+            ui.end_row();
+            ui.label("Adults");
+            ui.label(adults.to_string());
+            ui.end_row();
+            ui.label("Seniors");
+            ui.label(seniors.to_string());
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.label("Yesterday");
+        let y = &stats.yesterday;
+        Grid::new("population_change").show(ui, |ui| {
+            ui.label("Births");
+            ui.label(y.births.to_string());
+            ui.end_row();
+            ui.label("Deaths");
+            ui.label(y.deaths.to_string());
+            ui.end_row();
+            ui.label("Households moving in");
+            ui.label(y.immigrants.to_string());
+            ui.end_row();
+            ui.label("Households leaving");
+            ui.label(y.emigrants.to_string());
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.label("Zone demand");
+        Grid::new("zone_demand").show(ui, |ui| {
+            ui.label("Residential");
+            ui.label(demand.residential.to_string());
+            ui.end_row();
+            ui.label("Commercial");
+            ui.label(demand.commercial.to_string());
+            ui.end_row();
+            ui.label("Industrial");
+            ui.label(demand.industrial.to_string());
+            ui.end_row();
+        });
+    });
+}
@@ -0,0 +1,69 @@
+use crate::uiworld::UiWorld;
+use egregoria::utils::time::Tick;
+use egregoria::Egregoria;
+
+/// What the replay window shows and what it asks the game loop to do with the replay being watched
+#[derive(Default)]
+pub(crate) struct ReplayControls {
+    /// Current tick and tick of the last recorded command, None when no replay is being watched
+    pub(crate) watching: Option<(Tick, Tick)>,
+    target: u32,
+    pub(crate) request: Option<ReplayRequest>,
+}
+
+pub(crate) enum ReplayRequest {
+    /// Moves that many ticks, backward when negative
+    Step(i64),
+    Seek(Tick),
+    /// Stops watching and plays on from the current tick
+    Live,
+    /// Stops watching and goes back to the world from before watching
+    Stop,
+}
+
+pub(crate) fn replay(
+    window: egui::Window<'_>,
+    ui: &egui::Context,
+    uiw: &mut UiWorld,
+    _: &Egregoria,
+) {
+    window.show(ui, |ui| {
+        let mut controls = uiw.write::<ReplayControls>();
+        let (cur, last) = match controls.watching {
+            Some(x) => x,
+            None => {
+                ui.label("No replay is being watched, watch one from the Load window");
+                return;
+            }
+        };
+
+        ui.label(format!("Tick {} / {}", cur.0, last.0));
+
+        ui.horizontal(|ui| {
+            for n in [-1000, -100, -1, 1, 100, 1000] {
+                let label = if n > 0 {
+                    format!("+{}", n)
+                } else {
+                    n.to_string()
+                };
+                if ui.button(label).clicked() {
+                    controls.request = Some(ReplayRequest::Step(n));
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut controls.target).clamp_range(0..=last.0));
+            if ui.button("Seek").clicked() {
+                controls.request = Some(ReplayRequest::Seek(Tick(controls.target)));
+            }
+        });
+
+        if ui.button("Resume live from here").clicked() {
+            controls.request = Some(ReplayRequest::Live);
+        }
+        if ui.button("Stop watching").clicked() {
+            controls.request = Some(ReplayRequest::Stop);
+        }
+    });
+}
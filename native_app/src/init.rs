@@ -13,7 +13,7 @@ use crate::network::NetworkState;
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
 use crate::uiworld::{ReceivedCommands, UiWorld};
 use common::saveload::Encoder;
-use egregoria::engine_interaction::WorldCommands;
+use egregoria::engine_interaction::{UndoStack, WorldCommands};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -24,6 +24,8 @@ pub(crate) fn init() {
     register_resource::<Settings>("settings");
     #[cfg(feature = "multiplayer")]
     register_resource::<crate::gui::windows::network::NetworkConnectionInfo>("netinfo");
+    #[cfg(feature = "multiplayer")]
+    register_resource_noserialize::<egregoria::utils::desync::DesyncSnapshots>();
     register_resource::<LotBrushResource>("lot_brush");
     register_resource::<Bindings>("bindings");
 
@@ -47,7 +49,9 @@ pub(crate) fn init() {
     register_resource_noserialize::<Timings>();
     register_resource_noserialize::<Tool>();
     register_resource_noserialize::<WorldCommands>();
+    register_resource_noserialize::<UndoStack>();
     register_resource_noserialize::<crate::gui::windows::load::LoadState>();
+    register_resource_noserialize::<crate::gui::windows::replay::ReplayControls>();
     register_resource_noserialize::<crate::uiworld::SaveLoadState>();
 }
 
@@ -38,6 +38,8 @@ pub(crate) enum InputAction {
     DownElevation,
     OpenEconomyMenu,
     PausePlay,
+    Undo,
+    Redo,
 }
 
 // All unit inputs need to match
@@ -97,6 +99,8 @@ impl Default for Bindings {
             (DownElevation,   ics![Key(K::LControl), WheelDown]),
             (OpenEconomyMenu, ics![Key(K::E)]),
             (PausePlay,       ics![Key(K::Space)]),
+            (Undo,            ics![Key(K::LControl), Key(K::Z)]),
+            (Redo,            ics![Key(K::LControl), Key(K::Y) ; Key(K::LControl), Key(K::LShift), Key(K::Z)]),
         ] {
             if m.insert(k, v).is_some() {
                 log::error!("inserting same action twice!");
@@ -318,6 +322,8 @@ impl Display for InputAction {
                 InputAction::DownElevation => "Down Elevation",
                 InputAction::OpenEconomyMenu => "Economy Menu",
                 InputAction::PausePlay => "Pause/Play",
+                InputAction::Undo => "Undo",
+                InputAction::Redo => "Redo",
             }
         )
     }
@@ -1,5 +1,8 @@
 pub(crate) use self::inner::*;
+use crate::uiworld::UiWorld;
 use common::timestep::Timestep;
+use egregoria::engine_interaction::{CommandInverses, UndoStack, WorldCommand};
+use egregoria::Egregoria;
 
 impl Default for NetworkState {
     fn default() -> Self {
@@ -7,8 +10,28 @@ impl Default for NetworkState {
     }
 }
 
+/// Feeds the undo stack with the undo history entries of the commands applied during the last tick.
+/// `applied` must yield every applied command in order, along with whether it was sent by us.
+fn record_inverses<'a>(
+    uiw: &UiWorld,
+    goria: &Egregoria,
+    applied: impl Iterator<Item = (&'a WorldCommand, bool)>,
+) {
+    let inverses = goria.read::<CommandInverses>();
+    let mut stack = uiw.write::<UndoStack>();
+    for ((command, sent_by_me), entry) in applied.zip(&inverses.0) {
+        if let WorldCommand::ResetSave = command {
+            // The undo history of the new world starts over, whoever reset it
+            *stack = UndoStack::default();
+        } else if sent_by_me {
+            stack.handle_applied(command, *entry);
+        }
+    }
+}
+
 #[cfg(not(feature = "multiplayer"))]
 mod inner {
+    use super::record_inverses;
     use crate::game_loop::{State, Timings};
     use crate::gui::windows::settings::Settings;
     use crate::uiworld::ReceivedCommands;
@@ -39,8 +62,10 @@ mod inner {
 
         step.prepare_frame(timewarp);
         while step.tick() || (has_commands && commands_once.is_some()) {
-            let t = goria.tick(sched, commands_once.take().unwrap_or_default().as_ref());
+            let applied = commands_once.take().unwrap_or_default();
+            let t = goria.tick(sched, applied.as_ref());
             timings.world_update.add_value(t.as_secs_f32());
+            record_inverses(&state.uiw, &goria, applied.iter().map(|c| (c, true)));
         }
 
         if commands_once.is_none() {
@@ -53,15 +78,18 @@ mod inner {
 
 #[cfg(feature = "multiplayer")]
 mod inner {
+    use super::record_inverses;
     use crate::game_loop::{State, Timings, VERSION};
     use crate::gui::windows::network::NetworkConnectionInfo;
     use crate::gui::windows::settings::Settings;
     use crate::uiworld::ReceivedCommands;
     use common::timestep::Timestep;
     use egregoria::engine_interaction::WorldCommands;
+    use egregoria::utils::desync::DesyncSnapshots;
     use egregoria::Egregoria;
     use networking::{
         ConnectConf, Frame, PollResult, ServerConfiguration, ServerPollResult, VirtualClientConf,
+        HASH_CHECK_PERIOD,
     };
     use std::net::ToSocketAddrs;
     use std::sync::Mutex;
@@ -95,8 +123,10 @@ mod inner {
                 let mut commands_once = Some(commands.clone());
                 step.prepare_frame(timewarp);
                 while step.tick() || (has_commands && commands_once.is_some()) {
-                    let t = goria.tick(sched, commands_once.take().unwrap_or_default().as_ref());
+                    let applied = commands_once.take().unwrap_or_default();
+                    let t = goria.tick(sched, applied.as_ref());
                     timings.world_update.add_value(t.as_secs_f32());
+                    record_inverses(&state.uiw, &goria, applied.iter().map(|c| (c, true)));
                 }
 
                 if commands_once.is_none() {
@@ -156,11 +186,35 @@ mod inner {
                     .map(|x| x.inp.clone())
                     .collect();
                 let t = goria.tick(&mut state.game_schedule, commands.as_ref());
+                record_inverses(
+                    &state.uiw,
+                    &goria,
+                    frame_commands
+                        .inputs
+                        .iter()
+                        .flat_map(|x| x.inp.iter().map(move |c| (c, x.sent_by_me))),
+                );
                 state
                     .uiw
                     .write::<Timings>()
                     .world_update
                     .add_value(t.as_secs_f32());
+
+                let tick = goria.get_tick();
+                if tick % HASH_CHECK_PERIOD == 0 {
+                    let hashes = state.uiw.write::<DesyncSnapshots>().record(&goria);
+                    match *net_state {
+                        NetworkState::Server(ref mut server) => server
+                            .get_mut()
+                            .unwrap()
+                            .register_hashes(Frame(tick), hashes),
+                        NetworkState::Client(ref mut client) => {
+                            client.get_mut().unwrap().send_hashes(Frame(tick), hashes)
+                        }
+                        NetworkState::Singleplayer(_) => {}
+                    }
+                }
+
                 merged.merge(
                     &frame_commands
                         .inputs
@@ -172,6 +226,22 @@ mod inner {
             }
             *state.uiw.write::<ReceivedCommands>() = ReceivedCommands::new(merged);
         }
+
+        let snapshots = state.uiw.read::<DesyncSnapshots>();
+        match *net_state {
+            NetworkState::Server(ref mut server) => {
+                for d in server.get_mut().unwrap().take_desyncs() {
+                    snapshots.dump(d.frame.0, &d.resources, "server");
+                }
+            }
+            NetworkState::Client(ref mut client) => {
+                for d in client.get_mut().unwrap().take_desyncs() {
+                    let side = format!("client_{}", d.client);
+                    snapshots.dump(d.frame.0, &d.resources, &side);
+                }
+            }
+            NetworkState::Singleplayer(_) => {}
+        }
     }
 
     pub(crate) fn start_server(
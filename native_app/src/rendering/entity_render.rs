@@ -48,8 +48,8 @@ impl InstancedRender {
 
             match v.kind {
                 VehicleKind::Car => self.cars.instances.push(instance),
-                VehicleKind::Truck => self.trucks.instances.push(instance),
-                _ => {}
+                // No bus model yet, buses are tinted trucks
+                VehicleKind::Truck | VehicleKind::Bus => self.trucks.instances.push(instance),
             }
         }
 
@@ -603,6 +603,8 @@ impl MapBuilders {
             let col = match lot.kind {
                 LotKind::Unassigned => common::config().lot_unassigned_col,
                 LotKind::Residential => common::config().lot_residential_col,
+                LotKind::Commercial => common::config().lot_commercial_col,
+                LotKind::Industrial => common::config().lot_industrial_col,
             };
             tess.set_color(col);
             tess.draw_filled_polygon(&lot.shape.corners, lot.height + 0.3);
@@ -49,6 +49,7 @@ impl MapRenderer {
 
         let w = goria.map().terrain.width;
         let h = goria.map().terrain.height;
+        let sea_level = goria.map().terrain.water.sea_level;
 
         defer!(log::info!("finished init of road render"));
         MapRenderer {
@@ -62,7 +63,12 @@ impl MapRenderer {
                 .collect(),
             terrain_dirt_id: 0,
             terrain: TerrainRender::new(gfx, w, h),
-            water: Water::new(gfx, (w * CHUNK_SIZE) as f32, (h * CHUNK_SIZE) as f32),
+            water: Water::new(
+                gfx,
+                (w * CHUNK_SIZE) as f32,
+                (h * CHUNK_SIZE) as f32,
+                sea_level,
+            ),
         }
     }
 
@@ -141,6 +147,7 @@ impl MapRenderer {
     pub(crate) fn terrain_update(&mut self, ctx: &mut Context, goria: &Egregoria) {
         let map = goria.map();
         let ter = &map.terrain;
+        ctx.gfx.render_params.value_mut().sea_level = ter.water.sea_level;
         if ter.dirt_id.0 == self.terrain.dirt_id {
             return;
         }
@@ -16,6 +16,7 @@ pub(crate) struct UiWorld {
 #[derive(Default)]
 pub struct SaveLoadState {
     pub please_load: Option<Replay>,
+    pub please_watch: Option<Replay>,
     pub please_save: bool,
     pub saving_status: Arc<AtomicBool>,
 }
@@ -14,7 +14,8 @@ use crate::packets::{
 };
 use crate::worldsend::WorldReceive;
 use crate::{
-    decode, decode_merged, encode, AuthentID, Frame, PhantomSendSync, PlayerInput, DEFAULT_PORT,
+    decode, decode_merged, encode, AuthentID, Desync, Frame, Hashes, PhantomSendSync, PlayerInput,
+    DEFAULT_PORT,
 };
 use common::timestep::Timestep;
 
@@ -74,6 +75,7 @@ pub struct Client<WORLD: DeserializeOwned, INPUT: Serialize + DeserializeOwned +
 
     pub step: Timestep,
     lag_compensate: u32,
+    desyncs: Vec<Desync>,
 
     _phantom: PhantomSendSync<(INPUT, WORLD)>,
 }
@@ -103,6 +105,7 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
             name: conf.name,
             lag_compensate: conf.frame_buffer_advance,
             step: Timestep::default(),
+            desyncs: vec![],
             _phantom: Default::default(),
             version: conf.version,
         })
@@ -258,6 +261,25 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
         PollResult::Wait(input)
     }
 
+    /// Sends the hashes of the client world at the given frame so the server can check for desyncs
+    pub fn send_hashes(&mut self, frame: Frame, hashes: Hashes) {
+        if matches!(
+            self.state,
+            ClientState::Connecting | ClientState::Disconnected { .. }
+        ) {
+            return;
+        }
+        self.network.send(
+            self.tcp,
+            &*encode(&ClientReliablePacket::Hashes { frame, hashes }),
+        );
+    }
+
+    /// Desyncs reported by the server since the last call
+    pub fn take_desyncs(&mut self) -> Vec<Desync> {
+        std::mem::take(&mut self.desyncs)
+    }
+
     fn message_reliable(&mut self, p: ServerReliablePacket) -> Option<()> {
         match p {
             ServerReliablePacket::WorldSend(fragment) => {
@@ -269,6 +291,19 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                     log::error!("received world but was not downloading.. weird");
                 }
             }
+            ServerReliablePacket::Desync { frame, resources } => {
+                log::error!(
+                    "{}: server reported a desync at {:?} on {:?}",
+                    self.name,
+                    frame,
+                    resources
+                );
+                self.desyncs.push(Desync {
+                    frame,
+                    client: self.name.clone(),
+                    resources,
+                });
+            }
             ServerReliablePacket::Challenge(challenge) => {
                 log::info!("{}: received challenge", self.name);
                 self.network.send(
@@ -0,0 +1,181 @@
+use crate::Frame;
+use std::collections::BTreeMap;
+
+/// Every how many frames the hashes of the world should be compared
+pub const HASH_CHECK_PERIOD: u32 = 250;
+
+/// How many frames of local hashes are kept for comparison
+const MAX_LOCAL_HASHES: usize = 8;
+
+/// How many frames ahead of the server remote hashes can be queued
+const MAX_PENDING_FRAMES: usize = 8;
+
+pub type Hashes = BTreeMap<String, u64>;
+
+#[derive(Debug, Clone)]
+pub struct Desync {
+    pub frame: Frame,
+    /// Name of the client that diverged from the server
+    pub client: String,
+    /// Name of the resources whose hashes differ
+    pub resources: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum HashCheck {
+    Match,
+    /// Name of the resources whose hashes differ
+    Diverging(Vec<String>),
+    /// The server did not reach this frame yet, it will be checked in `register`
+    Queued,
+    /// The local hashes of this frame were evicted or never computed
+    Late,
+}
+
+/// Compares the hashes sent by clients against the ones of the server.
+/// `C` identifies the client that sent the hashes.
+pub(crate) struct DesyncDetector<C> {
+    local: BTreeMap<Frame, Hashes>,
+    /// Remote hashes of frames the server did not hash yet
+    pending: BTreeMap<Frame, Vec<(C, Hashes)>>,
+    desyncs: Vec<Desync>,
+}
+
+impl<C> Default for DesyncDetector<C> {
+    fn default() -> Self {
+        Self {
+            local: Default::default(),
+            pending: Default::default(),
+            desyncs: vec![],
+        }
+    }
+}
+
+impl<C> DesyncDetector<C> {
+    /// Registers the local hashes of a frame and checks the remote hashes that were waiting for it.
+    /// Returns the clients that diverged along with the diverging resources
+    pub fn register(&mut self, frame: Frame, hashes: Hashes) -> Vec<(C, Vec<String>)> {
+        let mut diverged = vec![];
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() > frame {
+                break;
+            }
+            let waiting_frame = *entry.key();
+            let remotes = entry.remove();
+            if waiting_frame < frame {
+                log::warn!(
+                    "{} remote hashes for {:?} were never checked",
+                    remotes.len(),
+                    waiting_frame
+                );
+                continue;
+            }
+            for (client, remote) in remotes {
+                let resources = diverging(&hashes, &remote);
+                if !resources.is_empty() {
+                    diverged.push((client, resources));
+                }
+            }
+        }
+
+        self.local.insert(frame, hashes);
+        while self.local.len() > MAX_LOCAL_HASHES {
+            self.local.pop_first();
+        }
+        diverged
+    }
+
+    /// Checks the hashes a client computed for a frame, queueing them if the server is behind
+    pub fn check(&mut self, client: C, frame: Frame, remote: Hashes) -> HashCheck {
+        if let Some(local) = self.local.get(&frame) {
+            let resources = diverging(local, &remote);
+            if resources.is_empty() {
+                return HashCheck::Match;
+            }
+            return HashCheck::Diverging(resources);
+        }
+
+        let newest = self.local.keys().next_back();
+        if newest.map_or(false, |&newest| frame <= newest) {
+            return HashCheck::Late;
+        }
+
+        self.pending
+            .entry(frame)
+            .or_default()
+            .push((client, remote));
+        while self.pending.len() > MAX_PENDING_FRAMES {
+            self.pending.pop_last();
+        }
+        HashCheck::Queued
+    }
+
+    pub fn push(&mut self, desync: Desync) {
+        self.desyncs.push(desync);
+    }
+
+    pub fn take(&mut self) -> Vec<Desync> {
+        std::mem::take(&mut self.desyncs)
+    }
+}
+
+fn diverging(a: &Hashes, b: &Hashes) -> Vec<String> {
+    let mut v: Vec<String> = a
+        .iter()
+        .filter(|(name, hash)| b.get(*name) != Some(*hash))
+        .map(|(name, _)| name.clone())
+        .collect();
+    v.extend(b.keys().filter(|name| !a.contains_key(*name)).cloned());
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(world: u64, map: u64) -> Hashes {
+        [("world".to_string(), world), ("map".to_string(), map)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn check_compares_registered_frames() {
+        let mut d = DesyncDetector::default();
+        assert!(d.register(Frame(250), hashes(1, 2)).is_empty());
+
+        assert_eq!(d.check(0, Frame(250), hashes(1, 2)), HashCheck::Match);
+        assert_eq!(
+            d.check(0, Frame(250), hashes(1, 3)),
+            HashCheck::Diverging(vec!["map".to_string()])
+        );
+    }
+
+    #[test]
+    fn early_hashes_are_checked_once_the_server_catches_up() {
+        let mut d = DesyncDetector::default();
+        assert_eq!(d.check(0, Frame(250), hashes(1, 2)), HashCheck::Queued);
+        assert_eq!(d.check(1, Frame(250), hashes(5, 2)), HashCheck::Queued);
+
+        let diverged = d.register(Frame(250), hashes(1, 2));
+        assert_eq!(diverged, vec![(1, vec!["world".to_string()])]);
+        assert!(d.register(Frame(500), hashes(1, 2)).is_empty());
+    }
+
+    #[test]
+    fn evicted_frames_are_late() {
+        let mut d = DesyncDetector::default();
+        for i in 1..=MAX_LOCAL_HASHES as u32 + 1 {
+            d.register(Frame(i * HASH_CHECK_PERIOD), hashes(1, 2));
+        }
+
+        assert_eq!(
+            d.check(0, Frame(HASH_CHECK_PERIOD), hashes(1, 2)),
+            HashCheck::Late
+        );
+        assert_eq!(
+            d.check(0, Frame(2 * HASH_CHECK_PERIOD), hashes(1, 2)),
+            HashCheck::Match
+        );
+    }
+}
@@ -10,6 +10,7 @@ use std::ops::Add;
 mod authent;
 mod catchup;
 mod client;
+mod desync;
 mod packets;
 mod ring;
 mod server;
@@ -17,6 +18,7 @@ mod worldsend;
 
 use crate::client::FrameInputs;
 pub use client::{Client, ConnectConf, PollResult, ServerInput};
+pub use desync::{Desync, Hashes, HASH_CHECK_PERIOD};
 pub use server::{Server, ServerConfiguration, ServerPollResult, VirtualClientConf};
 
 pub(crate) const MAX_WORLDSEND_PACKET_SIZE: usize = 262144; //32 ko at least 1.3Mo per s at 50FPS
@@ -1,5 +1,5 @@
 use crate::authent::AuthentID;
-use crate::{Frame, MergedInputs, PlayerInput};
+use crate::{Frame, Hashes, MergedInputs, PlayerInput};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -21,6 +21,10 @@ pub(crate) enum ServerReliablePacket {
         inputs: Vec<MergedInputs>,
     },
     WorldSend(WorldDataFragment),
+    Desync {
+        frame: Frame,
+        resources: Vec<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -35,6 +39,7 @@ pub(crate) enum ClientReliablePacket {
     BeginCatchUp,
     CatchUpAck,
     WorldAck,
+    Hashes { frame: Frame, hashes: Hashes },
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -8,13 +8,17 @@ use serde::Serialize;
 use crate::authent::{Authent, AuthentID, ClientGameState};
 use crate::catchup::CatchUp;
 use crate::client::FrameInputs;
+use crate::desync::{DesyncDetector, HashCheck};
 use crate::packets::{
     AuthentResponse, ClientReliablePacket, ClientUnreliablePacket, ServerReliablePacket,
     ServerUnreliablePacket,
 };
 use crate::server::server_playout::ServerPlayoutBuffer;
 use crate::worldsend::WorldSend;
-use crate::{decode, decode_merged, encode, Frame, PhantomSendSync, PlayerInput, DEFAULT_PORT};
+use crate::{
+    decode, decode_merged, encode, Desync, Frame, Hashes, PhantomSendSync, PlayerInput,
+    DEFAULT_PORT,
+};
 use common::timestep::Timestep;
 use serde::de::DeserializeOwned;
 use std::net::SocketAddr;
@@ -55,6 +59,7 @@ pub struct Server<WORLD: Serialize, INPUT> {
     buffer: ServerPlayoutBuffer,
     catchup: CatchUp,
     worldsend: WorldSend,
+    desync: DesyncDetector<Endpoint>,
 
     step: Timestep,
     always_run: bool,
@@ -88,6 +93,7 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
             authent,
             catchup: CatchUp::default(),
             worldsend: Default::default(),
+            desync: Default::default(),
             _phantom: Default::default(),
             tcp_addr,
             udp_addr,
@@ -143,6 +149,44 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
         ServerPollResult::Wait(local_inputs)
     }
 
+    /// Registers the hashes of the server world at the given frame,
+    /// the ones sent by the clients are compared against them
+    pub fn register_hashes(&mut self, frame: Frame, hashes: Hashes) {
+        for (e, resources) in self.desync.register(frame, hashes) {
+            self.report_desync(e, frame, resources);
+        }
+    }
+
+    fn report_desync(&mut self, e: Endpoint, frame: Frame, resources: Vec<String>) {
+        let Some(c) = self.authent.get_client(e) else {
+            return;
+        };
+        let name = c.name.clone();
+        log::error!(
+            "desync detected for {} at {:?} on {:?}",
+            name,
+            frame,
+            resources
+        );
+        self.network.send(
+            e,
+            &*encode(&ServerReliablePacket::Desync {
+                frame,
+                resources: resources.clone(),
+            }),
+        );
+        self.desync.push(Desync {
+            frame,
+            client: name,
+            resources,
+        });
+    }
+
+    /// Desyncs detected since the last call
+    pub fn take_desyncs(&mut self) -> Vec<Desync> {
+        self.desync.take()
+    }
+
     fn send_merged_inputs(&mut self) {
         let n_playing = self.authent.iter_playing().count() + self.v_client.is_some() as usize;
 
@@ -280,6 +324,16 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
                 log::info!("client {} world rcv acked", c.name);
                 self.worldsend.ack(c);
             }
+            ClientReliablePacket::Hashes { frame, hashes } => {
+                let c = self.authent.get_client(e)?;
+                match self.desync.check(e, frame, hashes) {
+                    HashCheck::Match | HashCheck::Queued => {}
+                    HashCheck::Late => {
+                        log::warn!("{}: hashes for {:?} arrived too late", c.name, frame);
+                    }
+                    HashCheck::Diverging(resources) => self.report_desync(e, frame, resources),
+                }
+            }
         }
         Some(())
     }
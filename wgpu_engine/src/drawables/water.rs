@@ -9,24 +9,24 @@ pub struct Water {
 }
 
 impl Water {
-    pub fn new(gfx: &mut GfxContext, w: f32, h: f32) -> Self {
+    pub fn new(gfx: &mut GfxContext, w: f32, h: f32, level: f32) -> Self {
         let mut mb = MeshBuilder::new(gfx.palette());
 
         mb.vertices.extend_from_slice(&[
             MeshVertex {
-                position: [0.0, 0.0, -10.0],
+                position: [0.0, 0.0, level],
                 ..Default::default()
             },
             MeshVertex {
-                position: [w, 0.0, -10.0],
+                position: [w, 0.0, level],
                 ..Default::default()
             },
             MeshVertex {
-                position: [w, h, -10.0],
+                position: [w, h, level],
                 ..Default::default()
             },
             MeshVertex {
-                position: [0.0, h, -10.0],
+                position: [0.0, h, level],
                 ..Default::default()
             },
         ]);
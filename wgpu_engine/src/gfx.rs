@@ -96,7 +96,8 @@ pub struct RenderParams {
     pub shadow_mapping_enabled: i32,
     pub realistic_sky: i32,
     pub grid_enabled: i32,
-    pub _pad5: [f32; 3],
+    pub sea_level: f32,
+    pub _pad5: [f32; 2],
 }
 
 impl Default for RenderParams {
@@ -123,6 +124,7 @@ impl Default for RenderParams {
             shadow_mapping_enabled: 1,
             realistic_sky: 1,
             grid_enabled: 1,
+            sea_level: -10.0,
             _pad: 0.0,
             _pad2: 0.0,
             _pad4: 0.0,